@@ -19,10 +19,19 @@ use esp_idf_svc::{
     wifi::{AsyncWifi, AuthMethod, ClientConfiguration, Configuration, EspWifi},
 };
 use log::{error, info};
-use waveshare_epd::epd_12in48b::{
-    Epd, EPD_HEIGHT, EPD_WIDTH, HALF_HEIGHT, LEFT_WIDTH, RIGHT_WIDTH,
+use std::time::SystemTime;
+use waveshare_epd::{
+    clean::{Policy, State},
+    epd_12in48b::{
+        self, quadrant_bytes, Plane, PlaneWriter, Revision, EPD_HEIGHT, EPD_WIDTH, HALF_HEIGHT,
+        LEFT_WIDTH, RIGHT_WIDTH,
+    },
+    esp::EspInterface,
+    interface::Controller,
 };
 
+type Epd<'d> = epd_12in48b::Epd<EspInterface<'d>>;
+
 const NAMESPACE: &'static str = "espiro_frame";
 const REFRESHES: &'static str = "refreshes";
 
@@ -39,6 +48,13 @@ struct Config {
     mate_endpoint: &'static str,
     #[default(6)]
     refreshes_per_day: u64,
+    /// Whether the panel is a V2, as printed on its back.
+    #[default(false)]
+    panel_v2: bool,
+    /// Whether the daily clear cycles through all inks to clear ghosting,
+    /// which takes four refreshes instead of one.
+    #[default(false)]
+    deep_clean: bool,
 }
 impl Config {
     fn wifi(&self) -> Result<ClientConfiguration> {
@@ -88,9 +104,9 @@ fn refresh() -> Result<()> {
     let peripherals = Peripherals::take()?;
 
     #[cfg(esp32)]
-    let mut epd = Epd::waveshare(peripherals.spi3, peripherals.pins)?;
+    let epd = Epd::waveshare(peripherals.spi3, peripherals.pins)?;
     #[cfg(esp32c3)]
-    let mut epd = Epd::custom(
+    let epd = Epd::custom(
         peripherals.spi2,
         peripherals.pins.gpio4,
         peripherals.pins.gpio3,
@@ -107,12 +123,22 @@ fn refresh() -> Result<()> {
         peripherals.pins.gpio20,
         peripherals.pins.gpio21,
     )?;
+    let mut epd = epd.with_revision(if CONFIG.panel_v2 {
+        Revision::V2
+    } else {
+        Revision::V1
+    });
 
     daily_clear(&mut epd)?;
 
     epd.init()?;
-    let display_result =
-        fetch_and_display(peripherals.modem, &mut epd).and_then(|_| Ok(block_on(epd.turn_on())?));
+    let display_result = fetch_and_display(peripherals.modem, &mut epd).and_then(|_| {
+        let report = block_on(epd.turn_on())?;
+        for timings in &report.timings {
+            info!("{timings:?}");
+        }
+        Ok(())
+    });
     epd.sleep()?;
     display_result
 }
@@ -120,15 +146,25 @@ fn refresh() -> Result<()> {
 fn daily_clear(epd: &mut Epd) -> Result<()> {
     let partition: EspNvsPartition<NvsDefault> = EspDefaultNvsPartition::take()?;
     let nvs = EspNvs::new(partition, NAMESPACE, true)?;
-    let refreshes = nvs.get_u64(REFRESHES)?.unwrap_or_default();
-    info!("Refresh {}", refreshes);
-    if refreshes == 0 {
+    let mut state = State {
+        refreshes: nvs.get_u64(REFRESHES)?.unwrap_or_default(),
+        ..Default::default()
+    };
+    info!("Refresh {}", state.refreshes);
+    let policy = Policy::new().with_refreshes(CONFIG.refreshes_per_day);
+    if policy.is_due(&state, SystemTime::now()) {
         epd.init()?;
-        epd.clear()?;
-        block_on(epd.turn_on())?;
+        if CONFIG.deep_clean {
+            block_on(epd.deep_clean())?;
+        } else {
+            epd.clear()?;
+            block_on(epd.turn_on())?;
+        }
         epd.sleep()?;
+        state.record_clean(SystemTime::now());
     }
-    nvs.set_u64(REFRESHES, (refreshes + 1) % CONFIG.refreshes_per_day)?;
+    state.record_refresh();
+    nvs.set_u64(REFRESHES, state.refreshes)?;
     Ok(())
 }
 
@@ -204,54 +240,35 @@ fn display(
     epd: &mut Epd,
     id: &str,
 ) -> Result<()> {
-    {
-        let mut buf = [0; RIGHT_WIDTH * HALF_HEIGHT / 8];
-
-        info!("s1");
-        let uri = quadrant_uri(
-            endpoint,
-            &id,
-            LEFT_WIDTH,
-            HALF_HEIGHT,
-            RIGHT_WIDTH,
-            HALF_HEIGHT,
-        );
-        let mut response = fetch_quadrant(client, &uri, 2 * RIGHT_WIDTH * HALF_HEIGHT / 8)?;
-        response.read_exact(&mut buf)?;
-        epd.s1_display_white(&buf)?;
-        response.read_exact(&mut buf)?;
-        epd.s1_display_red(&buf)?;
-
-        info!("m2");
-        let uri = quadrant_uri(endpoint, &id, LEFT_WIDTH, 0, RIGHT_WIDTH, HALF_HEIGHT);
-        let mut response = fetch_quadrant(client, &uri, 2 * RIGHT_WIDTH * HALF_HEIGHT / 8)?;
-        response.read_exact(&mut buf)?;
-        epd.m2_display_white(&buf)?;
-        response.read_exact(&mut buf)?;
-        epd.m2_display_red(&buf)?;
+    for (controller, x, y, width) in [
+        (Controller::S1, LEFT_WIDTH, HALF_HEIGHT, RIGHT_WIDTH),
+        (Controller::M2, LEFT_WIDTH, 0, RIGHT_WIDTH),
+        (Controller::M1, 0, HALF_HEIGHT, LEFT_WIDTH),
+        (Controller::S2, 0, 0, LEFT_WIDTH),
+    ] {
+        info!("{:?}", controller);
+        let uri = quadrant_uri(endpoint, &id, x, y, width, HALF_HEIGHT);
+        let mut response = fetch_quadrant(client, &uri, 2 * quadrant_bytes(controller))?;
+        for plane in [Plane::White, Plane::Red] {
+            stream(&mut response, epd.begin(controller, plane)?)?;
+        }
     }
+    Ok(())
+}
 
-    {
-        let mut buf = [0; LEFT_WIDTH * HALF_HEIGHT / 8];
-
-        info!("m1");
-        let uri = quadrant_uri(endpoint, &id, 0, HALF_HEIGHT, LEFT_WIDTH, HALF_HEIGHT);
-        let mut response = fetch_quadrant(client, &uri, 2 * LEFT_WIDTH * HALF_HEIGHT / 8)?;
-        response.read_exact(&mut buf)?;
-        epd.m1_display_white(&buf)?;
-        response.read_exact(&mut buf)?;
-        epd.m1_display_red(&buf)?;
-
-        info!("s2");
-        let uri = quadrant_uri(endpoint, &id, 0, 0, LEFT_WIDTH, HALF_HEIGHT);
-        let mut response = fetch_quadrant(client, &uri, 2 * LEFT_WIDTH * HALF_HEIGHT / 8)?;
-        response.read_exact(&mut buf)?;
-        epd.s2_display_white(&buf)?;
-        response.read_exact(&mut buf)?;
-        epd.s2_display_red(&buf)?;
+/// Copy a plane from the response to the panel, in small chunks.
+fn stream(
+    response: &mut Response<&mut EspHttpConnection>,
+    mut writer: PlaneWriter<EspInterface>,
+) -> Result<()> {
+    const CHUNK: usize = 1024;
+    let mut buf = [0; CHUNK];
+    while writer.remaining() > 0 {
+        let chunk = &mut buf[..writer.remaining().min(CHUNK)];
+        response.read_exact(chunk)?;
+        writer.write(chunk)?;
     }
-
-    Ok(())
+    Ok(writer.end()?)
 }
 
 fn quadrant_uri(
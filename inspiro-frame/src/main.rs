@@ -2,8 +2,18 @@ mod api;
 
 use clap::Parser;
 use image::imageops;
+use log::info;
 use simplelog::{LevelFilter::Info, SimpleLogger};
-use waveshare_epd::epd_2in7b as epd;
+use std::{path::PathBuf, time::SystemTime};
+use waveshare_epd::{
+    clean::Policy,
+    config::Config,
+    epd_2in7, epd_2in7b as epd,
+    guard::{FileStore, Record, Store},
+    power::Panel,
+    sim::Simulator,
+    Display,
+};
 
 #[derive(Parser, Debug)]
 struct Opt {
@@ -16,6 +26,42 @@ struct Opt {
     /// Disable dithering
     #[arg(long, conflicts_with = "dither")]
     no_dither: bool,
+    /// Render to a PNG file instead of the e-Paper
+    #[arg(long, value_name = "PNG")]
+    simulate: Option<PathBuf>,
+    /// Wiring of the e-Paper, as a TOML file. Waveshare's HAT by default
+    #[arg(long, value_name = "TOML", conflicts_with = "simulate")]
+    config: Option<PathBuf>,
+    /// Render in 4 levels of gray, on the black and white e-Paper
+    #[arg(long, conflicts_with = "simulate")]
+    gray: bool,
+    /// Deep clean the e-Paper every this many refreshes, to clear ghosting
+    #[arg(long, value_name = "REFRESHES", requires = "state")]
+    clean_every: Option<u64>,
+    /// Where to keep the refresh count between runs, as a TOML file
+    #[arg(long, value_name = "TOML")]
+    state: Option<PathBuf>,
+}
+
+/// Deep cleans due by a policy, whose state persists in a file.
+struct Cleaning {
+    policy: Policy,
+    record: Record,
+    store: FileStore,
+}
+impl Cleaning {
+    fn is_due(&self) -> bool {
+        self.policy.is_due(&self.record.clean, SystemTime::now())
+    }
+
+    /// Count a refresh, after a deep clean if `cleaned`, and save the record.
+    fn record(&mut self, cleaned: bool) -> anyhow::Result<()> {
+        if cleaned {
+            self.record.clean.record_clean(SystemTime::now());
+        }
+        self.record.clean.record_refresh();
+        Ok(self.store.save(&self.record)?)
+    }
 }
 
 pub fn main() -> anyhow::Result<()> {
@@ -25,8 +71,28 @@ pub fn main() -> anyhow::Result<()> {
         url,
         dither,
         no_dither,
+        simulate,
+        config,
+        gray,
+        clean_every,
+        state,
     } = Parser::parse();
     let dither = dither || !no_dither;
+    let mut cleaning = match state {
+        Some(path) => {
+            let policy = match clean_every {
+                Some(refreshes) => Policy::new().with_refreshes(refreshes),
+                None => Policy::new(),
+            };
+            let mut store = FileStore::new(path);
+            Some(Cleaning {
+                policy,
+                record: store.load()?,
+                store,
+            })
+        }
+        None => None,
+    };
 
     let image = match url {
         Some(url) => api::fetch_image(&url),
@@ -35,14 +101,59 @@ pub fn main() -> anyhow::Result<()> {
     .into_rgb8();
     let image = imageops::thumbnail(&image, epd::EPD_HEIGHT as u32, epd::EPD_WIDTH as u32);
 
+    let config = || match &config {
+        Some(path) => Config::load(path),
+        None => Ok(Config::default()),
+    };
+
+    if gray {
+        let frame = epd_2in7::pack_buffer(&rgb2bwr::to_gray4(image, dither))?;
+        return display(
+            epd_2in7::Epd::with_config(&config()?)?,
+            |epd| epd.write(&frame),
+            cleaning.as_mut(),
+        );
+    }
+
     let (black, red) = rgb2bwr::to_bwr_split(image, dither);
-    let black = epd::pack_buffer(&black).unwrap();
-    let red = epd::pack_buffer(&red).unwrap();
+    let black = epd::pack_buffer(&black)?;
+    let red = epd::pack_buffer(&red)?;
 
-    let mut epd = epd::Epd::new()?;
-    epd.init()?;
-    epd.display(black.iter().copied(), red.iter().copied())?;
-    epd.sleep()?;
+    match simulate {
+        Some(path) => display(
+            epd::Epd::with_interface(Simulator::epd_2in7b().with_output(path)),
+            |epd| epd.write_frame(&black, &red),
+            cleaning.as_mut(),
+        ),
+        None => display(
+            epd::Epd::with_config(&config()?)?,
+            |epd| epd.write_frame(&black, &red),
+            cleaning.as_mut(),
+        ),
+    }
+}
 
+/// Show the frame `write` writes, deep cleaning first if due. The panel is put
+/// to sleep even if that fails.
+fn display<D: Display>(
+    epd: D,
+    write: impl FnOnce(&mut D) -> waveshare_epd::Result<()>,
+    cleaning: Option<&mut Cleaning>,
+) -> anyhow::Result<()> {
+    let mut panel = Panel::new(epd).init()?;
+    let clean = cleaning.as_deref().is_some_and(Cleaning::is_due);
+    if clean {
+        info!("Deep clean");
+        panel.deep_clean()?;
+    }
+    write(panel.display_mut())?;
+    let report = panel.refresh()?;
+    for timings in &report.timings {
+        info!("{timings:?}");
+    }
+    panel.sleep()?;
+    if let Some(cleaning) = cleaning {
+        cleaning.record(clean)?;
+    }
     Ok(())
 }
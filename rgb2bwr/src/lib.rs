@@ -1,6 +1,6 @@
 // mod color;
 
-use image::{imageops::ColorMap, Pixel, Rgb, RgbImage};
+use image::{imageops::ColorMap, Luma, Pixel, Rgb, RgbImage};
 use imageproc::{
     contrast::otsu_level,
     map::{blue_channel, green_channel, map_colors, red_channel},
@@ -53,6 +53,22 @@ pub fn to_bwr_split(
     )
 }
 
+/// Convert an image to 4 levels of gray.
+///
+/// Although the result uses 8bpp, only four values (0, 85, 170 and 255) are
+/// used.
+pub fn to_gray4(image: image::RgbImage, dither: bool) -> image::GrayImage {
+    let mut image = image::imageops::grayscale(&image);
+    if dither {
+        image::imageops::dither(&mut image, &Gray4)
+    } else {
+        for pixel in image.pixels_mut() {
+            Gray4.map_color(pixel);
+        }
+    }
+    image
+}
+
 /// Calculate Hue, Saturation and Value of the given color.
 ///
 /// All values are between 0.0 and 1.0: Hue is in turns, Saturation and Value
@@ -178,6 +194,26 @@ impl BwrHeuristic {
 }
 bwr_color_map!(BwrHeuristic);
 
+/// Map gray pixels to the nearest of 4 evenly spaced levels.
+#[derive(Debug)]
+struct Gray4;
+impl ColorMap for Gray4 {
+    type Color = Luma<u8>;
+
+    fn index_of(&self, color: &Self::Color) -> usize {
+        (usize::from(color[0]) + 42) / 85
+    }
+    fn lookup(&self, index: usize) -> Option<Self::Color> {
+        (index < 4).then(|| Luma([index as u8 * 85]))
+    }
+    fn has_lookup(&self) -> bool {
+        true
+    }
+    fn map_color(&self, color: &mut Self::Color) {
+        *color = self.lookup(self.index_of(color)).unwrap();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use float_eq::assert_float_eq;
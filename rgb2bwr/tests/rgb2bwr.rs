@@ -15,3 +15,25 @@ fn black_and_white() -> image::ImageResult<()> {
     assert!(result.pixels().filter(|p| p.0 != [255, 0, 0]).count() > result.pixels().len() / 10);
     Ok(())
 }
+
+/// Ensure photos keep their midtones when quantized to 4 levels of gray.
+#[test]
+fn gray4() {
+    let image = image::io::Reader::with_format(
+        std::io::Cursor::new(include_bytes!("3Ee3DwnMJ0.jpg")),
+        image::ImageFormat::Jpeg,
+    )
+    .decode()
+    .unwrap()
+    .into_rgb8();
+    for dither in [false, true] {
+        let result = rgb2bwr::to_gray4(image.clone(), dither);
+        assert_eq!(result.dimensions(), image.dimensions());
+        let mut counts = [0; 4];
+        for pixel in result.pixels() {
+            assert_eq!(pixel.0[0] % 85, 0, "{pixel:?}");
+            counts[usize::from(pixel.0[0] / 85)] += 1;
+        }
+        assert!(counts.iter().all(|&count| count > 0), "{counts:?}");
+    }
+}
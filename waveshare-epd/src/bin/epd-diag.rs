@@ -0,0 +1,270 @@
+//! Show test patterns and time each phase of a refresh, to bring up panels.
+//!
+//! Refreshes are reported in the driver's own phases. Simulated panels are
+//! timed in the simulator's virtual time instead, unless run in real time.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use waveshare_epd::{
+    epd_12in48b, epd_2in7b, pattern::Pattern, sim::Simulator, Display, RefreshReport, Result,
+};
+
+#[derive(Parser, Debug)]
+struct Opt {
+    /// Panel to drive
+    #[arg(long, default_value = "epd-2in7b")]
+    panel: Panel,
+    /// Render to a PNG file instead of the e-Paper
+    #[arg(long, value_name = "PNG")]
+    simulate: Option<PathBuf>,
+    /// Take as long as the e-Paper would, when simulating
+    #[arg(long, requires = "simulate")]
+    realtime: bool,
+    /// Wiring of the e-Paper, as a TOML file. Waveshare's HAT by default
+    #[arg(long, value_name = "TOML", conflicts_with = "simulate")]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show a test pattern
+    Pattern {
+        #[arg()]
+        pattern: PatternName,
+        /// Side of the squares, or spacing of the grid, in pixels
+        #[arg(long, default_value_t = 16)]
+        size: usize,
+    },
+    /// Turn the e-Paper white
+    Clear,
+    /// Cycle the e-Paper through its inks, to clear ghosting
+    DeepClean,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Panel {
+    #[value(name = "epd-2in7b")]
+    Epd2in7b,
+    #[value(name = "epd-12in48b")]
+    Epd12in48b,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum PatternName {
+    Checkerboard,
+    Quadrants,
+    ColorBars,
+    Ramp,
+    Grid,
+}
+
+fn main() -> Result<()> {
+    let opt = Opt::parse();
+    match (opt.panel, &opt.simulate) {
+        (Panel::Epd2in7b, Some(path)) => run(
+            epd_2in7b::Epd::with_interface(simulator(Simulator::epd_2in7b(), path, opt.realtime)),
+            opt.panel,
+            opt.command,
+            clock(opt.realtime, |epd| epd.interface().elapsed()),
+        ),
+        (Panel::Epd12in48b, Some(path)) => run(
+            epd_12in48b::Epd::with_interface(simulator(
+                Simulator::epd_12in48b(),
+                path,
+                opt.realtime,
+            )),
+            opt.panel,
+            opt.command,
+            clock(opt.realtime, |epd| epd.interface().elapsed()),
+        ),
+        (Panel::Epd2in7b, None) => hardware(opt.config, opt.command),
+        (Panel::Epd12in48b, None) => Err(waveshare_epd::Error::Unsupported(
+            "driving the 12.48\" e-Paper from Linux",
+        )),
+    }
+}
+
+fn simulator(simulator: Simulator, path: &PathBuf, realtime: bool) -> Simulator {
+    simulator.with_output(path).with_realtime(realtime)
+}
+
+/// The clock of a simulated panel: its virtual time, unless it runs in real
+/// time like the driver's timings.
+fn clock<D: 'static>(realtime: bool, elapsed: fn(&D) -> Duration) -> Clock<D> {
+    if realtime {
+        Clock::wall()
+    } else {
+        Clock {
+            now: Box::new(elapsed),
+            driver: false,
+        }
+    }
+}
+
+#[cfg(feature = "rpi")]
+fn hardware(config: Option<PathBuf>, command: Command) -> Result<()> {
+    use waveshare_epd::config::Config;
+
+    let config = match config {
+        #[cfg(feature = "config")]
+        Some(path) => Config::load(path)?,
+        #[cfg(not(feature = "config"))]
+        Some(_) => {
+            return Err(waveshare_epd::Error::Unsupported(
+                "loading a configuration without the config feature",
+            ))
+        }
+        None => Config::default(),
+    };
+    run(
+        epd_2in7b::Epd::with_config(&config)?,
+        Panel::Epd2in7b,
+        command,
+        Clock::wall(),
+    )
+}
+
+#[cfg(not(feature = "rpi"))]
+fn hardware(_config: Option<PathBuf>, _command: Command) -> Result<()> {
+    Err(waveshare_epd::Error::Unsupported(
+        "driving the e-Paper without the rpi feature",
+    ))
+}
+
+fn run<D: Display>(mut epd: D, panel: Panel, command: Command, clock: Clock<D>) -> Result<()> {
+    let mut phases = Phases::new(&clock, &epd);
+    phases.time("init", &mut epd, |epd| epd.init())?;
+    match command {
+        Command::Pattern { pattern, size } => {
+            let (width, height) = (epd.width(), epd.height());
+            let pattern = match (pattern, panel) {
+                (PatternName::Checkerboard, _) => Pattern::Checkerboard(size),
+                (PatternName::Quadrants, Panel::Epd12in48b) => Pattern::epd_12in48b_quadrants(),
+                (PatternName::Quadrants, _) => Pattern::quadrants(width, height),
+                (PatternName::ColorBars, _) => Pattern::ColorBars,
+                (PatternName::Ramp, _) => Pattern::Ramp,
+                (PatternName::Grid, _) => Pattern::Grid(size),
+            };
+            let frame = pattern.render(width, height, epd.inks());
+            if clock.driver {
+                // The driver times sending the frame as part of the refresh.
+                epd.write_frame(frame.black(), frame.red())?;
+                let report = epd.refresh()?;
+                phases.report(&report);
+                print_report(&report);
+            } else {
+                phases.time("write", &mut epd, |epd| {
+                    epd.write_frame(frame.black(), frame.red())
+                })?;
+                let report = phases.time("refresh", &mut epd, |epd| epd.refresh())?;
+                print_report(&report);
+            }
+        }
+        Command::Clear => phases.time("clear", &mut epd, |epd| epd.clear())?,
+        Command::DeepClean => phases.time("deep clean", &mut epd, |epd| epd.deep_clean())?,
+    }
+    phases.time("sleep", &mut epd, |epd| epd.sleep())?;
+    phases.print(&epd);
+    Ok(())
+}
+
+/// Temperatures and LUT a refresh ran with.
+fn print_report(report: &RefreshReport) {
+    for (controller, celsius) in &report.temperatures {
+        match controller {
+            Some(controller) => println!("{controller:?} at {celsius} °C"),
+            None => println!("at {celsius} °C"),
+        }
+    }
+    if let Some(lut) = &report.lut {
+        println!("with the {lut} LUT");
+    }
+}
+
+/// Where a run reads the time from.
+struct Clock<D> {
+    now: Box<dyn Fn(&D) -> Duration>,
+    /// Whether the driver's timings, taken on the wall clock, are in the same
+    /// time.
+    driver: bool,
+}
+impl<D> Clock<D> {
+    fn wall() -> Self {
+        let start = Instant::now();
+        Self {
+            now: Box::new(move |_| start.elapsed()),
+            driver: true,
+        }
+    }
+}
+
+/// How long each phase took, in order.
+struct Phases<'a, D> {
+    clock: &'a Clock<D>,
+    start: Duration,
+    phases: Vec<(String, Duration)>,
+}
+impl<'a, D> Phases<'a, D> {
+    fn new(clock: &'a Clock<D>, epd: &D) -> Self {
+        Self {
+            clock,
+            start: (clock.now)(epd),
+            phases: Vec::new(),
+        }
+    }
+
+    fn time<T>(
+        &mut self,
+        name: &str,
+        epd: &mut D,
+        phase: impl FnOnce(&mut D) -> Result<T>,
+    ) -> Result<T> {
+        let start = (self.clock.now)(epd);
+        let result = phase(epd);
+        self.phases
+            .push((name.into(), (self.clock.now)(epd).saturating_sub(start)));
+        result
+    }
+
+    /// The phases of a refresh, as the driver timed them on each controller.
+    fn report(&mut self, report: &RefreshReport) {
+        for timings in &report.timings {
+            let prefix = timings
+                .controller
+                .map(|controller| format!("{controller:?} "))
+                .unwrap_or_default();
+            let phases = [
+                ("transfer", Some(timings.transfer)),
+                ("power on", timings.power_on),
+                ("refresh", Some(timings.refresh)),
+                ("power off", timings.power_off),
+            ];
+            for (name, duration) in phases {
+                if let Some(duration) = duration {
+                    self.phases.push((format!("{prefix}{name}"), duration));
+                }
+            }
+        }
+    }
+
+    /// Each phase, and the whole run. Controllers refresh at the same time, so
+    /// their phases may add up to more than the run took.
+    fn print(&self, epd: &D) {
+        let width = self
+            .phases
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        for (name, duration) in &self.phases {
+            println!("{name:width$}  {duration:>10.3?}");
+        }
+        let total = (self.clock.now)(epd).saturating_sub(self.start);
+        println!("{:width$}  {total:>10.3?}", "total");
+    }
+}
@@ -0,0 +1,147 @@
+//! Replay and compare traces recorded by [`waveshare_epd::trace::Recorder`].
+//!
+//! Traces of cascade panels, the 12.48", can only be replayed into the
+//! simulator: the Raspberry Pi and Linux backends drive a single controller.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{path::PathBuf, process::ExitCode, time::Duration};
+use waveshare_epd::{config::Config, sim::Simulator, trace, trace::Trace, Error, Result};
+
+/// How long to wait for each busy wait of a trace.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Parser, Debug)]
+struct Opt {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Play a trace into the simulator, or the e-Paper. Traces of the 12.48"
+    /// can only be simulated
+    Replay {
+        #[arg()]
+        trace: PathBuf,
+        /// Render to a PNG file instead of the e-Paper
+        #[arg(long, value_name = "PNG")]
+        simulate: Option<PathBuf>,
+        /// Panel to simulate. Guessed from the trace by default
+        #[arg(long, requires = "simulate")]
+        panel: Option<Panel>,
+        /// Wiring of the e-Paper, as a TOML file. Waveshare's HAT by default
+        #[arg(long, value_name = "TOML", conflicts_with = "simulate")]
+        config: Option<PathBuf>,
+        /// Backend driving the e-Paper
+        #[arg(long, default_value = "rpi", conflicts_with = "simulate")]
+        backend: Backend,
+    },
+    /// Compare two traces, ignoring timings, busy levels and the bytes read
+    Diff {
+        #[arg()]
+        left: PathBuf,
+        #[arg()]
+        right: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Backend {
+    /// Raspberry Pi, through rppal
+    Rpi,
+    /// Any Linux board, through spidev and the GPIO character device
+    Linux,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Panel {
+    #[value(name = "epd-2in7b")]
+    Epd2in7b,
+    #[value(name = "epd-12in48b")]
+    Epd12in48b,
+}
+
+fn main() -> Result<ExitCode> {
+    match Opt::parse().command {
+        Command::Replay {
+            trace,
+            simulate,
+            panel,
+            config,
+            backend,
+        } => {
+            let trace = Trace::load(trace)?;
+            match simulate {
+                Some(path) => simulate_replay(&trace, panel, path)?,
+                None => hardware_replay(&trace, config, backend)?,
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Diff { left, right } => {
+            let (left, right) = (Trace::load(left)?, Trace::load(right)?);
+            let differences = trace::diff(&left, &right);
+            for difference in &differences {
+                let describe = |entry: Option<&trace::Entry>| match entry {
+                    Some(entry) => entry.to_string(),
+                    None => "(nothing)".to_string(),
+                };
+                println!("@{}", difference.index);
+                println!("- {}", describe(difference.left));
+                println!("+ {}", describe(difference.right));
+            }
+            Ok(if differences.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+    }
+}
+
+fn simulate_replay(trace: &Trace, panel: Option<Panel>, path: PathBuf) -> Result<()> {
+    let panel = panel.unwrap_or(if trace.is_cascade() {
+        Panel::Epd12in48b
+    } else {
+        Panel::Epd2in7b
+    });
+    match panel {
+        Panel::Epd2in7b => trace.replay(&mut Simulator::epd_2in7b().with_output(path), TIMEOUT),
+        Panel::Epd12in48b => {
+            trace.replay_cascade(&mut Simulator::epd_12in48b().with_output(path), TIMEOUT)
+        }
+    }
+}
+
+#[cfg_attr(not(any(feature = "rpi", feature = "linux")), allow(unused_variables))]
+fn hardware_replay(trace: &Trace, config: Option<PathBuf>, backend: Backend) -> Result<()> {
+    if trace.is_cascade() {
+        return Err(Error::Unsupported("replaying a cascade trace"));
+    }
+    let config = match config {
+        #[cfg(feature = "config")]
+        Some(path) => Config::load(path)?,
+        #[cfg(not(feature = "config"))]
+        Some(_) => {
+            return Err(Error::Unsupported(
+                "loading a configuration without the config feature",
+            ))
+        }
+        None => Config::default(),
+    };
+    match backend {
+        #[cfg(feature = "rpi")]
+        Backend::Rpi => trace.replay(
+            &mut waveshare_epd::rpi::RpiInterface::with_config(&config)?,
+            TIMEOUT,
+        ),
+        #[cfg(not(feature = "rpi"))]
+        Backend::Rpi => Err(Error::Unsupported("replaying without the rpi feature")),
+        #[cfg(feature = "linux")]
+        Backend::Linux => trace.replay(
+            &mut waveshare_epd::linux::LinuxInterface::with_config(&config)?,
+            TIMEOUT,
+        ),
+        #[cfg(not(feature = "linux"))]
+        Backend::Linux => Err(Error::Unsupported("replaying without the linux feature")),
+    }
+}
@@ -0,0 +1,310 @@
+//! Several panels side by side, driven as one large [`Display`].
+//!
+//! Each [`Tile`] shows part of the canvas, at an offset and with a rotation
+//! of its own. Frames are written to the canvas as a whole, for instance from
+//! a [`Framebuffer`] of its size, and split across the panels.
+
+use crate::{
+    framebuffer::Framebuffer, packing::Rotation, Display, Error, Health, Ink, RefreshReport, Result,
+};
+use std::mem;
+
+const BLACK_WHITE: &[Ink] = &[Ink::Black, Ink::White];
+const BLACK_WHITE_RED: &[Ink] = &[Ink::Black, Ink::White, Ink::Red];
+
+/// A panel of a [`Canvas`].
+pub struct Tile<D> {
+    pub display: D,
+    /// Position of the top left corner of the panel's drawing on the canvas.
+    pub x: usize,
+    pub y: usize,
+    /// Clockwise rotation of the drawing relative to the panel.
+    pub rotation: Rotation,
+}
+impl<D: Display> Tile<D> {
+    /// Width and height of the part of the canvas the panel shows.
+    pub fn dimensions(&self) -> (usize, usize) {
+        let (width, height) = (self.display.width(), self.display.height());
+        match self.rotation {
+            Rotation::Rotate0 | Rotation::Rotate180 => (width, height),
+            Rotation::Rotate90 | Rotation::Rotate270 => (height, width),
+        }
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        let ((width, height), (other_width, other_height)) =
+            (self.dimensions(), other.dimensions());
+        self.x < other.x + other_width
+            && other.x < self.x + width
+            && self.y < other.y + other_height
+            && other.y < self.y + height
+    }
+}
+
+/// When the panels of a [`Canvas`] refresh.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Schedule {
+    /// All at once, so the whole canvas changes in a single refresh time.
+    #[default]
+    Together,
+    /// One after the other, so that only one panel at a time draws the
+    /// current of a refresh.
+    Sequence,
+}
+
+/// Panels laid out as one large display.
+///
+/// Its size is the smallest that holds all the tiles, which may not overlap,
+/// and parts of it no tile covers are ignored. It can show red only if all of
+/// the panels can, and otherwise shows red as white on every panel.
+///
+/// Refreshes report the temperatures and timings of each panel, in the order
+/// of the tiles.
+pub struct Canvas<D> {
+    tiles: Vec<Tile<D>>,
+    schedule: Schedule,
+    /// Reports of the panels already refreshed by
+    /// [`start_refresh`](Display::start_refresh).
+    refreshed: Vec<RefreshReport>,
+}
+impl<D> Default for Canvas<D> {
+    fn default() -> Self {
+        Self {
+            tiles: Vec::new(),
+            schedule: Schedule::default(),
+            refreshed: Vec::new(),
+        }
+    }
+}
+impl<D: Display> Canvas<D> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Show the part of the canvas from `(x, y)` on `display`, rotated by
+    /// `rotation`. Fails if that part overlaps another tile's.
+    pub fn with_tile(mut self, display: D, x: usize, y: usize, rotation: Rotation) -> Result<Self> {
+        let tile = Tile {
+            display,
+            x,
+            y,
+            rotation,
+        };
+        if let Some(other) = self.tiles.iter().position(|other| other.overlaps(&tile)) {
+            return Err(Error::OverlappingTiles {
+                tile: self.tiles.len(),
+                other,
+            });
+        }
+        self.tiles.push(tile);
+        Ok(self)
+    }
+
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    pub fn tiles(&self) -> &[Tile<D>] {
+        &self.tiles
+    }
+
+    pub fn tiles_mut(&mut self) -> &mut [Tile<D>] {
+        &mut self.tiles
+    }
+
+    pub fn into_tiles(self) -> Vec<Tile<D>> {
+        self.tiles
+    }
+
+    /// A white frame the size of the canvas, to draw on.
+    pub fn framebuffer(&self) -> Framebuffer {
+        Framebuffer::new(self.width(), self.height())
+    }
+
+    /// Write `frame` to the panels and show it.
+    pub fn display_framebuffer(&mut self, frame: &Framebuffer) -> Result<RefreshReport> {
+        if frame.dimensions() != (self.width(), self.height()) {
+            return Err(Error::InvalidDimensions {
+                expected: (self.width(), self.height()),
+                actual: frame.dimensions(),
+            });
+        }
+        if frame.rotation() == Rotation::Rotate0 {
+            self.write_frame(frame.black(), frame.red())?;
+        } else {
+            // Planes of rotated frames are laid out for the rotated panel.
+            let mut native = self.framebuffer();
+            for y in 0..self.height() {
+                for x in 0..self.width() {
+                    if let Some(color) = frame.pixel(x, y) {
+                        native.set_pixel(x, y, color);
+                    }
+                }
+            }
+            self.write_frame(native.black(), native.red())?;
+        }
+        self.refresh()
+    }
+}
+
+impl<D: Display> Display for Canvas<D> {
+    fn width(&self) -> usize {
+        (self.tiles.iter())
+            .map(|tile| tile.x + tile.dimensions().0)
+            .max()
+            .unwrap_or(0)
+    }
+    fn height(&self) -> usize {
+        (self.tiles.iter())
+            .map(|tile| tile.y + tile.dimensions().1)
+            .max()
+            .unwrap_or(0)
+    }
+    fn inks(&self) -> &'static [Ink] {
+        if (self.tiles.iter()).all(|tile| tile.display.inks().contains(&Ink::Red)) {
+            BLACK_WHITE_RED
+        } else {
+            BLACK_WHITE
+        }
+    }
+
+    fn init(&mut self) -> Result<()> {
+        for tile in &mut self.tiles {
+            tile.display.init()?;
+        }
+        Ok(())
+    }
+    fn write_frame(&mut self, black: &[u8], red: &[u8]) -> Result<()> {
+        let row_bytes = self.width().div_ceil(8);
+        let expected = row_bytes * self.height();
+        for plane in [black, red] {
+            if plane.len() != expected {
+                return Err(Error::InvalidLength {
+                    expected,
+                    actual: plane.len(),
+                });
+            }
+        }
+        // Red some of the panels can't show isn't shown on the others either.
+        let sources: &[&[u8]] = if self.inks().contains(&Ink::Red) {
+            &[black, red]
+        } else {
+            &[black]
+        };
+        for tile in &mut self.tiles {
+            let (width, height) = (tile.display.width(), tile.display.height());
+            let tile_row_bytes = width.div_ceil(8);
+            let mut planes = [
+                vec![0xFF; tile_row_bytes * height],
+                vec![0xFF; tile_row_bytes * height],
+            ];
+            for y in 0..height {
+                for x in 0..width {
+                    let (dx, dy) = tile.rotation.to_drawing(x, y, width, height);
+                    let (cx, cy) = (tile.x + dx, tile.y + dy);
+                    let (index, mask) = (cx / 8 + cy * row_bytes, 0x80 >> (cx % 8));
+                    for (plane, source) in planes.iter_mut().zip(sources) {
+                        if source[index] & mask == 0 {
+                            plane[x / 8 + y * tile_row_bytes] &= !(0x80 >> (x % 8));
+                        }
+                    }
+                }
+            }
+            let [black, red] = &planes;
+            tile.display.write_frame(black, red)?;
+        }
+        Ok(())
+    }
+    fn refresh(&mut self) -> Result<RefreshReport> {
+        match self.schedule {
+            Schedule::Together => {
+                self.start_refresh()?;
+                self.finish_refresh()
+            }
+            Schedule::Sequence => {
+                let mut reports = Vec::new();
+                for tile in &mut self.tiles {
+                    reports.push(tile.display.refresh()?);
+                }
+                Ok(combine(reports))
+            }
+        }
+    }
+    /// In [`Schedule::Sequence`], all but the last panel are refreshed
+    /// before returning.
+    fn start_refresh(&mut self) -> Result<()> {
+        self.refreshed.clear();
+        let Some((last, others)) = self.tiles.split_last_mut() else {
+            return Ok(());
+        };
+        for tile in others {
+            match self.schedule {
+                Schedule::Together => tile.display.start_refresh()?,
+                Schedule::Sequence => self.refreshed.push(tile.display.refresh()?),
+            }
+        }
+        last.display.start_refresh()
+    }
+    fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        let mut reports = mem::take(&mut self.refreshed);
+        let Some((last, others)) = self.tiles.split_last_mut() else {
+            return Ok(combine(reports));
+        };
+        if self.schedule == Schedule::Together {
+            for tile in others {
+                reports.push(tile.display.finish_refresh()?);
+            }
+        }
+        reports.push(last.display.finish_refresh()?);
+        Ok(combine(reports))
+    }
+    fn clear(&mut self) -> Result<()> {
+        match self.schedule {
+            Schedule::Together => {
+                let len = self.width().div_ceil(8) * self.height();
+                self.write_frame(&vec![0xFF; len], &vec![0xFF; len])?;
+                self.refresh()?;
+                Ok(())
+            }
+            Schedule::Sequence => {
+                for tile in &mut self.tiles {
+                    tile.display.clear()?;
+                }
+                Ok(())
+            }
+        }
+    }
+    fn sleep(&mut self) -> Result<()> {
+        for tile in &mut self.tiles {
+            tile.display.sleep()?;
+        }
+        Ok(())
+    }
+    fn diagnose(&mut self) -> Result<Vec<Health>> {
+        let mut health = Vec::new();
+        for tile in &mut self.tiles {
+            health.extend(tile.display.diagnose()?);
+        }
+        Ok(health)
+    }
+}
+
+/// The report of the whole canvas, from the ones of its panels: their
+/// temperatures and timings one after the other, and the LUT if they all
+/// refreshed with the same.
+fn combine(reports: Vec<RefreshReport>) -> RefreshReport {
+    let lut = (reports.first())
+        .and_then(|report| report.lut.clone())
+        .filter(|lut| (reports.iter()).all(|report| report.lut.as_ref() == Some(lut)));
+    RefreshReport {
+        temperatures: (reports.iter())
+            .flat_map(|report| report.temperatures.iter().copied())
+            .collect(),
+        lut,
+        timings: reports
+            .into_iter()
+            .flat_map(|report| report.timings)
+            .collect(),
+    }
+}
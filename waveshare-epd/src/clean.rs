@@ -0,0 +1,81 @@
+//! Deep cleans, which clear the ghosting left by many partial or short
+//! refreshes, see [`Display::deep_clean`].
+//!
+//! [`Display::deep_clean`]: crate::Display::deep_clean
+
+use crate::Ink;
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+/// Bytes of the black and red planes of each step of a deep clean: black,
+/// white, red and white.
+pub(crate) const CYCLE: [(u8, u8); 4] = [(0x00, 0xFF), (0xFF, 0xFF), (0xFF, 0x00), (0xFF, 0xFF)];
+
+/// The steps of [`CYCLE`] a panel with `inks` goes through, each a refresh.
+pub(crate) fn steps(inks: &[Ink]) -> &'static [(u8, u8)] {
+    if inks.contains(&Ink::Red) {
+        &CYCLE[..]
+    } else {
+        &CYCLE[..2]
+    }
+}
+
+/// When a deep clean is due.
+///
+/// Never by default. Either limit makes it due once reached.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Policy {
+    refreshes: Option<u64>,
+    interval: Option<Duration>,
+}
+impl Policy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clean every `refreshes` refreshes.
+    pub fn with_refreshes(mut self, refreshes: u64) -> Self {
+        self.refreshes = Some(refreshes);
+        self
+    }
+
+    /// Clean once `interval` has passed since the last clean.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Whether a deep clean is due at `now`. A panel that was never cleaned
+    /// is due as soon as any limit is set.
+    pub fn is_due(&self, state: &State, now: SystemTime) -> bool {
+        let refreshes = self.refreshes.is_some_and(|max| state.refreshes >= max);
+        let elapsed = self.interval.is_some_and(|interval| {
+            state.last_clean.is_none_or(|last| {
+                // A clock set back doesn't postpone cleans forever.
+                !matches!(now.duration_since(last), Ok(elapsed) if elapsed < interval)
+            })
+        });
+        refreshes || elapsed
+    }
+}
+
+/// What a [`Policy`] decides from, to persist across runs, for instance in a
+/// [`guard::Record`](crate::guard::Record).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
+pub struct State {
+    /// Refreshes since the last clean.
+    pub refreshes: u64,
+    pub last_clean: Option<SystemTime>,
+}
+impl State {
+    pub fn record_refresh(&mut self) {
+        self.refreshes += 1;
+    }
+
+    pub fn record_clean(&mut self, now: SystemTime) {
+        self.refreshes = 0;
+        self.last_clean = Some(now);
+    }
+}
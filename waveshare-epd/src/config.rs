@@ -0,0 +1,110 @@
+//! Wiring of a panel to the board, for the SPI backends.
+
+#[cfg(feature = "config")]
+use crate::{Error, Result};
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "config")]
+use std::{fs, path::Path};
+
+/// Names accepted by [`Config::profile`].
+pub const PROFILES: &[&str] = &["waveshare-hat", "waveshare-hat-ce1"];
+
+/// Where the panel is wired, as GPIO numbers of the board.
+///
+/// Defaults to Waveshare's e-Paper HAT on a Raspberry Pi.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "config",
+    derive(Serialize, Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Config {
+    /// SPI bus, the `X` of `/dev/spidevX.Y`.
+    pub spi_bus: u8,
+    /// Chip select of the bus, the `Y` of `/dev/spidevX.Y`.
+    pub spi_device: u8,
+    pub spi_speed_hz: u32,
+    /// GPIO controller, the `N` of `/dev/gpiochipN`. Only used by the Linux
+    /// backend.
+    pub gpio_chip: u32,
+    pub reset_pin: u32,
+    pub dc_pin: u32,
+    /// Pin driven as chip select, instead of the bus' own. Unlike the latter,
+    /// it is held low across the chunks of large transfers. It can't be the
+    /// bus' own line, which the Linux SPI driver already claims.
+    pub cs_pin: Option<u32>,
+    pub busy_pin: u32,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self::waveshare_hat()
+    }
+}
+impl Config {
+    /// Waveshare's e-Paper HAT, selected by the bus' own chip select, CE0.
+    pub fn waveshare_hat() -> Self {
+        Self {
+            spi_bus: 0,
+            spi_device: 0,
+            spi_speed_hz: 16_000_000,
+            gpio_chip: 0,
+            reset_pin: 17,
+            dc_pin: 25,
+            cs_pin: None,
+            busy_pin: 24,
+        }
+    }
+
+    /// Waveshare's e-Paper HAT, rewired to the second chip select of SPI0 so
+    /// that the first one stays free for another device.
+    pub fn waveshare_hat_ce1() -> Self {
+        Self {
+            spi_device: 1,
+            ..Self::waveshare_hat()
+        }
+    }
+
+    /// The profile called `name`, one of [`PROFILES`].
+    pub fn profile(name: &str) -> Option<Self> {
+        match name {
+            "waveshare-hat" => Some(Self::waveshare_hat()),
+            "waveshare-hat-ce1" => Some(Self::waveshare_hat_ce1()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "config")]
+impl Config {
+    /// Parse a TOML configuration.
+    ///
+    /// It starts from the `profile` it names, the HAT by default, and
+    /// overrides any of the fields. `cs_pin = "bus"` selects the bus' own chip
+    /// select.
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let mut table: toml::Table = text.parse()?;
+        let base = match table.remove("profile") {
+            None => Self::default(),
+            Some(toml::Value::String(name)) => Self::profile(&name)
+                .ok_or_else(|| Error::InvalidConfig(format!("unknown profile {name:?}")))?,
+            Some(value) => {
+                return Err(Error::InvalidConfig(format!("invalid profile {value}")));
+            }
+        };
+        let mut config = toml::Table::try_from(base).expect("configurations are valid TOML tables");
+        for (key, value) in table {
+            if key == "cs_pin" && value.as_str() == Some("bus") {
+                config.remove(&key);
+            } else {
+                config.insert(key, value);
+            }
+        }
+        Ok(config.try_into()?)
+    }
+
+    /// Read a TOML configuration, see [`Config::from_toml`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+}
@@ -0,0 +1,166 @@
+//! Connection checks of the controllers, see [`Display::diagnose`].
+//!
+//! [`Display::diagnose`]: crate::Display::diagnose
+
+// Each kind of interface is only used by some of the panels.
+#![cfg_attr(
+    not(all(
+        any(
+            feature = "epd_2in7",
+            feature = "epd_2in7b",
+            feature = "epd_2in13_v4",
+            feature = "epd_4in2",
+            feature = "epd_5in65f",
+            feature = "epd_7in5_v2",
+            feature = "epd_7in5b_v2"
+        ),
+        feature = "epd_12in48b"
+    )),
+    allow(dead_code, unused_imports)
+)]
+
+use crate::{
+    interface::{CascadeInterface, Controller, Interface},
+    Error, Result,
+};
+use futures::executor::block_on;
+use std::time::Duration;
+
+/// Longest wait for a healthy controller, even if the panel's timeout is
+/// longer: it resets and measures its temperature in well under that.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Health of one controller.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Health {
+    /// Only given for panels with more than one.
+    pub controller: Option<Controller>,
+    /// Whether the busy line settled to its idle level after reset.
+    pub idle: bool,
+    /// Status register (0x71), if the backend can read it.
+    pub status: Option<u8>,
+    /// Revision register (0x70), if the backend can read it.
+    pub revision: Option<[u8; 2]>,
+    /// How long the controller took to measure its temperature, if it did
+    /// within the timeout, by the clock of the interface.
+    pub response_time: Option<Duration>,
+}
+impl Health {
+    pub fn healthy(&self) -> bool {
+        self.idle && self.response_time.is_some()
+    }
+}
+
+/// Check a panel driven by a single controller, which must be reset already.
+pub(crate) fn single(
+    interface: &mut impl Interface,
+    idle_high: bool,
+    timeout: Duration,
+) -> Result<Health> {
+    check(&mut Single(interface), None, idle_high, timeout)
+}
+
+/// Check each controller of a panel, which must be reset already.
+pub(crate) fn cascade(
+    interface: &mut impl CascadeInterface,
+    idle_high: bool,
+    timeout: Duration,
+) -> Result<Vec<Health>> {
+    Controller::ALL
+        .into_iter()
+        .map(|controller| {
+            check(
+                &mut Cascade(interface, controller),
+                Some(controller),
+                idle_high,
+                timeout,
+            )
+        })
+        .collect()
+}
+
+fn check(
+    port: &mut impl Port,
+    controller: Option<Controller>,
+    idle_high: bool,
+    timeout: Duration,
+) -> Result<Health> {
+    let timeout = timeout.min(TIMEOUT);
+    let idle = wait_idle(port, idle_high, timeout)?.is_some();
+    let mut status = [0];
+    let status = read(port, 0x71, &mut status)?.then_some(status[0]);
+    let mut revision = [0; 2];
+    let revision = read(port, 0x70, &mut revision)?.then_some(revision);
+    let response_time = if idle {
+        port.send_command(0x40)?; // Temperature Sensor Calibration
+        wait_idle(port, idle_high, timeout)?
+    } else {
+        None
+    };
+    Ok(Health {
+        controller,
+        idle,
+        status,
+        revision,
+        response_time,
+    })
+}
+
+/// Send `command` and read its answer, returning whether the backend could.
+fn read(port: &mut impl Port, command: u8, buf: &mut [u8]) -> Result<bool> {
+    port.send_command(command)?;
+    match port.read_data(buf) {
+        Ok(()) => Ok(true),
+        Err(Error::Unsupported(_)) => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+/// Time until the busy line is at its idle level, if within `timeout`.
+fn wait_idle(port: &mut impl Port, idle_high: bool, timeout: Duration) -> Result<Option<Duration>> {
+    let start = port.now();
+    Ok((!port.wait_idle(idle_high, timeout)?).then(|| port.now() - start))
+}
+
+/// Access to one controller, whatever the interface.
+trait Port {
+    fn send_command(&mut self, command: u8) -> Result<()>;
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<()>;
+    /// Whether the busy line is still busy after `timeout`, see
+    /// [`Interface::wait_idle`].
+    fn wait_idle(&mut self, idle_high: bool, timeout: Duration) -> Result<bool>;
+    /// See [`Interface::now`].
+    fn now(&self) -> Duration;
+}
+
+struct Single<'a, I>(&'a mut I);
+impl<I: Interface> Port for Single<'_, I> {
+    fn send_command(&mut self, command: u8) -> Result<()> {
+        self.0.send_command(command)
+    }
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.0.read_data(buf)
+    }
+    fn wait_idle(&mut self, idle_high: bool, timeout: Duration) -> Result<bool> {
+        block_on(self.0.wait_idle(idle_high, timeout))
+    }
+    fn now(&self) -> Duration {
+        self.0.now()
+    }
+}
+
+struct Cascade<'a, I>(&'a mut I, Controller);
+impl<I: CascadeInterface> Port for Cascade<'_, I> {
+    fn send_command(&mut self, command: u8) -> Result<()> {
+        self.0.send_command(&[self.1], command)
+    }
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.0.read_data(self.1, buf)
+    }
+    fn wait_idle(&mut self, idle_high: bool, timeout: Duration) -> Result<bool> {
+        Ok(block_on(self.0.wait_idle(&[self.1], idle_high, timeout))?.is_some())
+    }
+    fn now(&self) -> Duration {
+        self.0.now()
+    }
+}
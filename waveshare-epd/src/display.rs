@@ -0,0 +1,193 @@
+use crate::{clean, interface::Controller, Health, Result};
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// A color a panel is able to show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Ink {
+    Black,
+    White,
+    Red,
+    Green,
+    Blue,
+    Yellow,
+    Orange,
+    DarkGray,
+    LightGray,
+}
+
+/// What a refresh ran with, and how long it took.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
+pub struct RefreshReport {
+    /// Temperature of each controller before refreshing, in °C. The
+    /// controller is only given for panels with more than one.
+    #[cfg_attr(feature = "config", serde(default, with = "temperatures"))]
+    pub temperatures: Vec<(Option<Controller>, i8)>,
+    /// Name of the LUT the driver picked, if any.
+    #[cfg_attr(feature = "config", serde(default))]
+    pub lut: Option<String>,
+    /// Phases of the refresh on each controller.
+    #[cfg_attr(feature = "config", serde(default))]
+    pub timings: Vec<Timings>,
+}
+
+/// How long each phase of a refresh took on a controller.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
+pub struct Timings {
+    /// The controller, for panels with more than one.
+    #[cfg_attr(
+        feature = "config",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub controller: Option<Controller>,
+    /// Sending data to the controller since the previous refresh, the frame
+    /// included.
+    pub transfer: Duration,
+    /// `None` if the panel isn't powered on as part of refreshing, but when
+    /// initialized or by the refresh itself.
+    #[cfg_attr(
+        feature = "config",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub power_on: Option<Duration>,
+    /// Until the controller was seen idle again.
+    pub refresh: Duration,
+    /// `None` if the panel isn't powered off as part of refreshing, but when
+    /// put to sleep or by the refresh itself.
+    #[cfg_attr(
+        feature = "config",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub power_off: Option<Duration>,
+}
+
+/// Temperatures as a list of tables, which TOML can hold even without
+/// controllers.
+#[cfg(feature = "config")]
+mod temperatures {
+    use crate::interface::Controller;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Temperature {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        controller: Option<Controller>,
+        celsius: i8,
+    }
+
+    pub fn serialize<S: Serializer>(
+        temperatures: &[(Option<Controller>, i8)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            temperatures
+                .iter()
+                .map(|&(controller, celsius)| Temperature {
+                    controller,
+                    celsius,
+                }),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(Option<Controller>, i8)>, D::Error> {
+        let temperatures = Vec::<Temperature>::deserialize(deserializer)?;
+        Ok(temperatures
+            .into_iter()
+            .map(|t| (t.controller, t.celsius))
+            .collect())
+    }
+}
+
+/// Operations common to all supported panels.
+///
+/// Frames are passed as packed planes, one per non-white ink: rows are
+/// `width` bits rounded up to whole bytes, the most significant bit is the
+/// leftmost pixel, and a cleared bit means the ink is present. This is the
+/// format produced by [`Packing`](crate::packing::Packing) by default. Planes
+/// of inks the panel doesn't support are ignored.
+pub trait Display {
+    /// Width in pixels, in the panel's native orientation.
+    fn width(&self) -> usize;
+    /// Height in pixels, in the panel's native orientation.
+    fn height(&self) -> usize;
+    /// Inks the panel is able to show.
+    fn inks(&self) -> &'static [Ink];
+
+    /// Wake the panel up and configure it.
+    fn init(&mut self) -> Result<()>;
+    /// Write a full frame to the panel's memory.
+    ///
+    /// Nothing is shown until the next [`refresh`](Display::refresh).
+    fn write_frame(&mut self, black: &[u8], red: &[u8]) -> Result<()>;
+    /// Show the frame that was last written, waiting until it is.
+    fn refresh(&mut self) -> Result<RefreshReport> {
+        self.start_refresh()?;
+        self.finish_refresh()
+    }
+    /// Start showing the frame that was last written, without waiting.
+    fn start_refresh(&mut self) -> Result<()>;
+    /// Wait for the refresh that was started to end.
+    fn finish_refresh(&mut self) -> Result<RefreshReport>;
+    /// Turn the whole panel white.
+    fn clear(&mut self) -> Result<()>;
+    /// Cycle the whole panel through black, white, red and white, to clear
+    /// ghosting. Inks the panel doesn't support are skipped.
+    fn deep_clean(&mut self) -> Result<()> {
+        let len = self.width().div_ceil(8) * self.height();
+        for &(black, red) in clean::steps(self.inks()) {
+            self.write_frame(&vec![black; len], &vec![red; len])?;
+            self.refresh()?;
+        }
+        Ok(())
+    }
+    /// Put the panel into deep sleep. It must be initialized again before use.
+    fn sleep(&mut self) -> Result<()>;
+    /// Reset the panel and check the connection to each of its controllers.
+    /// It must be initialized again before use.
+    fn diagnose(&mut self) -> Result<Vec<Health>>;
+}
+
+impl<D: Display + ?Sized> Display for &mut D {
+    fn width(&self) -> usize {
+        (**self).width()
+    }
+    fn height(&self) -> usize {
+        (**self).height()
+    }
+    fn inks(&self) -> &'static [Ink] {
+        (**self).inks()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        (**self).init()
+    }
+    fn write_frame(&mut self, black: &[u8], red: &[u8]) -> Result<()> {
+        (**self).write_frame(black, red)
+    }
+    fn refresh(&mut self) -> Result<RefreshReport> {
+        (**self).refresh()
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        (**self).start_refresh()
+    }
+    fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        (**self).finish_refresh()
+    }
+    fn clear(&mut self) -> Result<()> {
+        (**self).clear()
+    }
+    fn deep_clean(&mut self) -> Result<()> {
+        (**self).deep_clean()
+    }
+    fn sleep(&mut self) -> Result<()> {
+        (**self).sleep()
+    }
+    fn diagnose(&mut self) -> Result<Vec<Health>> {
+        (**self).diagnose()
+    }
+}
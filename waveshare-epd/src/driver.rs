@@ -0,0 +1,131 @@
+//! Plumbing shared by the drivers of panels with a single controller.
+
+use crate::{diagnose, interface::Interface, Error, Health, RefreshReport, Result, Timings};
+use futures::executor::block_on;
+use log::debug;
+use std::{
+    mem,
+    time::{Duration, Instant},
+};
+
+pub(crate) struct Driver<I> {
+    pub(crate) interface: I,
+    pub(crate) timeout: Duration,
+    /// Level of the busy line while the controller is idle.
+    idle_high: bool,
+    /// Last command sent since reset, reported on timeouts.
+    command: Option<u8>,
+    /// Phases of the next refresh, so far.
+    pub(crate) timings: Timings,
+    /// When the refresh in progress started.
+    refresh_started: Option<Instant>,
+}
+impl<I: Interface> Driver<I> {
+    pub(crate) fn new(interface: I, idle_high: bool, timeout: Duration) -> Self {
+        Self {
+            interface,
+            timeout,
+            idle_high,
+            command: None,
+            timings: Timings::default(),
+            refresh_started: None,
+        }
+    }
+
+    /// Pull the reset line low for `pulse`, letting the controller settle
+    /// before and after.
+    pub(crate) fn reset(&mut self, settle: Duration, pulse: Duration) -> Result<()> {
+        self.command = None;
+        self.interface.set_reset(true)?;
+        self.interface.delay(settle);
+        self.interface.set_reset(false)?;
+        self.interface.delay(pulse);
+        self.interface.set_reset(true)?;
+        self.interface.delay(settle);
+        Ok(())
+    }
+
+    /// Send `command`, followed by its parameters if any.
+    pub(crate) fn command(&mut self, command: u8, data: &[u8]) -> Result<()> {
+        self.command = Some(command);
+        self.interface.send_command(command)?;
+        if !data.is_empty() {
+            self.send_data(data)?;
+        }
+        Ok(())
+    }
+
+    /// Send more data for the last command.
+    pub(crate) fn send_data(&mut self, data: &[u8]) -> Result<()> {
+        let start = Instant::now();
+        self.interface.send_data(data)?;
+        self.timings.transfer += start.elapsed();
+        Ok(())
+    }
+
+    /// Send `command`, which starts the refresh, timing it from now on.
+    pub(crate) fn start_refresh(&mut self, command: u8, data: &[u8]) -> Result<()> {
+        self.refresh_started = Some(Instant::now());
+        self.command(command, data)
+    }
+
+    /// Wait until the refresh that was started is done.
+    pub(crate) async fn finish_refresh(&mut self) -> Result<()> {
+        self.wait_idle().await?;
+        if let Some(start) = self.refresh_started.take() {
+            self.timings.refresh = start.elapsed();
+        }
+        Ok(())
+    }
+
+    /// The phases of the refresh that ended, starting over for the next one.
+    pub(crate) fn report(&mut self) -> RefreshReport {
+        let timings = mem::take(&mut self.timings);
+        debug!("{timings:?}");
+        RefreshReport {
+            timings: vec![timings],
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn delay(&mut self, millis: u64) {
+        self.interface.delay(Duration::from_millis(millis));
+    }
+
+    /// Wait until the controller is idle.
+    pub(crate) async fn wait_idle(&mut self) -> Result<()> {
+        debug!("e-Paper busy");
+        if self
+            .interface
+            .wait_idle(self.idle_high, self.timeout)
+            .await?
+        {
+            return Err(Error::Timeout {
+                controller: None,
+                command: self.command,
+            });
+        }
+        debug!("e-Paper busy release");
+        Ok(())
+    }
+
+    pub(crate) fn read_busy(&mut self) -> Result<()> {
+        block_on(self.wait_idle())
+    }
+
+    /// Check the connection to the controller, which must be reset already.
+    pub(crate) fn diagnose(&mut self) -> Result<Health> {
+        diagnose::single(&mut self.interface, self.idle_high, self.timeout)
+    }
+}
+
+/// Check that `plane` is a full frame of `expected` items.
+pub(crate) fn check_length<T>(plane: &[T], expected: usize) -> Result<()> {
+    if plane.len() != expected {
+        return Err(Error::InvalidLength {
+            expected,
+            actual: plane.len(),
+        });
+    }
+    Ok(())
+}
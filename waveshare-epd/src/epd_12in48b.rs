@@ -1,16 +1,31 @@
 // ! 12.48" 3-color
 
-use crate::Result;
-#[cfg(esp32)]
+pub mod lut;
+
+use self::lut::Lut;
+#[cfg(feature = "esp")]
+use crate::esp::EspInterface;
+use crate::{
+    clean, diagnose,
+    framebuffer::{Framebuffer, Rotation},
+    interface::{CascadeInterface, Controller},
+    Display, Error, Health, Ink, RefreshReport, Result, Timings,
+};
+#[cfg(all(feature = "esp", esp32))]
 use esp_idf_hal::{gpio, spi::SPI3};
+#[cfg(feature = "esp")]
 use esp_idf_hal::{
-    gpio::{AnyInputPin, AnyOutputPin, Input, InputPin, Output, OutputPin, PinDriver},
-    peripheral::{Peripheral, PeripheralRef},
-    spi::{SpiAnyPins, SpiConfig, SpiDeviceDriver, SpiDriver, SpiDriverConfig},
+    gpio::{InputPin, OutputPin},
+    peripheral::Peripheral,
+    spi::SpiAnyPins,
 };
-use futures::future;
+use futures::executor::block_on;
 use log::info;
-use std::{thread, time::Duration};
+use std::{
+    mem,
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
 
 pub const EPD_WIDTH: usize = 1304;
 pub const EPD_HEIGHT: usize = 984;
@@ -21,49 +36,124 @@ pub const HALF_HEIGHT: usize = EPD_HEIGHT / 2;
 
 const LEFT_BYTES: usize = LEFT_WIDTH / 8;
 const RIGHT_BYTES: usize = RIGHT_WIDTH / 8;
+const ROW_BYTES: usize = EPD_WIDTH / 8;
+const FRAME_BYTES: usize = ROW_BYTES * EPD_HEIGHT;
+
+const M1: &[Controller] = &[Controller::M1];
+const S1: &[Controller] = &[Controller::S1];
+const M2: &[Controller] = &[Controller::M2];
+const S2: &[Controller] = &[Controller::S2];
+const M1S1: &[Controller] = &[Controller::M1, Controller::S1];
+const M2S2: &[Controller] = &[Controller::M2, Controller::S2];
+const M1M2: &[Controller] = &[Controller::M1, Controller::M2];
+const M1S2: &[Controller] = &[Controller::M1, Controller::S2];
+const S1M2: &[Controller] = &[Controller::S1, Controller::M2];
+const M1S1M2S2: &[Controller] = &Controller::ALL;
+
+/// Memory of the controllers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Plane {
+    /// Set bits are white.
+    White,
+    /// Set bits are red.
+    Red,
+}
+impl Plane {
+    fn command(self) -> u8 {
+        match self {
+            Plane::White => 0x10,
+            Plane::Red => 0x13,
+        }
+    }
+}
+
+/// Size of each plane of a controller's memory.
+pub fn quadrant_bytes(controller: Controller) -> usize {
+    match controller {
+        Controller::M1 | Controller::S2 => LEFT_BYTES * HALF_HEIGHT,
+        Controller::S1 | Controller::M2 => RIGHT_BYTES * HALF_HEIGHT,
+    }
+}
 
-pub struct Epd<'d> {
-    spi: SpiDriver<'d>,
-    m1_cs: PeripheralRef<'d, AnyOutputPin>,
-    s1_cs: PeripheralRef<'d, AnyOutputPin>,
-    m2_cs: PeripheralRef<'d, AnyOutputPin>,
-    s2_cs: PeripheralRef<'d, AnyOutputPin>,
-    m1s1_dc: PinDriver<'d, AnyOutputPin, Output>,
-    m2s2_dc: PinDriver<'d, AnyOutputPin, Output>,
-    m1s1_rst: PinDriver<'d, AnyOutputPin, Output>,
-    m2s2_rst: Option<PinDriver<'d, AnyOutputPin, Output>>,
-    m1_busy: PinDriver<'d, AnyInputPin, Input>,
-    s1_busy: PinDriver<'d, AnyInputPin, Input>,
-    m2_busy: PinDriver<'d, AnyInputPin, Input>,
-    s2_busy: PinDriver<'d, AnyInputPin, Input>,
+/// Hardware revision of the panel.
+///
+/// V2 panels have "V2" printed on the sticker on their back. The revisions can
+/// also be told apart by the level of the busy lines while idle, see
+/// [`Epd::detect_revision`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Revision {
+    /// LUTs loaded by the driver, busy lines low while busy.
+    #[default]
+    V1,
+    /// LUTs from OTP, busy lines high while busy.
+    V2,
 }
+impl Revision {
+    fn idle_high(self) -> bool {
+        self == Revision::V1
+    }
+}
+
+/// Default limit on how long the panel may stay busy.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
 
-impl<'d> Epd<'d> {
+/// Temperatures the panel is rated to refresh at, in °C.
+pub const RATED_TEMPERATURE: RangeInclusive<i8> = 0..=50;
+
+/// A LUT to refresh with from a temperature up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TemperatureLut {
+    /// Lowest temperature, in °C.
+    pub from: i8,
+    /// Reported in [`RefreshReport::lut`].
+    pub name: String,
+    pub lut: Lut,
+}
+
+/// How refreshes adapt to the temperature, see [`Epd::turn_on_compensated`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Compensation {
+    /// Temperatures to refuse refreshing outside of, in °C.
+    pub rated: RangeInclusive<i8>,
+    /// LUTs by increasing temperature. The current LUT is kept below the
+    /// first one.
+    pub luts: Vec<TemperatureLut>,
+}
+impl Default for Compensation {
+    fn default() -> Self {
+        Self {
+            rated: RATED_TEMPERATURE,
+            luts: Vec::new(),
+        }
+    }
+}
+
+/// The driver of the 12.48" panel.
+///
+/// Refreshing leaves the panel powered until it is put to sleep, which
+/// dropping the driver doesn't do. Drive it through a
+/// [`Panel`](crate::power::Panel), or make sure every path out of a refresh,
+/// errors included, calls [`sleep`](Epd::sleep).
+pub struct Epd<I> {
+    interface: I,
+    revision: Revision,
+    timeout: Duration,
+    compensation: Compensation,
+    /// Phases of the next refresh so far, in the order of
+    /// [`Controller::ALL`].
+    timings: [Timings; 4],
+    /// When the refresh in progress started.
+    refresh_started: Option<Instant>,
+}
+
+#[cfg(feature = "esp")]
+impl<'d> Epd<EspInterface<'d>> {
     #[cfg(esp32)]
     pub fn waveshare(spi: impl Peripheral<P = SPI3> + 'd, pins: gpio::Pins) -> Result<Self> {
-        Ok(Self {
-            spi: SpiDriver::new(
-                spi,
-                pins.gpio13,
-                pins.gpio14,
-                None::<gpio::Gpio12>,
-                &SpiDriverConfig::new(),
-            )?,
-            m1_cs: pins.gpio23.downgrade_output().into_ref(),
-            s1_cs: pins.gpio22.downgrade_output().into_ref(),
-            m2_cs: pins.gpio16.downgrade_output().into_ref(),
-            s2_cs: pins.gpio19.downgrade_output().into_ref(),
-            m1s1_dc: PinDriver::output(pins.gpio25.downgrade_output())?,
-            m2s2_dc: PinDriver::output(pins.gpio17.downgrade_output())?,
-            m1s1_rst: PinDriver::output(pins.gpio33.downgrade_output())?,
-            m2s2_rst: Some(PinDriver::output(pins.gpio5.downgrade_output())?),
-            m1_busy: PinDriver::input(pins.gpio32.downgrade_input())?,
-            s1_busy: PinDriver::input(pins.gpio26.downgrade_input())?,
-            m2_busy: PinDriver::input(pins.gpio18.downgrade_input())?,
-            s2_busy: PinDriver::input(pins.gpio4.downgrade_input())?,
-        })
+        Ok(Self::with_interface(EspInterface::waveshare(spi, pins)?))
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn custom(
         spi: impl Peripheral<P = impl SpiAnyPins> + 'd,
         sclk: impl OutputPin + 'd,
@@ -81,453 +171,634 @@ impl<'d> Epd<'d> {
         m2_busy: impl InputPin + 'd,
         s2_busy: impl InputPin + 'd,
     ) -> Result<Self> {
-        Ok(Self {
-            spi: SpiDriver::new(spi, sclk, sdo, None::<AnyInputPin>, &SpiDriverConfig::new())?,
-            m1_cs: m1_cs.downgrade_output().into_ref(),
-            s1_cs: s1_cs.downgrade_output().into_ref(),
-            m2_cs: m2_cs.downgrade_output().into_ref(),
-            s2_cs: s2_cs.downgrade_output().into_ref(),
-            m1s1_dc: PinDriver::output(m1s1_dc.downgrade_output())?,
-            m2s2_dc: PinDriver::output(m2s2_dc.downgrade_output())?,
-            m1s1_rst: PinDriver::output(m1s1_rst.downgrade_output())?,
-            m2s2_rst: match m2s2_rst {
-                Some(pin) => Some(PinDriver::output(pin.downgrade_output())?),
-                None => None,
-            },
-            m1_busy: PinDriver::input(m1_busy.downgrade_input())?,
-            s1_busy: PinDriver::input(s1_busy.downgrade_input())?,
-            m2_busy: PinDriver::input(m2_busy.downgrade_input())?,
-            s2_busy: PinDriver::input(s2_busy.downgrade_input())?,
+        Ok(Self::with_interface(EspInterface::custom(
+            spi, sclk, sdo, m1_cs, s1_cs, m2_cs, s2_cs, m1s1_dc, m2s2_dc, m1s1_rst, m2s2_rst,
+            m1_busy, s1_busy, m2_busy, s2_busy,
+        )?))
+    }
+}
+
+impl<I: CascadeInterface> Epd<I> {
+    pub fn with_interface(interface: I) -> Self {
+        Self {
+            interface,
+            revision: Revision::V1,
+            timeout: DEFAULT_TIMEOUT,
+            compensation: Compensation::default(),
+            timings: no_timings(),
+            refresh_started: None,
+        }
+    }
+
+    pub fn with_revision(mut self, revision: Revision) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /// Limit how long each busy wait may take.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_compensation(mut self, compensation: Compensation) -> Self {
+        self.compensation = compensation;
+        self
+    }
+
+    pub fn interface(&self) -> &I {
+        &self.interface
+    }
+
+    pub fn interface_mut(&mut self) -> &mut I {
+        &mut self.interface
+    }
+
+    /// Guess the revision of the attached panel from the level of M1's busy
+    /// line right after a reset, when it is idle.
+    ///
+    /// The panel must be initialized again afterwards.
+    pub fn detect_revision(&mut self) -> Result<Revision> {
+        self.reset()?;
+        Ok(if self.interface.busy_high(Controller::M1)? {
+            Revision::V1
+        } else {
+            Revision::V2
         })
     }
 
     pub fn init(&mut self) -> Result<()> {
         self.reset()?;
-        PinDriver::output(self.m1_cs.reborrow())?.set_high()?;
-        PinDriver::output(self.s1_cs.reborrow())?.set_high()?;
-        PinDriver::output(self.m2_cs.reborrow())?.set_high()?;
-        PinDriver::output(self.s2_cs.reborrow())?.set_high()?;
-        self.init_v1()
+        match self.revision {
+            Revision::V1 => self.init_v1(),
+            Revision::V2 => self.init_v2(),
+        }
     }
     fn init_v1(&mut self) -> Result<()> {
         info!("Init V1");
         // panel setting
         // KW-3f    KWR-2F   BWROTP 0f   BWOTP 1f
-        self.m1s1m2s2_send_command(0x00)?;
-        self.m1s1_send_data(&[0x2f])?;
-        self.m2s2_send_data(&[0x23])?;
+        self.send_command(M1S1M2S2, 0x00)?;
+        self.send_data(M1S1, &[0x2f])?;
+        self.send_data(M2S2, &[0x23])?;
 
         // POWER SETTING
         // VGH=20V,VGL=-20V
         // VDH=15V
         // VDL=-15V
-        self.m1m2_send_command(0x01)?;
-        self.m1m2_send_data(&[0x07, 0x17, 0x3F, 0x3F, 0x0d])?;
+        self.send_command(M1M2, 0x01)?;
+        self.send_data(M1M2, &[0x07, 0x17, 0x3F, 0x3F, 0x0d])?;
 
         // booster soft start
-        self.m1m2_send_command(0x06)?;
-        self.m1m2_send_data(&[0x17, 0x17, 0x39, 0x17])?;
+        self.send_command(M1M2, 0x06)?;
+        self.send_data(M1M2, &[0x17, 0x17, 0x39, 0x17])?;
 
         // resolution setting
-        self.m1s1m2s2_send_command(0x61)?;
+        self.send_command(M1S1M2S2, 0x61)?;
         // source 648
         // gate 492
-        self.m1s2_send_data(&[0x02, 0x88, 0x01, 0xEC])?;
+        self.send_data(M1S2, &[0x02, 0x88, 0x01, 0xEC])?;
         // source 656
         // gate 492
-        self.s1m2_send_data(&[0x02, 0x90, 0x01, 0xEC])?;
+        self.send_data(S1M2, &[0x02, 0x90, 0x01, 0xEC])?;
 
         // DUSPI
-        self.m1s1m2s2_send_command(0x15)?;
-        self.m1s1m2s2_send_data(&[0x20])?;
+        self.send_command(M1S1M2S2, 0x15)?;
+        self.send_data(M1S1M2S2, &[0x20])?;
 
         // PLL
-        self.m1s1m2s2_send_command(0x30)?;
-        self.m1s1m2s2_send_data(&[0x08])?;
+        self.send_command(M1S1M2S2, 0x30)?;
+        self.send_data(M1S1M2S2, &[0x08])?;
 
         // Vcom and data interval setting
-        self.m1s1m2s2_send_command(0x50)?;
-        self.m1s1m2s2_send_data(&[0x31, 0x07])?;
+        self.send_command(M1S1M2S2, 0x50)?;
+        self.send_data(M1S1M2S2, &[0x31, 0x07])?;
 
         // TCON
-        self.m1s1m2s2_send_command(0x60)?;
-        self.m1s1m2s2_send_data(&[0x22])?;
+        self.send_command(M1S1M2S2, 0x60)?;
+        self.send_data(M1S1M2S2, &[0x22])?;
 
         // POWER SETTING
-        self.m1m2_send_command(0xE0)?;
-        self.m1m2_send_data(&[0x01])?;
+        self.send_command(M1M2, 0xE0)?;
+        self.send_data(M1M2, &[0x01])?;
 
-        self.m1s1m2s2_send_command(0xE3)?;
-        self.m1s1m2s2_send_data(&[0x00])?;
+        self.send_command(M1S1M2S2, 0xE3)?;
+        self.send_data(M1S1M2S2, &[0x00])?;
 
-        self.m1m2_send_command(0x82)?;
-        self.m1m2_send_data(&[0x1c])?;
+        self.send_command(M1M2, 0x82)?;
+        self.send_data(M1M2, &[0x1c])?;
 
-        self.set_lut()?;
+        self.set_lut(&Lut::stock())?;
         Ok(())
     }
 
+    fn init_v2(&mut self) -> Result<()> {
+        info!("Init V2");
+        // panel setting, LUTs from OTP
+        self.send_command(M1S1M2S2, 0x00)?;
+        self.send_data(M1S1, &[0x0f])?;
+        self.send_data(M2S2, &[0x03])?;
+
+        // booster soft start
+        self.send_command(M1M2, 0x06)?;
+        self.send_data(M1M2, &[0x17, 0x17, 0x39, 0x17])?;
+
+        // resolution setting
+        self.send_command(M1S1M2S2, 0x61)?;
+        // source 648
+        // gate 492
+        self.send_data(M1S2, &[0x02, 0x88, 0x01, 0xEC])?;
+        // source 656
+        // gate 492
+        self.send_data(S1M2, &[0x02, 0x90, 0x01, 0xEC])?;
+
+        // DUSPI
+        self.send_command(M1S1M2S2, 0x15)?;
+        self.send_data(M1S1M2S2, &[0x20])?;
+
+        // Vcom and data interval setting
+        self.send_command(M1S1M2S2, 0x50)?;
+        self.send_data(M1S1M2S2, &[0x11, 0x07])?;
+
+        // TCON
+        self.send_command(M1S1M2S2, 0x60)?;
+        self.send_data(M1S1M2S2, &[0x22])?;
+
+        self.send_command(M1S1M2S2, 0xE3)?;
+        self.send_data(M1S1M2S2, &[0x00])?;
+
+        // cascade setting, then force the OTP tables for M1's temperature
+        let celsius = match block_on(self.read_temperature(Controller::M1)) {
+            Ok(celsius) => celsius,
+            // The controllers keep sensing the temperature on their own.
+            Err(Error::Unsupported(what)) => {
+                info!("Not forcing the temperature: {what} is not supported");
+                return Ok(());
+            }
+            Err(error) => return Err(error),
+        };
+        info!("M1 at {celsius} °C");
+        self.send_command(M1S1M2S2, 0xE0)?;
+        self.send_data(M1S1M2S2, &[0x03])?;
+        self.force_temperature(celsius)
+    }
+
+    /// Make V2 panels pick their OTP tables for `celsius`, until the next
+    /// [`Epd::init`].
+    fn force_temperature(&mut self, celsius: i8) -> Result<()> {
+        self.send_command(M1S1M2S2, 0xE5)?;
+        self.send_data(M1S1M2S2, &[celsius as u8])
+    }
+
+    /// Reset the panel and check the connection to each controller. The
+    /// panel must be initialized again before use.
+    pub fn diagnose(&mut self) -> Result<Vec<Health>> {
+        self.reset()?;
+        let health =
+            diagnose::cascade(&mut self.interface, self.revision.idle_high(), self.timeout)?;
+        for health in &health {
+            info!("{health:?}");
+        }
+        Ok(health)
+    }
+
     pub fn clear(&mut self) -> Result<()> {
+        self.fill(0xFF, 0xFF)
+    }
+
+    /// Cycle the whole panel through black, white, red and white, to clear
+    /// ghosting. Takes four refreshes.
+    pub async fn deep_clean(&mut self) -> Result<()> {
+        for (black, red) in clean::CYCLE {
+            self.fill(black, red)?;
+            self.turn_on().await?;
+        }
+        Ok(())
+    }
+
+    /// Write the same byte all over each plane, in the format of
+    /// [`Display::write_frame`].
+    fn fill(&mut self, black: u8, red: u8) -> Result<()> {
+        self.send_command(M1S1M2S2, 0x10)?;
+        self.fill_plane(black)?;
+        // The controllers use set bits for red.
+        self.send_command(M1S1M2S2, 0x13)?;
+        self.fill_plane(!red)
+    }
+    fn fill_plane(&mut self, byte: u8) -> Result<()> {
         // M1 part 648*492
         // S1 part 656*492
         // M2 part 656*492
         // S2 part 648*492
-        self.m1s1m2s2_send_command(0x10)?;
-        self.s2_send_data(&[0xff; LEFT_BYTES * HALF_HEIGHT])?;
-        self.m2_send_data(&[0xff; RIGHT_BYTES * HALF_HEIGHT])?;
-        self.m1_send_data(&[0xff; LEFT_BYTES * HALF_HEIGHT])?;
-        self.s1_send_data(&[0xff; RIGHT_BYTES * HALF_HEIGHT])?;
-
-        self.m1s1m2s2_send_command(0x13)?;
-        self.s2_send_data(&[0x00; LEFT_BYTES * HALF_HEIGHT])?;
-        self.m2_send_data(&[0x00; RIGHT_BYTES * HALF_HEIGHT])?;
-        self.m1_send_data(&[0x00; LEFT_BYTES * HALF_HEIGHT])?;
-        self.s1_send_data(&[0x00; RIGHT_BYTES * HALF_HEIGHT])?;
-
-        Ok(())
+        self.send_data(S2, &[byte; LEFT_BYTES * HALF_HEIGHT])?;
+        self.send_data(M2, &[byte; RIGHT_BYTES * HALF_HEIGHT])?;
+        self.send_data(M1, &[byte; LEFT_BYTES * HALF_HEIGHT])?;
+        self.send_data(S1, &[byte; RIGHT_BYTES * HALF_HEIGHT])
+    }
+
+    /// Start writing `plane` of `controller`'s memory, which can then be sent
+    /// in chunks of any size.
+    pub fn begin(&mut self, controller: Controller, plane: Plane) -> Result<PlaneWriter<'_, I>> {
+        self.send_command(&[controller], plane.command())?;
+        Ok(PlaneWriter {
+            epd: self,
+            controller,
+            remaining: quadrant_bytes(controller),
+        })
     }
 
     /// Write the bottom left white buffer.
     pub fn m1_display_white(&mut self, white: &[u8; LEFT_BYTES * HALF_HEIGHT]) -> Result<()> {
-        self.m1_send_command(0x10)?;
-        self.m1_send_data(white)?;
-        Ok(())
+        self.display_quadrant(Controller::M1, Plane::White, white)
     }
     /// Write the bottom left red buffer.
     pub fn m1_display_red(&mut self, red: &[u8; LEFT_BYTES * HALF_HEIGHT]) -> Result<()> {
-        self.m1_send_command(0x13)?;
-        self.m1_send_data(red)?;
-        Ok(())
+        self.display_quadrant(Controller::M1, Plane::Red, red)
     }
 
     /// Write the bottom right white buffer.
     pub fn s1_display_white(&mut self, white: &[u8; RIGHT_BYTES * HALF_HEIGHT]) -> Result<()> {
-        self.s1_send_command(0x10)?;
-        self.s1_send_data(white)?;
-        Ok(())
+        self.display_quadrant(Controller::S1, Plane::White, white)
     }
     /// Write the bottom right red buffer.
     pub fn s1_display_red(&mut self, red: &[u8; RIGHT_BYTES * HALF_HEIGHT]) -> Result<()> {
-        self.s1_send_command(0x13)?;
-        self.s1_send_data(red)?;
-        Ok(())
+        self.display_quadrant(Controller::S1, Plane::Red, red)
     }
 
     /// Write the top right white buffer.
     pub fn m2_display_white(&mut self, white: &[u8; RIGHT_BYTES * HALF_HEIGHT]) -> Result<()> {
-        self.m2_send_command(0x10)?;
-        self.m2_send_data(white)?;
-        Ok(())
+        self.display_quadrant(Controller::M2, Plane::White, white)
     }
     /// Write the top right red buffer.
     pub fn m2_display_red(&mut self, red: &[u8; RIGHT_BYTES * HALF_HEIGHT]) -> Result<()> {
-        self.m2_send_command(0x13)?;
-        self.m2_send_data(red)?;
-        Ok(())
+        self.display_quadrant(Controller::M2, Plane::Red, red)
     }
 
     /// Write the top left white buffer.
     pub fn s2_display_white(&mut self, white: &[u8; LEFT_BYTES * HALF_HEIGHT]) -> Result<()> {
-        self.s2_send_command(0x10)?;
-        self.s2_send_data(white)?;
-        Ok(())
+        self.display_quadrant(Controller::S2, Plane::White, white)
     }
     /// Write the top left red buffer.
     pub fn s2_display_red(&mut self, red: &[u8; LEFT_BYTES * HALF_HEIGHT]) -> Result<()> {
-        self.s2_send_command(0x13)?;
-        self.s2_send_data(red)?;
-        Ok(())
-    }
-
-    pub async fn turn_on(&mut self) -> Result<()> {
-        self.m1m2_send_command(0x04)?; // power on
-        thread::sleep(Duration::from_millis(300));
-        self.m1s1m2s2_send_command(0x12)?; // Display Refresh
+        self.display_quadrant(Controller::S2, Plane::Red, red)
+    }
+
+    fn display_quadrant(
+        &mut self,
+        controller: Controller,
+        plane: Plane,
+        data: &[u8],
+    ) -> Result<()> {
+        let mut writer = self.begin(controller, plane)?;
+        writer.write(data)?;
+        writer.end()
+    }
+
+    /// Show the frame that was last written. The panel stays powered until
+    /// put to sleep.
+    pub async fn turn_on(&mut self) -> Result<RefreshReport> {
+        self.start_refresh()?;
+        self.finish_refresh().await
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        let start = Instant::now();
+        self.send_command(M1M2, 0x04)?; // power on
+        self.interface.delay(Duration::from_millis(300));
+        let power_on = start.elapsed();
+        for timings in &mut self.timings {
+            timings.power_on = Some(power_on);
+        }
+        self.refresh_started = Some(Instant::now());
+        self.send_command(M1S1M2S2, 0x12)?; // Display Refresh
 
         info!("Busy");
-        self.m1s1m2s2_send_command(0x71)?;
-        future::try_join4(
-            self.m1_busy.wait_for_high(),
-            self.s1_busy.wait_for_high(),
-            self.m2_busy.wait_for_high(),
-            self.s2_busy.wait_for_high(),
-        )
-        .await?;
+        self.send_command(M1S1M2S2, 0x71)
+    }
+    /// Wait for each controller in turn, timing it until it is seen idle.
+    async fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        let start = self.refresh_started.take().unwrap_or_else(Instant::now);
+        let mut busy = M1S1M2S2.to_vec();
+        while let Some(&controller) = busy.first() {
+            self.wait_idle(&[controller], 0x12).await?;
+            let elapsed = start.elapsed();
+            // The others may have finished while waiting.
+            let mut idle = vec![controller];
+            for &other in &busy[1..] {
+                if self.interface.busy_high(other)? == self.revision.idle_high() {
+                    idle.push(other);
+                }
+            }
+            for controller in &idle {
+                self.timings[controller_index(*controller)].refresh = elapsed;
+            }
+            busy.retain(|controller| !idle.contains(controller));
+        }
         info!("Busy free");
+        Ok(RefreshReport {
+            timings: mem::replace(&mut self.timings, no_timings()).to_vec(),
+            ..Default::default()
+        })
+    }
+
+    /// Read the temperature sensor of `controller`, in °C.
+    pub fn temperature(&mut self, controller: Controller) -> Result<i8> {
+        block_on(self.read_temperature(controller))
+    }
+    async fn read_temperature(&mut self, controller: Controller) -> Result<i8> {
+        self.send_command(&[controller], 0x40)?; // Temperature Sensor Calibration
+        self.wait_idle(&[controller], 0x40).await?;
+        let mut buf = [0];
+        self.interface.read_data(controller, &mut buf)?;
+        Ok(buf[0] as i8)
+    }
+
+    /// Refresh with the LUT matching M1's temperature, unless any controller
+    /// is outside the rated range.
+    pub async fn turn_on_compensated(&mut self) -> Result<RefreshReport> {
+        let mut temperatures = Vec::new();
+        for controller in Controller::ALL {
+            let celsius = self.read_temperature(controller).await?;
+            info!("{controller:?} at {celsius} °C");
+            if !self.compensation.rated.contains(&celsius) {
+                return Err(Error::Temperature {
+                    controller: Some(controller),
+                    celsius,
+                });
+            }
+            temperatures.push((Some(controller), celsius));
+        }
+
+        let celsius = temperatures[0].1;
+        if self.revision == Revision::V2 {
+            self.force_temperature(celsius)?;
+        }
+        let lut = (self.compensation.luts.iter())
+            .rev()
+            .find(|lut| lut.from <= celsius)
+            .cloned();
+        if let Some(lut) = &lut {
+            info!("LUT {}", lut.name);
+            self.set_lut(&lut.lut)?;
+        }
+        let report = self.turn_on().await?;
+        Ok(RefreshReport {
+            temperatures,
+            lut: lut.map(|lut| lut.name),
+            ..report
+        })
+    }
+
+    /// Wait for the given controllers to be done with `command`.
+    async fn wait_idle(&mut self, controllers: &[Controller], command: u8) -> Result<()> {
+        if let Some(controller) = self
+            .interface
+            .wait_idle(controllers, self.revision.idle_high(), self.timeout)
+            .await?
+        {
+            return Err(Error::Timeout {
+                controller: Some(controller),
+                command: Some(command),
+            });
+        }
         Ok(())
     }
 
     pub fn sleep(&mut self) -> Result<()> {
         // power off
-        self.m1s1m2s2_send_command(0x02)?;
-        thread::sleep(Duration::from_millis(300));
+        self.send_command(M1S1M2S2, 0x02)?;
+        self.interface.delay(Duration::from_millis(300));
 
         // deep sleep
-        self.m1s1m2s2_send_command(0x07)?;
-        self.m1s1m2s2_send_data(&[0xA5])?;
-        thread::sleep(Duration::from_millis(300));
+        self.send_command(M1S1M2S2, 0x07)?;
+        self.send_data(M1S1M2S2, &[0xA5])?;
+        self.interface.delay(Duration::from_millis(300));
         Ok(())
     }
 
     pub fn reset(&mut self) -> Result<()> {
-        self.m1s1_rst.set_high()?;
-        if let Some(pin) = &mut self.m2s2_rst {
-            pin.set_high()?;
-        }
-        thread::sleep(Duration::from_millis(200));
-        self.m1s1_rst.set_low()?;
-        if let Some(pin) = &mut self.m2s2_rst {
-            pin.set_low()?;
-        }
-        thread::sleep(Duration::from_millis(5));
-        self.m1s1_rst.set_high()?;
-        if let Some(pin) = &mut self.m2s2_rst {
-            pin.set_high()?;
-        }
-        thread::sleep(Duration::from_millis(200));
+        self.interface.set_reset(true)?;
+        self.interface.delay(Duration::from_millis(200));
+        self.interface.set_reset(false)?;
+        self.interface.delay(Duration::from_millis(5));
+        self.interface.set_reset(true)?;
+        self.interface.delay(Duration::from_millis(200));
         Ok(())
     }
 
-    fn m1_send_command(&mut self, reg: u8) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, Some(self.m1_cs.reborrow()), &spi_config())?;
-
-        self.m1s1_dc.set_low()?;
-        spi.write(&[reg])?;
-        Ok(())
+    fn send_command(&mut self, to: &[Controller], reg: u8) -> Result<()> {
+        self.interface.send_command(to, reg)
     }
-    fn m1_send_data(&mut self, data: &[u8]) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, Some(self.m1_cs.reborrow()), &spi_config())?;
-
-        self.m1s1_dc.set_high()?;
-        spi.write(data)?;
+    fn send_data(&mut self, to: &[Controller], data: &[u8]) -> Result<()> {
+        let start = Instant::now();
+        self.interface.send_data(to, data)?;
+        let elapsed = start.elapsed();
+        for &controller in to {
+            self.timings[controller_index(controller)].transfer += elapsed;
+        }
         Ok(())
     }
 
-    fn s1_send_command(&mut self, reg: u8) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, Some(self.s1_cs.reborrow()), &spi_config())?;
-
-        self.m1s1_dc.set_low()?;
-        spi.write(&[reg])?;
+    /// Write a full frame, in the format of inspiro-mate's `bwr-raw`: `white`
+    /// has a set bit for every white pixel, and `red` for every red one.
+    ///
+    /// Rows are `width / 8` bytes long, where `width` is the width of the
+    /// drawing after `rotation`. Call [`Epd::turn_on`] to show it.
+    pub fn display_frame(&mut self, white: &[u8], red: &[u8], rotation: Rotation) -> Result<()> {
+        check_length(white)?;
+        check_length(red)?;
+        self.send_command(M1S1M2S2, 0x10)?;
+        self.write_plane(white, false, rotation)?;
+        self.send_command(M1S1M2S2, 0x13)?;
+        self.write_plane(red, false, rotation)
+    }
+
+    /// Write a full frame drawn on `frame`, which must match the panel's size.
+    ///
+    /// Call [`Epd::turn_on`] to show it.
+    pub fn display_framebuffer(&mut self, frame: &Framebuffer) -> Result<()> {
+        if frame.native_dimensions() != (EPD_WIDTH, EPD_HEIGHT) {
+            return Err(Error::InvalidDimensions {
+                expected: (EPD_WIDTH, EPD_HEIGHT),
+                actual: frame.native_dimensions(),
+            });
+        }
+        Display::write_frame(self, frame.black(), frame.red())
+    }
+
+    /// Write a full-frame plane, splitting it across the four controllers.
+    ///
+    /// The plane is rotated by `rotation` relative to the panel, and inverted
+    /// if `invert` is set.
+    fn write_plane(&mut self, plane: &[u8], invert: bool, rotation: Rotation) -> Result<()> {
+        let quadrants = [
+            (S2, 0, 0, LEFT_WIDTH),
+            (M2, LEFT_WIDTH, 0, RIGHT_WIDTH),
+            (M1, 0, HALF_HEIGHT, LEFT_WIDTH),
+            (S1, LEFT_WIDTH, HALF_HEIGHT, RIGHT_WIDTH),
+        ];
+        let source_width = match rotation {
+            Rotation::Rotate0 | Rotation::Rotate180 => EPD_WIDTH,
+            Rotation::Rotate90 | Rotation::Rotate270 => EPD_HEIGHT,
+        };
+        let mut buf = [0; RIGHT_BYTES];
+        for (controller, x0, y0, width) in quadrants {
+            let buf = &mut buf[..width / 8];
+            for y in y0..y0 + HALF_HEIGHT {
+                if rotation == Rotation::Rotate0 {
+                    buf.copy_from_slice(&plane[x0 / 8 + y * ROW_BYTES..][..width / 8]);
+                } else {
+                    for (i, byte) in buf.iter_mut().enumerate() {
+                        *byte = (0..8).fold(0, |byte, bit| {
+                            let (x, y) =
+                                rotation.to_drawing(x0 + i * 8 + bit, y, EPD_WIDTH, EPD_HEIGHT);
+                            let set = plane[(x + y * source_width) / 8] & (0x80 >> (x % 8)) != 0;
+                            byte | u8::from(set) << (7 - bit)
+                        });
+                    }
+                }
+                if invert {
+                    buf.iter_mut().for_each(|b| *b = !*b);
+                }
+                self.send_data(controller, buf)?;
+            }
+        }
         Ok(())
     }
-    fn s1_send_data(&mut self, data: &[u8]) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, Some(self.s1_cs.reborrow()), &spi_config())?;
 
-        self.m1s1_dc.set_high()?;
-        spi.write(data)?;
+    /// Upload the waveforms used by the following refreshes.
+    ///
+    /// V2 panels switch from their OTP tables to these ones until the next
+    /// [`Epd::init`].
+    pub fn set_lut(&mut self, lut: &Lut) -> Result<()> {
+        if self.revision == Revision::V2 {
+            // panel setting, LUTs from registers
+            self.send_command(M1S1M2S2, 0x00)?;
+            self.send_data(M1S1, &[0x2f])?;
+            self.send_data(M2S2, &[0x23])?;
+        }
+        for (register, waveform) in (0x20..).zip(lut.waveforms()) {
+            self.send_command(M1S1M2S2, register)?;
+            self.send_data(M1S1M2S2, &lut::encode(waveform))?;
+        }
         Ok(())
     }
 
-    fn m2_send_command(&mut self, reg: u8) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, Some(self.m2_cs.reborrow()), &spi_config())?;
-
-        self.m2s2_dc.set_low()?;
-        spi.write(&[reg])?;
-        Ok(())
+    /// Refresh using `lut`, which stays in use afterwards.
+    pub async fn turn_on_with(&mut self, lut: &Lut) -> Result<RefreshReport> {
+        self.set_lut(lut)?;
+        self.turn_on().await
     }
-    fn m2_send_data(&mut self, data: &[u8]) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, Some(self.m2_cs.reborrow()), &spi_config())?;
+}
 
-        self.m2s2_dc.set_high()?;
-        spi.write(data)?;
+/// A plane of a controller's memory being written, started by [`Epd::begin`].
+pub struct PlaneWriter<'a, I> {
+    epd: &'a mut Epd<I>,
+    controller: Controller,
+    remaining: usize,
+}
+impl<I: CascadeInterface> PlaneWriter<'_, I> {
+    /// Send the next chunk of the plane.
+    pub fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        if chunk.len() > self.remaining {
+            let expected = quadrant_bytes(self.controller);
+            return Err(Error::InvalidLength {
+                expected,
+                actual: expected - self.remaining + chunk.len(),
+            });
+        }
+        self.epd.send_data(&[self.controller], chunk)?;
+        self.remaining -= chunk.len();
         Ok(())
     }
 
-    fn s2_send_command(&mut self, reg: u8) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, Some(self.s2_cs.reborrow()), &spi_config())?;
-
-        self.m2s2_dc.set_low()?;
-        spi.write(&[reg])?;
-        Ok(())
+    /// Bytes left to complete the plane.
+    pub fn remaining(&self) -> usize {
+        self.remaining
     }
-    fn s2_send_data(&mut self, data: &[u8]) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, Some(self.s2_cs.reborrow()), &spi_config())?;
 
-        self.m2s2_dc.set_high()?;
-        spi.write(data)?;
+    /// Check that the whole plane was written.
+    pub fn end(self) -> Result<()> {
+        if self.remaining != 0 {
+            let expected = quadrant_bytes(self.controller);
+            return Err(Error::InvalidLength {
+                expected,
+                actual: expected - self.remaining,
+            });
+        }
         Ok(())
     }
+}
 
-    fn m1s2_send_data(&mut self, data: &[u8]) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, AnyOutputPin::none(), &spi_config())?;
-        let mut m1_cs = PinDriver::output(self.m1_cs.reborrow())?;
-        let mut s2_cs = PinDriver::output(self.s2_cs.reborrow())?;
-
-        self.m1s1_dc.set_high()?;
-        self.m2s2_dc.set_high()?;
-        m1_cs.set_low()?;
-        s2_cs.set_low()?;
-        spi.write(data)?;
-        m1_cs.set_high()?;
-        s2_cs.set_high()?;
-        Ok(())
+impl<I: CascadeInterface> Display for Epd<I> {
+    fn width(&self) -> usize {
+        EPD_WIDTH
     }
-    fn s1m2_send_data(&mut self, data: &[u8]) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, AnyOutputPin::none(), &spi_config())?;
-        let mut s1_cs = PinDriver::output(self.s1_cs.reborrow())?;
-        let mut m1_cs = PinDriver::output(self.m1_cs.reborrow())?;
-
-        self.m1s1_dc.set_high()?;
-        self.m2s2_dc.set_high()?;
-        s1_cs.set_low()?;
-        m1_cs.set_low()?;
-        spi.write(data)?;
-        s1_cs.set_high()?;
-        m1_cs.set_high()?;
-        Ok(())
+    fn height(&self) -> usize {
+        EPD_HEIGHT
+    }
+    fn inks(&self) -> &'static [Ink] {
+        &[Ink::Black, Ink::White, Ink::Red]
     }
 
-    fn m1s1_send_data(&mut self, data: &[u8]) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, AnyOutputPin::none(), &spi_config())?;
-        let mut m1_cs = PinDriver::output(self.m1_cs.reborrow())?;
-        let mut s1_cs = PinDriver::output(self.s1_cs.reborrow())?;
-
-        self.m1s1_dc.set_high()?;
-        m1_cs.set_low()?;
-        s1_cs.set_low()?;
-        spi.write(data)?;
-        m1_cs.set_high()?;
-        s1_cs.set_high()?;
-        Ok(())
+    fn init(&mut self) -> Result<()> {
+        Epd::init(self)
     }
-    fn m2s2_send_data(&mut self, data: &[u8]) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, AnyOutputPin::none(), &spi_config())?;
-        let mut m2_cs = PinDriver::output(self.m2_cs.reborrow())?;
-        let mut s2_cs = PinDriver::output(self.s2_cs.reborrow())?;
-
-        self.m2s2_dc.set_high()?;
-        m2_cs.set_low()?;
-        s2_cs.set_low()?;
-        spi.write(data)?;
-        m2_cs.set_high()?;
-        s2_cs.set_high()?;
-        Ok(())
+    fn write_frame(&mut self, black: &[u8], red: &[u8]) -> Result<()> {
+        check_length(black)?;
+        check_length(red)?;
+        self.send_command(M1S1M2S2, 0x10)?;
+        self.write_plane(black, false, Rotation::Rotate0)?;
+        // The controllers use set bits for red.
+        self.send_command(M1S1M2S2, 0x13)?;
+        self.write_plane(red, true, Rotation::Rotate0)
     }
-
-    fn m1m2_send_command(&mut self, reg: u8) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, AnyOutputPin::none(), &spi_config())?;
-        let mut m1_cs = PinDriver::output(self.m1_cs.reborrow())?;
-        let mut m2_cs = PinDriver::output(self.m2_cs.reborrow())?;
-
-        self.m1s1_dc.set_low()?;
-        self.m2s2_dc.set_low()?;
-        m1_cs.set_low()?;
-        m2_cs.set_low()?;
-        spi.write(&[reg])?;
-        m1_cs.set_high()?;
-        m2_cs.set_high()?;
-        Ok(())
+    fn start_refresh(&mut self) -> Result<()> {
+        Epd::start_refresh(self)
     }
-    fn m1m2_send_data(&mut self, data: &[u8]) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, AnyOutputPin::none(), &spi_config())?;
-        let mut m1_cs = PinDriver::output(self.m1_cs.reborrow())?;
-        let mut m2_cs = PinDriver::output(self.m2_cs.reborrow())?;
-
-        self.m1s1_dc.set_high()?;
-        self.m2s2_dc.set_high()?;
-        m1_cs.set_low()?;
-        m2_cs.set_low()?;
-        spi.write(data)?;
-        m1_cs.set_high()?;
-        m2_cs.set_high()?;
-        Ok(())
+    fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        block_on(Epd::finish_refresh(self))
     }
-
-    fn m1s1m2s2_send_command(&mut self, reg: u8) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, AnyOutputPin::none(), &spi_config())?;
-        let mut m1_cs = PinDriver::output(self.m1_cs.reborrow())?;
-        let mut s1_cs = PinDriver::output(self.s1_cs.reborrow())?;
-        let mut m2_cs = PinDriver::output(self.m2_cs.reborrow())?;
-        let mut s2_cs = PinDriver::output(self.s2_cs.reborrow())?;
-
-        self.m1s1_dc.set_low()?;
-        self.m2s2_dc.set_low()?;
-        m1_cs.set_low()?;
-        s1_cs.set_low()?;
-        m2_cs.set_low()?;
-        s2_cs.set_low()?;
-        spi.write(&[reg])?;
-        m1_cs.set_high()?;
-        s1_cs.set_high()?;
-        m2_cs.set_high()?;
-        s2_cs.set_high()?;
+    fn clear(&mut self) -> Result<()> {
+        Epd::clear(self)?;
+        self.refresh()?;
         Ok(())
     }
-    fn m1s1m2s2_send_data(&mut self, data: &[u8]) -> Result<()> {
-        let mut spi = SpiDeviceDriver::new(&self.spi, AnyOutputPin::none(), &spi_config())?;
-        let mut m1_cs = PinDriver::output(self.m1_cs.reborrow())?;
-        let mut s1_cs = PinDriver::output(self.s1_cs.reborrow())?;
-        let mut m2_cs = PinDriver::output(self.m2_cs.reborrow())?;
-        let mut s2_cs = PinDriver::output(self.s2_cs.reborrow())?;
-
-        self.m1s1_dc.set_high()?;
-        self.m2s2_dc.set_high()?;
-        m1_cs.set_low()?;
-        s1_cs.set_low()?;
-        m2_cs.set_low()?;
-        s2_cs.set_low()?;
-        spi.write(data)?;
-        m1_cs.set_high()?;
-        s1_cs.set_high()?;
-        m2_cs.set_high()?;
-        s2_cs.set_high()?;
-        Ok(())
+    fn deep_clean(&mut self) -> Result<()> {
+        block_on(Epd::deep_clean(self))
     }
+    fn sleep(&mut self) -> Result<()> {
+        Epd::sleep(self)
+    }
+    fn diagnose(&mut self) -> Result<Vec<Health>> {
+        Epd::diagnose(self)
+    }
+}
 
-    fn set_lut(&mut self) -> Result<()> {
-        self.m1s1m2s2_send_command(0x20)?; // vcom
-        self.m1s1m2s2_send_data(&LUT_VCOM1)?;
-
-        self.m1s1m2s2_send_command(0x21)?; // red not use
-        self.m1s1m2s2_send_data(&LUT_WW1)?;
-
-        self.m1s1m2s2_send_command(0x22)?; // bw r
-        self.m1s1m2s2_send_data(&LUT_BW1)?; // bw=r
-
-        self.m1s1m2s2_send_command(0x23)?; // wb w
-        self.m1s1m2s2_send_data(&LUT_WB1)?; // wb=w
-
-        self.m1s1m2s2_send_command(0x24)?; // bb b
-        self.m1s1m2s2_send_data(&LUT_BB1)?; // bb=b
-
-        self.m1s1m2s2_send_command(0x25)?; // bb b
-        self.m1s1m2s2_send_data(&LUT_WW1)?; // bb=b
+/// Empty timings of each controller, in the order of [`Controller::ALL`].
+fn no_timings() -> [Timings; 4] {
+    Controller::ALL.map(|controller| Timings {
+        controller: Some(controller),
+        ..Default::default()
+    })
+}
 
-        Ok(())
-    }
+fn controller_index(controller: Controller) -> usize {
+    Controller::ALL
+        .iter()
+        .position(|&c| c == controller)
+        .expect("all controllers are listed")
 }
 
-const LUT_VCOM1: [u8; 60] = [
-    0x00, 0x10, 0x10, 0x01, 0x08, 0x01, 0x00, 0x06, 0x01, 0x06, 0x01, 0x05, 0x00, 0x08, 0x01, 0x08,
-    0x01, 0x06, 0x00, 0x06, 0x01, 0x06, 0x01, 0x05, 0x00, 0x05, 0x01, 0x1E, 0x0F, 0x06, 0x00, 0x05,
-    0x01, 0x1E, 0x0F, 0x01, 0x00, 0x04, 0x05, 0x08, 0x08, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-];
-const LUT_WW1: [u8; 60] = [
-    0x91, 0x10, 0x10, 0x01, 0x08, 0x01, 0x04, 0x06, 0x01, 0x06, 0x01, 0x05, 0x84, 0x08, 0x01, 0x08,
-    0x01, 0x06, 0x80, 0x06, 0x01, 0x06, 0x01, 0x05, 0x00, 0x05, 0x01, 0x1E, 0x0F, 0x06, 0x00, 0x05,
-    0x01, 0x1E, 0x0F, 0x01, 0x08, 0x04, 0x05, 0x08, 0x08, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-];
-const LUT_BW1: [u8; 60] = [
-    0xA8, 0x10, 0x10, 0x01, 0x08, 0x01, 0x84, 0x06, 0x01, 0x06, 0x01, 0x05, 0x84, 0x08, 0x01, 0x08,
-    0x01, 0x06, 0x86, 0x06, 0x01, 0x06, 0x01, 0x05, 0x8C, 0x05, 0x01, 0x1E, 0x0F, 0x06, 0x8C, 0x05,
-    0x01, 0x1E, 0x0F, 0x01, 0xF0, 0x04, 0x05, 0x08, 0x08, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-];
-const LUT_WB1: [u8; 60] = [
-    0x91, 0x10, 0x10, 0x01, 0x08, 0x01, 0x04, 0x06, 0x01, 0x06, 0x01, 0x05, 0x84, 0x08, 0x01, 0x08,
-    0x01, 0x06, 0x80, 0x06, 0x01, 0x06, 0x01, 0x05, 0x00, 0x05, 0x01, 0x1E, 0x0F, 0x06, 0x00, 0x05,
-    0x01, 0x1E, 0x0F, 0x01, 0x08, 0x04, 0x05, 0x08, 0x08, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-];
-const LUT_BB1: [u8; 60] = [
-    0x92, 0x10, 0x10, 0x01, 0x08, 0x01, 0x80, 0x06, 0x01, 0x06, 0x01, 0x05, 0x84, 0x08, 0x01, 0x08,
-    0x01, 0x06, 0x04, 0x06, 0x01, 0x06, 0x01, 0x05, 0x00, 0x05, 0x01, 0x1E, 0x0F, 0x06, 0x00, 0x05,
-    0x01, 0x1E, 0x0F, 0x01, 0x01, 0x04, 0x05, 0x08, 0x08, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-];
-
-fn spi_config() -> SpiConfig {
-    SpiConfig::new()
+fn check_length(plane: &[u8]) -> Result<()> {
+    if plane.len() != FRAME_BYTES {
+        return Err(Error::InvalidLength {
+            expected: FRAME_BYTES,
+            actual: plane.len(),
+        });
+    }
+    Ok(())
 }
@@ -0,0 +1,213 @@
+//! Waveform tables of the 12.48" controllers.
+//!
+//! A [`Lut`] drives every transition of a refresh. Its tables are uploaded to
+//! registers 0x20 to 0x25, and can be stored in a file in that order, see
+//! [`Lut::from_bytes`].
+
+use crate::{Error, Result};
+use std::{fs, path::Path};
+
+/// Number of groups of a waveform.
+pub const GROUPS: usize = 10;
+/// Size of an encoded waveform.
+pub const WAVEFORM_BYTES: usize = GROUPS * 6;
+/// Size of an encoded [`Lut`].
+pub const LUT_BYTES: usize = 6 * WAVEFORM_BYTES;
+
+/// Groups driving the red particles in the stock tables.
+const RED_GROUPS: [usize; 2] = [4, 5];
+
+/// A group of a waveform: up to four steps, each at a voltage level and
+/// lasting a number of frames, repeated as a whole.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Group {
+    /// Level of each step, two bits each, the first step in the most
+    /// significant bits.
+    pub levels: u8,
+    pub frames: [u8; 4],
+    pub repeat: u8,
+}
+impl Group {
+    /// Frames the group lasts, repeats included.
+    pub fn duration(&self) -> u32 {
+        self.frames.iter().map(|&f| u32::from(f)).sum::<u32>() * u32::from(self.repeat)
+    }
+}
+
+/// Groups applied one after the other during a refresh.
+pub type Waveform = [Group; GROUPS];
+
+/// Waveforms of a refresh.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lut {
+    /// Common electrode (register 0x20).
+    pub vcom: Waveform,
+    /// White to white, unused in tri-color mode (register 0x21).
+    pub ww: Waveform,
+    /// Pixels turning red (register 0x22).
+    pub red: Waveform,
+    /// Pixels turning white (register 0x23).
+    pub white: Waveform,
+    /// Pixels turning black (register 0x24).
+    pub black: Waveform,
+    /// Border (register 0x25).
+    pub border: Waveform,
+}
+impl Default for Lut {
+    fn default() -> Self {
+        Self::stock()
+    }
+}
+impl Lut {
+    /// Waveshare's tables.
+    pub fn stock() -> Self {
+        Self {
+            vcom: decode(&LUT_VCOM1),
+            ww: decode(&LUT_WW1),
+            red: decode(&LUT_BW1),
+            white: decode(&LUT_WB1),
+            black: decode(&LUT_BB1),
+            border: decode(&LUT_WW1),
+        }
+    }
+
+    /// The stock tables with fewer repeats, trading contrast and ghosting for
+    /// about half the refresh time.
+    pub fn fast() -> Self {
+        let mut lut = Self::stock();
+        for group in lut.waveforms_mut().into_iter().flatten() {
+            group.repeat = group.repeat.div_ceil(2);
+        }
+        lut
+    }
+
+    /// Like [`Lut::fast`], but skipping the red phases. Red pixels aren't
+    /// driven to red, so it only suits black and white frames.
+    pub fn fast_bw() -> Self {
+        let mut lut = Self::fast();
+        for waveform in lut.waveforms_mut() {
+            for group in RED_GROUPS {
+                waveform[group] = Group::default();
+            }
+        }
+        lut
+    }
+
+    /// Decode tables in register order, as produced by [`Lut::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != LUT_BYTES {
+            return Err(Error::InvalidLength {
+                expected: LUT_BYTES,
+                actual: bytes.len(),
+            });
+        }
+        let mut waveforms = bytes.chunks_exact(WAVEFORM_BYTES).map(decode);
+        let mut next = || waveforms.next().unwrap();
+        Ok(Self {
+            vcom: next(),
+            ww: next(),
+            red: next(),
+            white: next(),
+            black: next(),
+            border: next(),
+        })
+    }
+
+    /// Load tables saved with [`Lut::save`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(fs::write(path, self.to_bytes())?)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.waveforms().into_iter().flat_map(encode).collect()
+    }
+
+    /// Frames the refresh lasts.
+    pub fn duration(&self) -> u32 {
+        self.waveforms()
+            .into_iter()
+            .map(|waveform| waveform.iter().map(Group::duration).sum())
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Waveforms in register order, starting from 0x20.
+    pub fn waveforms(&self) -> [&Waveform; 6] {
+        [
+            &self.vcom,
+            &self.ww,
+            &self.red,
+            &self.white,
+            &self.black,
+            &self.border,
+        ]
+    }
+
+    fn waveforms_mut(&mut self) -> [&mut Waveform; 6] {
+        [
+            &mut self.vcom,
+            &mut self.ww,
+            &mut self.red,
+            &mut self.white,
+            &mut self.black,
+            &mut self.border,
+        ]
+    }
+}
+
+fn decode(bytes: &[u8]) -> Waveform {
+    let mut waveform = Waveform::default();
+    for (group, bytes) in waveform.iter_mut().zip(bytes.chunks_exact(6)) {
+        *group = Group {
+            levels: bytes[0],
+            frames: [bytes[1], bytes[2], bytes[3], bytes[4]],
+            repeat: bytes[5],
+        };
+    }
+    waveform
+}
+
+pub(super) fn encode(waveform: &Waveform) -> Vec<u8> {
+    waveform
+        .iter()
+        .flat_map(|group| {
+            let [a, b, c, d] = group.frames;
+            [group.levels, a, b, c, d, group.repeat]
+        })
+        .collect()
+}
+
+const LUT_VCOM1: [u8; 60] = [
+    0x00, 0x10, 0x10, 0x01, 0x08, 0x01, 0x00, 0x06, 0x01, 0x06, 0x01, 0x05, 0x00, 0x08, 0x01, 0x08,
+    0x01, 0x06, 0x00, 0x06, 0x01, 0x06, 0x01, 0x05, 0x00, 0x05, 0x01, 0x1E, 0x0F, 0x06, 0x00, 0x05,
+    0x01, 0x1E, 0x0F, 0x01, 0x00, 0x04, 0x05, 0x08, 0x08, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const LUT_WW1: [u8; 60] = [
+    0x91, 0x10, 0x10, 0x01, 0x08, 0x01, 0x04, 0x06, 0x01, 0x06, 0x01, 0x05, 0x84, 0x08, 0x01, 0x08,
+    0x01, 0x06, 0x80, 0x06, 0x01, 0x06, 0x01, 0x05, 0x00, 0x05, 0x01, 0x1E, 0x0F, 0x06, 0x00, 0x05,
+    0x01, 0x1E, 0x0F, 0x01, 0x08, 0x04, 0x05, 0x08, 0x08, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const LUT_BW1: [u8; 60] = [
+    0xA8, 0x10, 0x10, 0x01, 0x08, 0x01, 0x84, 0x06, 0x01, 0x06, 0x01, 0x05, 0x84, 0x08, 0x01, 0x08,
+    0x01, 0x06, 0x86, 0x06, 0x01, 0x06, 0x01, 0x05, 0x8C, 0x05, 0x01, 0x1E, 0x0F, 0x06, 0x8C, 0x05,
+    0x01, 0x1E, 0x0F, 0x01, 0xF0, 0x04, 0x05, 0x08, 0x08, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const LUT_WB1: [u8; 60] = [
+    0x91, 0x10, 0x10, 0x01, 0x08, 0x01, 0x04, 0x06, 0x01, 0x06, 0x01, 0x05, 0x84, 0x08, 0x01, 0x08,
+    0x01, 0x06, 0x80, 0x06, 0x01, 0x06, 0x01, 0x05, 0x00, 0x05, 0x01, 0x1E, 0x0F, 0x06, 0x00, 0x05,
+    0x01, 0x1E, 0x0F, 0x01, 0x08, 0x04, 0x05, 0x08, 0x08, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const LUT_BB1: [u8; 60] = [
+    0x92, 0x10, 0x10, 0x01, 0x08, 0x01, 0x80, 0x06, 0x01, 0x06, 0x01, 0x05, 0x84, 0x08, 0x01, 0x08,
+    0x01, 0x06, 0x04, 0x06, 0x01, 0x06, 0x01, 0x05, 0x00, 0x05, 0x01, 0x1E, 0x0F, 0x06, 0x00, 0x05,
+    0x01, 0x1E, 0x0F, 0x01, 0x01, 0x04, 0x05, 0x08, 0x08, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
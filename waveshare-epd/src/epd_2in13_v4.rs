@@ -0,0 +1,148 @@
+//! 2.13" V4 black and white
+
+use crate::{
+    driver::{check_length, Driver},
+    interface::Interface,
+    Display, Health, Ink, RefreshReport, Result,
+};
+use futures::executor::block_on;
+use log::info;
+use std::time::Duration;
+
+pub const EPD_WIDTH: usize = 122;
+pub const EPD_HEIGHT: usize = 250;
+/// Rows are padded to whole bytes.
+const FRAME_BYTES: usize = EPD_WIDTH.div_ceil(8) * EPD_HEIGHT;
+
+/// Default limit on how long the panel may stay busy.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct Epd<I> {
+    driver: Driver<I>,
+}
+impl<I: Interface> Epd<I> {
+    pub fn with_interface(interface: I) -> Self {
+        Self {
+            driver: Driver::new(interface, false, DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// Limit how long each busy wait may take.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.driver.timeout = timeout;
+        self
+    }
+
+    pub fn interface(&self) -> &I {
+        &self.driver.interface
+    }
+
+    pub fn interface_mut(&mut self) -> &mut I {
+        &mut self.driver.interface
+    }
+
+    pub fn init(&mut self) -> Result<()> {
+        self.reset()?;
+        let d = &mut self.driver;
+        d.read_busy()?;
+        d.command(0x12, &[])?; // SW Reset
+        d.read_busy()?;
+        d.command(0x01, &[0xF9, 0x00, 0x00])?; // Driver Output Control: 250 gates
+        d.command(0x11, &[0x03])?; // Data Entry Mode: X then Y increasing
+        d.command(0x44, &[0x00, 0x0F])?; // RAM X Start/End: bytes 0 to 15
+        d.command(0x45, &[0x00, 0x00, 0xF9, 0x00])?; // RAM Y Start/End: 0 to 249
+        d.command(0x4E, &[0x00])?; // RAM X Counter
+        d.command(0x4F, &[0x00, 0x00])?; // RAM Y Counter
+        d.command(0x3C, &[0x05])?; // Border Waveform
+        d.command(0x21, &[0x00, 0x80])?; // Display Update Control
+        d.command(0x18, &[0x80])?; // Internal Temperature Sensor
+        d.read_busy()?;
+        Ok(())
+    }
+
+    /// Write and show `black`, a full frame with cleared bits where black.
+    pub fn display(&mut self, black: &[u8]) -> Result<RefreshReport> {
+        self.write(black)?;
+        block_on(self.turn_on())
+    }
+
+    /// Write `black` to the panel's memory.
+    pub fn write(&mut self, black: &[u8]) -> Result<()> {
+        check_length(black, FRAME_BYTES)?;
+        self.driver.command(0x24, black) // Write RAM (BW)
+    }
+
+    /// Show the frame that was last written.
+    pub async fn turn_on(&mut self) -> Result<RefreshReport> {
+        self.start_refresh()?;
+        self.finish_refresh().await
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        self.driver.command(0x22, &[0xF7])?; // Display Update Control 2
+        self.driver.start_refresh(0x20, &[]) // Master Activation
+    }
+    async fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        self.driver.finish_refresh().await?;
+        Ok(self.driver.report())
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.driver.command(0x24, &[0xFF; FRAME_BYTES])?;
+        block_on(self.turn_on())?;
+        Ok(())
+    }
+
+    pub fn sleep(&mut self) -> Result<()> {
+        self.driver.command(0x10, &[0x01])?; // Deep Sleep Mode 1
+        self.driver.delay(100);
+        Ok(())
+    }
+
+    /// Reset the panel and check the connection to the controller. The panel
+    /// must be initialized again before use.
+    pub fn diagnose(&mut self) -> Result<Health> {
+        self.reset()?;
+        let health = self.driver.diagnose()?;
+        info!("{health:?}");
+        Ok(health)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.driver
+            .reset(Duration::from_millis(20), Duration::from_millis(2))
+    }
+}
+
+impl<I: Interface> Display for Epd<I> {
+    fn width(&self) -> usize {
+        EPD_WIDTH
+    }
+    fn height(&self) -> usize {
+        EPD_HEIGHT
+    }
+    fn inks(&self) -> &'static [Ink] {
+        &[Ink::Black, Ink::White]
+    }
+
+    fn init(&mut self) -> Result<()> {
+        Epd::init(self)
+    }
+    fn write_frame(&mut self, black: &[u8], _red: &[u8]) -> Result<()> {
+        self.write(black)
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        Epd::start_refresh(self)
+    }
+    fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        block_on(Epd::finish_refresh(self))
+    }
+    fn clear(&mut self) -> Result<()> {
+        Epd::clear(self)
+    }
+    fn sleep(&mut self) -> Result<()> {
+        Epd::sleep(self)
+    }
+    fn diagnose(&mut self) -> Result<Vec<Health>> {
+        Epd::diagnose(self).map(|health| vec![health])
+    }
+}
@@ -0,0 +1,261 @@
+//! 2.7" black and white, in 4-level grayscale
+
+#[cfg(any(feature = "rpi", feature = "linux"))]
+use crate::config::Config;
+#[cfg(feature = "linux")]
+use crate::linux::LinuxInterface;
+#[cfg(feature = "rpi")]
+use crate::rpi::RpiInterface;
+use crate::{
+    driver::{check_length, Driver},
+    interface::Interface,
+    packing::{Depth, Packing, Rotation},
+    Display, Health, Ink, RefreshReport, Result,
+};
+use futures::executor::block_on;
+use image::GrayImage;
+use log::{debug, info, warn};
+use std::time::Duration;
+
+pub const EPD_WIDTH: usize = 176;
+pub const EPD_HEIGHT: usize = 264;
+/// Size of a grayscale frame, 2 bits per pixel.
+pub const GRAY_BUFFER_SIZE: usize = EPD_WIDTH / 4 * EPD_HEIGHT;
+/// Size of each of the 1-bit planes the controller takes.
+const PLANE_BYTES: usize = EPD_WIDTH / 8 * EPD_HEIGHT;
+
+/// Default limit on how long the panel may stay busy.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct Epd<I> {
+    driver: Driver<I>,
+}
+#[cfg(feature = "rpi")]
+impl Epd<RpiInterface> {
+    /// Wired as Waveshare's e-Paper HAT.
+    pub fn new() -> Result<Self> {
+        Self::with_config(&Config::default())
+    }
+
+    pub fn with_config(config: &Config) -> Result<Self> {
+        Ok(Self::with_interface(RpiInterface::with_config(config)?))
+    }
+}
+#[cfg(feature = "linux")]
+impl Epd<LinuxInterface> {
+    /// Open the panel wired as in `config`, through the Linux backend.
+    pub fn open(config: &Config) -> Result<Self> {
+        Ok(Self::with_interface(LinuxInterface::with_config(config)?))
+    }
+}
+impl<I: Interface> Epd<I> {
+    pub fn with_interface(interface: I) -> Self {
+        Self {
+            driver: Driver::new(interface, true, DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// Limit how long each busy wait may take.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.driver.timeout = timeout;
+        self
+    }
+
+    pub fn interface(&self) -> &I {
+        &self.driver.interface
+    }
+
+    pub fn interface_mut(&mut self) -> &mut I {
+        &mut self.driver.interface
+    }
+
+    /// Wake the panel up and load the grayscale waveforms.
+    pub fn init(&mut self) -> Result<()> {
+        self.reset()?;
+        let d = &mut self.driver;
+        d.command(0x01, &[0x03, 0x00, 0x2B, 0x2B, 0x13])?; // Power Setting
+        d.command(0x06, &[0x07, 0x07, 0x17])?; // Booster Soft Start
+        for param in [
+            [0x60, 0xA5],
+            [0x89, 0xA5],
+            [0x90, 0x00],
+            [0x93, 0x2A],
+            [0xA0, 0xA5],
+            [0xA1, 0x00],
+            [0x73, 0x41],
+        ] {
+            d.command(0xF8, &param)?; // Power Optimization
+        }
+        d.command(0x16, &[0x00])?; // Partial Display Refresh
+        d.command(0x04, &[])?; // Power ON
+        d.read_busy()?;
+        d.command(0x00, &[0xBF])?; // Panel Setting: KW, LUT from registers
+        d.command(0x30, &[0x90])?; // PLL Control: 100 Hz
+        d.command(0x61, &[0x00, 0xB0, 0x01, 0x08])?; // Resolution: 176x264
+        d.command(0x82, &[0x12])?; // VCOM_DC Setting
+        d.command(0x50, &[0x97])?; // VCOM and Data Interval
+        d.command(0x20, &LUT_VCOM)?;
+        d.command(0x21, &LUT_WW)?;
+        d.command(0x22, &LUT_BW)?;
+        d.command(0x23, &LUT_WB)?;
+        d.command(0x24, &LUT_BB)?;
+        d.command(0x25, &LUT_WW)?;
+        Ok(())
+    }
+
+    /// Write and show `frame`, packed by [`pack_buffer`].
+    pub fn display(&mut self, frame: &[u8]) -> Result<RefreshReport> {
+        self.write(frame)?;
+        block_on(self.turn_on())
+    }
+
+    /// Write `frame`, packed by [`pack_buffer`], to the panel's memory.
+    pub fn write(&mut self, frame: &[u8]) -> Result<()> {
+        check_length(frame, GRAY_BUFFER_SIZE)?;
+        // The controller takes the high and the low bit of each level as two
+        // planes, which the waveforms combine.
+        let (high, low) = split_levels(frame);
+        self.driver.command(0x10, &high)?; // Old Data
+        self.driver.command(0x13, &low) // New Data
+    }
+
+    /// Show the frame that was last written.
+    pub async fn turn_on(&mut self) -> Result<RefreshReport> {
+        self.start_refresh()?;
+        self.finish_refresh().await
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        self.driver.start_refresh(0x12, &[])?; // Display Refresh
+        self.driver.delay(200);
+        Ok(())
+    }
+    async fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        self.driver.finish_refresh().await?;
+        Ok(self.driver.report())
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.driver.command(0x10, &[0xFF; PLANE_BYTES])?;
+        self.driver.command(0x13, &[0xFF; PLANE_BYTES])?;
+        block_on(self.turn_on())?;
+        Ok(())
+    }
+
+    pub fn sleep(&mut self) -> Result<()> {
+        self.driver.command(0x50, &[0xF7])?; // VCOM and Data Interval: floating border
+        self.driver.command(0x02, &[])?; // Power OFF
+        self.driver.read_busy()?;
+        self.driver.command(0x07, &[0xA5]) // Deep Sleep
+    }
+
+    /// Reset the panel and check the connection to the controller. The panel
+    /// must be initialized again before use.
+    pub fn diagnose(&mut self) -> Result<Health> {
+        self.reset()?;
+        let health = self.driver.diagnose()?;
+        info!("{health:?}");
+        Ok(health)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.driver
+            .reset(Duration::from_millis(200), Duration::from_millis(10))
+    }
+}
+
+impl<I: Interface> Display for Epd<I> {
+    fn width(&self) -> usize {
+        EPD_WIDTH
+    }
+    fn height(&self) -> usize {
+        EPD_HEIGHT
+    }
+    fn inks(&self) -> &'static [Ink] {
+        &[Ink::Black, Ink::White, Ink::DarkGray, Ink::LightGray]
+    }
+
+    fn init(&mut self) -> Result<()> {
+        Epd::init(self)
+    }
+    /// Only black can be written as a plane, grays need [`Epd::write`].
+    fn write_frame(&mut self, black: &[u8], _red: &[u8]) -> Result<()> {
+        check_length(black, PLANE_BYTES)?;
+        // Black is level 0 and white level 3, both of whose bits match.
+        self.driver.command(0x10, black)?;
+        self.driver.command(0x13, black)
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        Epd::start_refresh(self)
+    }
+    fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        block_on(Epd::finish_refresh(self))
+    }
+    fn clear(&mut self) -> Result<()> {
+        Epd::clear(self)
+    }
+    fn sleep(&mut self) -> Result<()> {
+        Epd::sleep(self)
+    }
+    fn diagnose(&mut self) -> Result<Vec<Health>> {
+        Epd::diagnose(self).map(|health| vec![health])
+    }
+}
+
+/// Split a 2 bits per pixel frame into the planes of its high and low bits.
+fn split_levels(frame: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    frame
+        .chunks(2)
+        .map(|pair| {
+            let levels = u16::from_be_bytes([pair[0], pair[1]]);
+            let (mut high, mut low) = (0, 0);
+            for pixel in 0..8 {
+                let level = levels >> (14 - 2 * pixel) & 0b11;
+                high |= u8::from(level & 0b10 != 0) << (7 - pixel);
+                low |= u8::from(level & 0b01 != 0) << (7 - pixel);
+            }
+            (high, low)
+        })
+        .unzip()
+}
+
+/// Pack `image`, quantized to 4 levels of gray, for the panel, vertical or
+/// horizontal. Horizontal images get rotated clockwise.
+pub fn pack_buffer(image: &GrayImage) -> Result<Vec<u8>> {
+    let packing = Packing::new(EPD_WIDTH, EPD_HEIGHT).with_depth(Depth::Gray4);
+    let packing = if image.dimensions() == (EPD_HEIGHT as u32, EPD_WIDTH as u32) {
+        debug!("Horizontal");
+        packing.with_rotation(Rotation::Rotate270)
+    } else {
+        debug!("Vertical");
+        packing
+    };
+    packing.pack(image).inspect_err(|_| {
+        warn!("Unsupported image size {:?}", image.dimensions());
+    })
+}
+
+const LUT_VCOM: [u8; 44] = [
+    0x00, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x01, 0x60, 0x14, 0x14, 0x00, 0x00, 0x01, 0x00, 0x14,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x13, 0x0A, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const LUT_WW: [u8; 42] = [
+    0x40, 0x0A, 0x00, 0x00, 0x00, 0x01, 0x90, 0x14, 0x14, 0x00, 0x00, 0x01, 0x10, 0x14, 0x0A, 0x00,
+    0x00, 0x01, 0xA0, 0x13, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const LUT_BW: [u8; 42] = [
+    0x40, 0x0A, 0x00, 0x00, 0x00, 0x01, 0x90, 0x14, 0x14, 0x00, 0x00, 0x01, 0x00, 0x14, 0x0A, 0x00,
+    0x00, 0x01, 0x99, 0x0C, 0x01, 0x03, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const LUT_WB: [u8; 42] = [
+    0x40, 0x0A, 0x00, 0x00, 0x00, 0x01, 0x90, 0x14, 0x14, 0x00, 0x00, 0x01, 0x00, 0x14, 0x0A, 0x00,
+    0x00, 0x01, 0x99, 0x0B, 0x04, 0x04, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const LUT_BB: [u8; 42] = [
+    0x80, 0x0A, 0x00, 0x00, 0x00, 0x01, 0x90, 0x14, 0x14, 0x00, 0x00, 0x01, 0x20, 0x14, 0x0A, 0x00,
+    0x00, 0x01, 0x50, 0x13, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
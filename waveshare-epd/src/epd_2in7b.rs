@@ -1,96 +1,172 @@
 //! 2.7" 3-color
 
-use crate::Result;
-use image::Pixel;
-use log::{debug, warn};
-use rppal::{
-    gpio::{Gpio, InputPin, OutputPin},
-    spi::{Bus, Mode, SlaveSelect, Spi},
+#[cfg(any(feature = "rpi", feature = "linux"))]
+use crate::config::Config;
+#[cfg(feature = "linux")]
+use crate::linux::LinuxInterface;
+#[cfg(feature = "rpi")]
+use crate::rpi::RpiInterface;
+use crate::{
+    clean,
+    driver::{check_length, Driver},
+    interface::Interface,
+    packing::{Packing, Rotation},
+    Display, Error, Health, Ink, RefreshReport, Result,
 };
-use std::time::Duration;
-use std::{iter::repeat, thread};
-
-const RST_PIN: u8 = 17;
-const DC_PIN: u8 = 25;
-const CS_PIN: u8 = 8;
-const BUSY_PIN: u8 = 24;
+use futures::executor::block_on;
+use log::{debug, info, warn};
+use std::iter::repeat;
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
 
 pub const EPD_WIDTH: usize = 176;
 pub const EPD_HEIGHT: usize = 264;
 const EPD_BUFFER_SIZE: usize = EPD_WIDTH * EPD_HEIGHT / 8;
 
-pub struct Epd {
-    reset_pin: OutputPin,
-    dc_pin: OutputPin,
-    cs_pin: OutputPin,
-    busy_pin: InputPin,
-    spi: Spi,
+/// Default limit on how long the panel may stay busy.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Temperatures the panel is rated to refresh at, in °C.
+pub const RATED_TEMPERATURE: RangeInclusive<i8> = 0..=50;
+
+/// A rectangle of the panel, in its native orientation.
+///
+/// `x` and `width` must be multiples of 8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Window {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
 }
-impl Epd {
+impl Window {
+    /// Size of each plane covering the window.
+    pub fn len(&self) -> usize {
+        self.width / 8 * self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn check(&self) -> Result<()> {
+        if !self.x.is_multiple_of(8)
+            || !self.width.is_multiple_of(8)
+            || self.x + self.width > EPD_WIDTH
+            || self.y + self.height > EPD_HEIGHT
+        {
+            return Err(Error::InvalidWindow {
+                x: self.x,
+                y: self.y,
+                width: self.width,
+                height: self.height,
+            });
+        }
+        Ok(())
+    }
+
+    /// Parameters of the partial commands.
+    fn params(&self) -> [u8; 8] {
+        let [x, y, width, height] = [self.x, self.y, self.width, self.height].map(|v| v as u16);
+        [
+            (x >> 8) as u8,
+            x as u8 & 0xF8,
+            (y >> 8) as u8,
+            y as u8,
+            (width >> 8) as u8,
+            width as u8 & 0xF8,
+            (height >> 8) as u8,
+            height as u8,
+        ]
+    }
+}
+
+pub struct Epd<I> {
+    driver: Driver<I>,
+    rated_temperature: RangeInclusive<i8>,
+}
+#[cfg(feature = "rpi")]
+impl Epd<RpiInterface> {
+    /// Wired as Waveshare's e-Paper HAT.
     pub fn new() -> Result<Self> {
-        let gpio = Gpio::new()?;
-        let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, 16_000_000, Mode::Mode0)?;
-        Ok(Self {
-            reset_pin: gpio.get(RST_PIN)?.into_output(),
-            dc_pin: gpio.get(DC_PIN)?.into_output(),
-            cs_pin: gpio.get(CS_PIN)?.into_output(),
-            busy_pin: gpio.get(BUSY_PIN)?.into_input(),
-            spi,
-        })
+        Self::with_config(&Config::default())
+    }
+
+    pub fn with_config(config: &Config) -> Result<Self> {
+        Ok(Self::with_interface(RpiInterface::with_config(config)?))
+    }
+}
+#[cfg(feature = "linux")]
+impl Epd<LinuxInterface> {
+    /// Open the panel wired as in `config`, through the Linux backend.
+    pub fn open(config: &Config) -> Result<Self> {
+        Ok(Self::with_interface(LinuxInterface::with_config(config)?))
+    }
+}
+impl<I: Interface> Epd<I> {
+    pub fn with_interface(interface: I) -> Self {
+        Self {
+            driver: Driver::new(interface, true, DEFAULT_TIMEOUT),
+            rated_temperature: RATED_TEMPERATURE,
+        }
+    }
+
+    /// Limit how long each busy wait may take.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.driver.timeout = timeout;
+        self
+    }
+
+    /// Temperatures to refuse refreshing outside of, in °C.
+    pub fn with_rated_temperature(mut self, range: RangeInclusive<i8>) -> Self {
+        self.rated_temperature = range;
+        self
+    }
+
+    pub fn interface(&self) -> &I {
+        &self.driver.interface
+    }
+
+    pub fn interface_mut(&mut self) -> &mut I {
+        &mut self.driver.interface
     }
 
     pub fn init(&mut self) -> Result<()> {
-        self.reset();
+        self.reset()?;
 
-        self.read_busy();
+        self.driver.read_busy()?;
 
-        self.send_command(0x4D)?;
-        self.send_data(0xAA)?;
+        self.driver.command(0x4D, &[0xAA])?;
 
-        self.send_command(0x87)?;
-        self.send_data(0x28)?;
+        self.driver.command(0x87, &[0x28])?;
 
-        self.send_command(0x84)?;
-        self.send_data(0x00)?;
+        self.driver.command(0x84, &[0x00])?;
 
-        self.send_command(0x83)?;
-        self.send_data(0x05)?;
+        self.driver.command(0x83, &[0x05])?;
 
-        self.send_command(0xA8)?;
-        self.send_data(0xDF)?;
+        self.driver.command(0xA8, &[0xDF])?;
 
-        self.send_command(0xA9)?;
-        self.send_data(0x05)?;
+        self.driver.command(0xA9, &[0x05])?;
 
-        self.send_command(0xB1)?;
-        self.send_data(0xE8)?;
+        self.driver.command(0xB1, &[0xE8])?;
 
-        self.send_command(0xAB)?;
-        self.send_data(0xA1)?;
+        self.driver.command(0xAB, &[0xA1])?;
 
-        self.send_command(0xB9)?;
-        self.send_data(0x10)?;
+        self.driver.command(0xB9, &[0x10])?;
 
-        self.send_command(0x88)?;
-        self.send_data(0x80)?;
+        self.driver.command(0x88, &[0x80])?;
 
-        self.send_command(0x90)?;
-        self.send_data(0x02)?;
+        self.driver.command(0x90, &[0x02])?;
 
-        self.send_command(0x86)?;
-        self.send_data(0x15)?;
+        self.driver.command(0x86, &[0x15])?;
 
-        self.send_command(0x91)?;
-        self.send_data(0x8D)?;
+        self.driver.command(0x91, &[0x8D])?;
 
-        self.send_command(0x50)?;
-        self.send_data(0x57)?;
+        self.driver.command(0x50, &[0x57])?;
 
-        self.send_command(0xAA)?;
-        self.send_data(0x0F)?;
+        self.driver.command(0xAA, &[0x0F])?;
 
-        self.send_command(0x00)?;
-        self.send_data(0x8F)?;
+        self.driver.command(0x00, &[0x8F])?;
         Ok(())
     }
 
@@ -98,106 +174,217 @@ impl Epd {
         &mut self,
         black: impl Iterator<Item = u8>,
         red: impl Iterator<Item = u8>,
-    ) -> Result<()> {
-        self.send_command(0x10)?;
-        for b in black.take(EPD_BUFFER_SIZE) {
-            self.send_data(b)?;
-        }
+    ) -> Result<RefreshReport> {
+        self.write(black, red)?;
+        block_on(self.turn_on())
+    }
 
-        self.send_command(0x13)?;
-        for r in red.take(EPD_BUFFER_SIZE) {
-            self.send_data(!r)?;
-        }
+    /// Update only `window`: `black` and `red` cover it, in the same format as
+    /// the full frame.
+    pub fn display_window(
+        &mut self,
+        window: &Window,
+        black: &[u8],
+        red: &[u8],
+    ) -> Result<RefreshReport> {
+        self.write_window(window, black, red)?;
+        self.refresh_window(window)
+    }
 
-        self.send_command(0x04)?; // Power ON
-        self.read_busy();
-        thread::sleep(Duration::from_millis(10));
-        self.send_command(0x12)?; // Display Refresh
-        self.read_busy();
-        thread::sleep(Duration::from_millis(10));
-        self.send_command(0x02)?; // Power OFF
-        self.read_busy();
-        thread::sleep(Duration::from_millis(20));
+    /// Write `black` and `red` to `window` of the panel's memory, leaving the
+    /// rest untouched.
+    pub fn write_window(&mut self, window: &Window, black: &[u8], red: &[u8]) -> Result<()> {
+        window.check()?;
+        check_length(black, window.len())?;
+        check_length(red, window.len())?;
+
+        // Partial Data Start Transmission 1
+        self.driver.command(0x14, &window.params())?;
+        self.driver.delay(2);
+        self.driver.send_data(black)?;
+        self.driver.delay(2);
+
+        // Partial Data Start Transmission 2
+        self.driver.command(0x15, &window.params())?;
+        self.driver.delay(2);
+        self.driver
+            .send_data(&red.iter().map(|r| !r).collect::<Vec<_>>())?;
+        self.driver.delay(2);
         Ok(())
     }
 
-    pub fn clear(&mut self) -> Result<()> {
-        self.display(repeat(0xFF), repeat(0xFF))
+    /// Refresh only `window`.
+    pub fn refresh_window(&mut self, window: &Window) -> Result<RefreshReport> {
+        window.check()?;
+        block_on(self.power_on())?;
+        // Partial Display Refresh
+        self.driver.start_refresh(0x16, &window.params())?;
+        block_on(self.power_off())
     }
 
-    pub fn sleep(&mut self) -> Result<()> {
-        self.send_command(0x07)?;
-        self.send_data(0xA5)?;
+    fn write(
+        &mut self,
+        black: impl Iterator<Item = u8>,
+        red: impl Iterator<Item = u8>,
+    ) -> Result<()> {
+        self.driver
+            .command(0x10, &black.take(EPD_BUFFER_SIZE).collect::<Vec<_>>())?;
+        self.driver.command(
+            0x13,
+            &red.take(EPD_BUFFER_SIZE).map(|r| !r).collect::<Vec<_>>(),
+        )
+    }
+
+    /// Show the frame that was last written.
+    ///
+    /// Waiting for the refresh doesn't block, if the interface supports it.
+    pub async fn turn_on(&mut self) -> Result<RefreshReport> {
+        self.start_refresh().await?;
+        self.finish_refresh().await
+    }
+    async fn start_refresh(&mut self) -> Result<()> {
+        self.power_on().await?;
+        self.driver.start_refresh(0x12, &[]) // Display Refresh
+    }
+    async fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        self.power_off().await
+    }
+    async fn power_on(&mut self) -> Result<()> {
+        let start = Instant::now();
+        self.driver.command(0x04, &[])?; // Power ON
+        self.driver.wait_idle().await?;
+        self.driver.delay(10);
+        self.driver.timings.power_on = Some(start.elapsed());
         Ok(())
     }
+    /// Wait for the refresh that was started, then power off.
+    async fn power_off(&mut self) -> Result<RefreshReport> {
+        self.driver.finish_refresh().await?;
+        let start = Instant::now();
+        self.driver.delay(10);
+        self.driver.command(0x02, &[])?; // Power OFF
+        self.driver.wait_idle().await?;
+        self.driver.delay(20);
+        self.driver.timings.power_off = Some(start.elapsed());
+        Ok(self.driver.report())
+    }
 
-    fn reset(&mut self) {
-        self.reset_pin.set_high();
-        thread::sleep(Duration::from_millis(200));
-        self.reset_pin.set_low();
-        thread::sleep(Duration::from_millis(5));
-        self.reset_pin.set_high();
-        thread::sleep(Duration::from_millis(200));
+    /// Read the controller's temperature sensor, in °C.
+    pub fn temperature(&mut self) -> Result<i8> {
+        self.driver.command(0x40, &[])?; // Temperature Sensor Calibration
+        self.driver.read_busy()?;
+        let mut buf = [0];
+        self.driver.interface.read_data(&mut buf)?;
+        Ok(buf[0] as i8)
     }
 
-    fn send_command(&mut self, command: u8) -> Result<()> {
-        self.dc_pin.set_low();
-        self.cs_pin.set_low();
-        self.spi.write(&[command])?;
-        self.cs_pin.set_high();
-        Ok(())
+    /// Show the frame that was last written, unless the panel is outside its
+    /// rated temperature range.
+    pub fn refresh_checked(&mut self) -> Result<RefreshReport> {
+        let celsius = self.temperature()?;
+        info!("Temperature {celsius} °C");
+        if !self.rated_temperature.contains(&celsius) {
+            return Err(Error::Temperature {
+                controller: None,
+                celsius,
+            });
+        }
+        let report = block_on(self.turn_on())?;
+        Ok(RefreshReport {
+            temperatures: vec![(None, celsius)],
+            ..report
+        })
     }
 
-    fn send_data(&mut self, data: u8) -> Result<()> {
-        self.dc_pin.set_high();
-        self.cs_pin.set_low();
-        self.spi.write(&[data])?;
-        self.cs_pin.set_high();
+    /// Reset the panel and check the connection to the controller. The panel
+    /// must be initialized again before use.
+    pub fn diagnose(&mut self) -> Result<Health> {
+        self.reset()?;
+        let health = self.driver.diagnose()?;
+        info!("{health:?}");
+        Ok(health)
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.display(repeat(0xFF), repeat(0xFF))?;
         Ok(())
     }
 
-    fn read_busy(&self) {
-        debug!("e-Paper busy");
-        while self.busy_pin.is_low() {
-            thread::sleep(Duration::from_millis(100));
+    /// Cycle the whole panel through black, white, red and white, to clear
+    /// ghosting. Takes four refreshes.
+    pub fn deep_clean(&mut self) -> Result<()> {
+        for (black, red) in clean::CYCLE {
+            self.display(repeat(black), repeat(red))?;
         }
-        debug!("e-Paper busy release")
+        Ok(())
+    }
+
+    pub fn sleep(&mut self) -> Result<()> {
+        // Refreshes power off on their own, unless they were cut short.
+        self.driver.command(0x02, &[])?; // Power OFF
+        self.driver.read_busy()?;
+        self.driver.command(0x07, &[0xA5]) // Deep Sleep
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.driver
+            .reset(Duration::from_millis(200), Duration::from_millis(5))
     }
 }
 
-impl Drop for Epd {
-    fn drop(&mut self) {
-        debug!("close 5V, Module enters 0 power consumption ...");
-        self.reset_pin.set_low();
-        self.dc_pin.set_low();
+impl<I: Interface> Display for Epd<I> {
+    fn width(&self) -> usize {
+        EPD_WIDTH
+    }
+    fn height(&self) -> usize {
+        EPD_HEIGHT
+    }
+    fn inks(&self) -> &'static [Ink] {
+        &[Ink::Black, Ink::White, Ink::Red]
+    }
+
+    fn init(&mut self) -> Result<()> {
+        Epd::init(self)
+    }
+    fn write_frame(&mut self, black: &[u8], red: &[u8]) -> Result<()> {
+        check_length(black, EPD_BUFFER_SIZE)?;
+        check_length(red, EPD_BUFFER_SIZE)?;
+        self.write(black.iter().copied(), red.iter().copied())
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        block_on(Epd::start_refresh(self))
+    }
+    fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        block_on(Epd::finish_refresh(self))
+    }
+    fn clear(&mut self) -> Result<()> {
+        Epd::clear(self)
+    }
+    fn deep_clean(&mut self) -> Result<()> {
+        Epd::deep_clean(self)
+    }
+    fn sleep(&mut self) -> Result<()> {
+        Epd::sleep(self)
+    }
+    fn diagnose(&mut self) -> Result<Vec<Health>> {
+        Epd::diagnose(self).map(|health| vec![health])
     }
 }
 
 type BwImage = image::GrayImage;
-pub fn pack_buffer(image: &BwImage) -> Option<[u8; EPD_BUFFER_SIZE]> {
-    if image.width() as usize == EPD_WIDTH && image.height() as usize == EPD_HEIGHT {
-        debug!("Vertical");
-        let mut buf = [0xFFu8; EPD_BUFFER_SIZE];
-        for (x, y, pixel) in image.enumerate_pixels() {
-            if pixel.channels()[0] == 0 {
-                set_pixel(&mut buf, x as usize, y as usize);
-            }
-        }
-        Some(buf)
-    } else if image.width() as usize == EPD_HEIGHT && image.height() as usize == EPD_WIDTH {
+/// Pack `image` for the panel, vertical or horizontal. Horizontal images get
+/// rotated clockwise.
+pub fn pack_buffer(image: &BwImage) -> Result<[u8; EPD_BUFFER_SIZE]> {
+    let packing = Packing::new(EPD_WIDTH, EPD_HEIGHT);
+    let packing = if image.dimensions() == (EPD_HEIGHT as u32, EPD_WIDTH as u32) {
         debug!("Horizontal");
-        let mut buf = [0xFF; EPD_BUFFER_SIZE];
-        for (x, y, pixel) in image.enumerate_pixels() {
-            if pixel.channels()[0] == 0 {
-                set_pixel(&mut buf, y as usize, EPD_HEIGHT - x as usize - 1);
-            }
-        }
-        Some(buf)
+        packing.with_rotation(Rotation::Rotate270)
     } else {
+        debug!("Vertical");
+        packing
+    };
+    let plane = packing.pack(image).inspect_err(|_| {
         warn!("Unsupported image size {:?}", image.dimensions());
-        None
-    }
-}
-fn set_pixel(buf: &mut [u8], x: usize, y: usize) {
-    buf[(x + y * EPD_WIDTH) / 8] &= !(0x80 >> (x % 8));
+    })?;
+    Ok(plane.try_into().expect("planes are EPD_BUFFER_SIZE long"))
 }
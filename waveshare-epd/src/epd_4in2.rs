@@ -0,0 +1,168 @@
+//! 4.2" black and white
+
+use crate::{
+    driver::{check_length, Driver},
+    interface::Interface,
+    Display, Health, Ink, RefreshReport, Result,
+};
+use futures::executor::block_on;
+use log::info;
+use std::time::Duration;
+
+pub const EPD_WIDTH: usize = 400;
+pub const EPD_HEIGHT: usize = 300;
+const FRAME_BYTES: usize = EPD_WIDTH / 8 * EPD_HEIGHT;
+
+/// Default limit on how long the panel may stay busy.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct Epd<I> {
+    driver: Driver<I>,
+}
+impl<I: Interface> Epd<I> {
+    pub fn with_interface(interface: I) -> Self {
+        Self {
+            driver: Driver::new(interface, true, DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// Limit how long each busy wait may take.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.driver.timeout = timeout;
+        self
+    }
+
+    pub fn interface(&self) -> &I {
+        &self.driver.interface
+    }
+
+    pub fn interface_mut(&mut self) -> &mut I {
+        &mut self.driver.interface
+    }
+
+    pub fn init(&mut self) -> Result<()> {
+        self.reset()?;
+        let d = &mut self.driver;
+        d.command(0x01, &[0x03, 0x00, 0x2B, 0x2B])?; // Power Setting
+        d.command(0x06, &[0x17, 0x17, 0x17])?; // Booster Soft Start
+        d.command(0x04, &[])?; // Power ON
+        d.read_busy()?;
+        d.command(0x00, &[0xBF, 0x0D])?; // Panel Setting: KW, LUT from registers
+        d.command(0x30, &[0x3C])?; // PLL Control: 50 Hz
+        d.command(0x61, &[0x01, 0x90, 0x01, 0x2C])?; // Resolution: 400x300
+        d.command(0x82, &[0x28])?; // VCOM_DC Setting
+        d.command(0x50, &[0x97])?; // VCOM and Data Interval
+        d.command(0x20, &LUT_VCOM)?;
+        d.command(0x21, &LUT_WHITE)?; // White to white
+        d.command(0x22, &LUT_WHITE)?; // Black to white
+        d.command(0x23, &LUT_BLACK)?; // White to black
+        d.command(0x24, &LUT_BLACK)?; // Black to black
+        Ok(())
+    }
+
+    /// Write and show `black`, a full frame with cleared bits where black.
+    pub fn display(&mut self, black: &[u8]) -> Result<RefreshReport> {
+        self.write(black)?;
+        block_on(self.turn_on())
+    }
+
+    /// Write `black` to the panel's memory.
+    pub fn write(&mut self, black: &[u8]) -> Result<()> {
+        check_length(black, FRAME_BYTES)?;
+        self.driver.command(0x10, &[0xFF; FRAME_BYTES])?; // Old Data
+        self.driver.command(0x13, black) // New Data
+    }
+
+    /// Show the frame that was last written.
+    pub async fn turn_on(&mut self) -> Result<RefreshReport> {
+        self.start_refresh()?;
+        self.finish_refresh().await
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        self.driver.start_refresh(0x12, &[])?; // Display Refresh
+        self.driver.delay(100);
+        Ok(())
+    }
+    async fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        self.driver.finish_refresh().await?;
+        Ok(self.driver.report())
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.driver.command(0x10, &[0xFF; FRAME_BYTES])?;
+        self.driver.command(0x13, &[0xFF; FRAME_BYTES])?;
+        block_on(self.turn_on())?;
+        Ok(())
+    }
+
+    pub fn sleep(&mut self) -> Result<()> {
+        self.driver.command(0x02, &[])?; // Power OFF
+        self.driver.read_busy()?;
+        self.driver.command(0x07, &[0xA5]) // Deep Sleep
+    }
+
+    /// Reset the panel and check the connection to the controller. The panel
+    /// must be initialized again before use.
+    pub fn diagnose(&mut self) -> Result<Health> {
+        self.reset()?;
+        let health = self.driver.diagnose()?;
+        info!("{health:?}");
+        Ok(health)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.driver
+            .reset(Duration::from_millis(200), Duration::from_millis(2))
+    }
+}
+
+impl<I: Interface> Display for Epd<I> {
+    fn width(&self) -> usize {
+        EPD_WIDTH
+    }
+    fn height(&self) -> usize {
+        EPD_HEIGHT
+    }
+    fn inks(&self) -> &'static [Ink] {
+        &[Ink::Black, Ink::White]
+    }
+
+    fn init(&mut self) -> Result<()> {
+        Epd::init(self)
+    }
+    fn write_frame(&mut self, black: &[u8], _red: &[u8]) -> Result<()> {
+        self.write(black)
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        Epd::start_refresh(self)
+    }
+    fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        block_on(Epd::finish_refresh(self))
+    }
+    fn clear(&mut self) -> Result<()> {
+        Epd::clear(self)
+    }
+    fn sleep(&mut self) -> Result<()> {
+        Epd::sleep(self)
+    }
+    fn diagnose(&mut self) -> Result<Vec<Health>> {
+        Epd::diagnose(self).map(|health| vec![health])
+    }
+}
+
+/// Waveforms of the full refresh, since the panel has none in OTP.
+const LUT_VCOM: [u8; 44] = [
+    0x00, 0x17, 0x00, 0x00, 0x00, 0x02, 0x00, 0x17, 0x17, 0x00, 0x00, 0x02, 0x00, 0x0A, 0x01, 0x00,
+    0x00, 0x01, 0x00, 0x0E, 0x0E, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const LUT_WHITE: [u8; 42] = [
+    0x40, 0x17, 0x00, 0x00, 0x00, 0x02, 0x90, 0x17, 0x17, 0x00, 0x00, 0x02, 0x40, 0x0A, 0x01, 0x00,
+    0x00, 0x01, 0xA0, 0x0E, 0x0E, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const LUT_BLACK: [u8; 42] = [
+    0x80, 0x17, 0x00, 0x00, 0x00, 0x02, 0x90, 0x17, 0x17, 0x00, 0x00, 0x02, 0x80, 0x0A, 0x01, 0x00,
+    0x00, 0x01, 0x50, 0x0E, 0x0E, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
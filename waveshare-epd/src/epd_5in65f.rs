@@ -0,0 +1,211 @@
+//! 5.65" 7-color ACeP
+
+use crate::{
+    driver::{check_length, Driver},
+    interface::Interface,
+    Display, Health, Ink, RefreshReport, Result,
+};
+use futures::executor::block_on;
+use log::info;
+use std::time::{Duration, Instant};
+
+pub const EPD_WIDTH: usize = 600;
+pub const EPD_HEIGHT: usize = 448;
+/// Size of a frame of [`Color`]s, two pixels per byte.
+const FRAME_BYTES: usize = EPD_WIDTH / 2 * EPD_HEIGHT;
+/// Size of each plane taken by [`Display::write_frame`].
+const PLANE_BYTES: usize = EPD_WIDTH / 8 * EPD_HEIGHT;
+
+/// Default limit on how long the panel may stay busy.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A color of the panel, as the controller encodes it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Color {
+    Black = 0,
+    #[default]
+    White = 1,
+    Green = 2,
+    Blue = 3,
+    Red = 4,
+    Yellow = 5,
+    Orange = 6,
+    /// Drives the particles to a neutral state, to clear ghosting.
+    Clean = 7,
+}
+
+pub struct Epd<I> {
+    driver: Driver<I>,
+}
+impl<I: Interface> Epd<I> {
+    pub fn with_interface(interface: I) -> Self {
+        Self {
+            driver: Driver::new(interface, true, DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// Limit how long each busy wait may take.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.driver.timeout = timeout;
+        self
+    }
+
+    pub fn interface(&self) -> &I {
+        &self.driver.interface
+    }
+
+    pub fn interface_mut(&mut self) -> &mut I {
+        &mut self.driver.interface
+    }
+
+    pub fn init(&mut self) -> Result<()> {
+        self.reset()?;
+        let d = &mut self.driver;
+        d.read_busy()?;
+        d.command(0x00, &[0xEF, 0x08])?; // Panel Setting
+        d.command(0x01, &[0x37, 0x00, 0x23, 0x23])?; // Power Setting
+        d.command(0x03, &[0x00])?; // Power Off Sequence
+        d.command(0x06, &[0xC7, 0xC7, 0x1D])?; // Booster Soft Start
+        d.command(0x30, &[0x3C])?; // PLL Control: 50 Hz
+        d.command(0x41, &[0x00])?; // Temperature Sensor Enable
+        d.command(0x50, &[0x37])?; // VCOM and Data Interval
+        d.command(0x60, &[0x22])?; // TCON
+        d.command(0x61, &[0x02, 0x58, 0x01, 0xC0])?; // Resolution: 600x448
+        d.command(0xE3, &[0xAA])?; // Power Saving
+        d.delay(100);
+        d.command(0x50, &[0x37])?;
+        Ok(())
+    }
+
+    /// Write and show `colors`, a full frame in row-major order.
+    pub fn display(&mut self, colors: &[Color]) -> Result<RefreshReport> {
+        self.write(colors)?;
+        block_on(self.turn_on())
+    }
+
+    /// Write `colors`, a full frame in row-major order, to the panel's memory.
+    pub fn write(&mut self, colors: &[Color]) -> Result<()> {
+        check_length(colors, EPD_WIDTH * EPD_HEIGHT)?;
+        let frame: Vec<u8> = colors
+            .chunks(2)
+            .map(|pair| (pair[0] as u8) << 4 | pair[1] as u8)
+            .collect();
+        self.write_packed(&frame)
+    }
+
+    fn write_packed(&mut self, frame: &[u8]) -> Result<()> {
+        self.driver.command(0x61, &[0x02, 0x58, 0x01, 0xC0])?;
+        self.driver.command(0x10, frame) // Data Start Transmission
+    }
+
+    /// Show the frame that was last written.
+    pub async fn turn_on(&mut self) -> Result<RefreshReport> {
+        self.start_refresh().await?;
+        self.finish_refresh().await
+    }
+    async fn start_refresh(&mut self) -> Result<()> {
+        let start = Instant::now();
+        self.driver.command(0x04, &[])?; // Power ON
+        self.driver.wait_idle().await?;
+        self.driver.timings.power_on = Some(start.elapsed());
+        self.driver.start_refresh(0x12, &[]) // Display Refresh
+    }
+    async fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        self.driver.finish_refresh().await?;
+        let start = Instant::now();
+        self.driver.command(0x02, &[])?; // Power OFF
+
+        // The busy line isn't reliable while powering off.
+        self.driver.delay(200);
+        self.driver.timings.power_off = Some(start.elapsed());
+        Ok(self.driver.report())
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        let white = Color::White as u8;
+        self.write_packed(&[white << 4 | white; FRAME_BYTES])?;
+        block_on(self.turn_on())?;
+        Ok(())
+    }
+
+    pub fn sleep(&mut self) -> Result<()> {
+        self.driver.delay(100);
+        self.driver.command(0x07, &[0xA5])?; // Deep Sleep
+        self.driver.delay(100);
+        Ok(())
+    }
+
+    /// Reset the panel and check the connection to the controller. The panel
+    /// must be initialized again before use.
+    pub fn diagnose(&mut self) -> Result<Health> {
+        self.reset()?;
+        let health = self.driver.diagnose()?;
+        info!("{health:?}");
+        Ok(health)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.driver
+            .reset(Duration::from_millis(200), Duration::from_millis(1))
+    }
+}
+
+impl<I: Interface> Display for Epd<I> {
+    fn width(&self) -> usize {
+        EPD_WIDTH
+    }
+    fn height(&self) -> usize {
+        EPD_HEIGHT
+    }
+    fn inks(&self) -> &'static [Ink] {
+        &[
+            Ink::Black,
+            Ink::White,
+            Ink::Green,
+            Ink::Blue,
+            Ink::Red,
+            Ink::Yellow,
+            Ink::Orange,
+        ]
+    }
+
+    fn init(&mut self) -> Result<()> {
+        Epd::init(self)
+    }
+    /// Only black and red can be written as planes, the other colors need
+    /// [`Epd::write`]. Red wins where both are set.
+    fn write_frame(&mut self, black: &[u8], red: &[u8]) -> Result<()> {
+        check_length(black, PLANE_BYTES)?;
+        check_length(red, PLANE_BYTES)?;
+        let color = |i: usize| {
+            let bit = 0x80 >> (i % 8);
+            if red[i / 8] & bit == 0 {
+                Color::Red
+            } else if black[i / 8] & bit == 0 {
+                Color::Black
+            } else {
+                Color::White
+            }
+        };
+        let frame: Vec<u8> = (0..EPD_WIDTH * EPD_HEIGHT)
+            .step_by(2)
+            .map(|i| (color(i) as u8) << 4 | color(i + 1) as u8)
+            .collect();
+        self.write_packed(&frame)
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        block_on(Epd::start_refresh(self))
+    }
+    fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        block_on(Epd::finish_refresh(self))
+    }
+    fn clear(&mut self) -> Result<()> {
+        Epd::clear(self)
+    }
+    fn sleep(&mut self) -> Result<()> {
+        Epd::sleep(self)
+    }
+    fn diagnose(&mut self) -> Result<Vec<Health>> {
+        Epd::diagnose(self).map(|health| vec![health])
+    }
+}
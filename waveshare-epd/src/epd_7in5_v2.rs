@@ -0,0 +1,150 @@
+//! 7.5" V2 black and white
+
+use crate::{
+    driver::{check_length, Driver},
+    interface::Interface,
+    Display, Health, Ink, RefreshReport, Result,
+};
+use futures::executor::block_on;
+use log::info;
+use std::time::Duration;
+
+pub const EPD_WIDTH: usize = 800;
+pub const EPD_HEIGHT: usize = 480;
+const FRAME_BYTES: usize = EPD_WIDTH / 8 * EPD_HEIGHT;
+
+/// Default limit on how long the panel may stay busy.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct Epd<I> {
+    driver: Driver<I>,
+}
+impl<I: Interface> Epd<I> {
+    pub fn with_interface(interface: I) -> Self {
+        Self {
+            driver: Driver::new(interface, true, DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// Limit how long each busy wait may take.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.driver.timeout = timeout;
+        self
+    }
+
+    pub fn interface(&self) -> &I {
+        &self.driver.interface
+    }
+
+    pub fn interface_mut(&mut self) -> &mut I {
+        &mut self.driver.interface
+    }
+
+    pub fn init(&mut self) -> Result<()> {
+        self.reset()?;
+        let d = &mut self.driver;
+        d.command(0x01, &[0x07, 0x07, 0x3F, 0x3F])?; // Power Setting
+        d.command(0x06, &[0x17, 0x17, 0x28, 0x17])?; // Booster Soft Start
+        d.command(0x04, &[])?; // Power ON
+        d.delay(100);
+        d.read_busy()?;
+        d.command(0x00, &[0x1F])?; // Panel Setting: KW, LUT from OTP
+        d.command(0x61, &[0x03, 0x20, 0x01, 0xE0])?; // Resolution: 800x480
+        d.command(0x15, &[0x00])?; // Dual SPI off
+        d.command(0x50, &[0x10, 0x07])?; // VCOM and Data Interval
+        d.command(0x60, &[0x22])?; // TCON
+        Ok(())
+    }
+
+    /// Write and show `black`, a full frame with cleared bits where black.
+    pub fn display(&mut self, black: &[u8]) -> Result<RefreshReport> {
+        self.write(black)?;
+        block_on(self.turn_on())
+    }
+
+    /// Write `black` to the panel's memory.
+    pub fn write(&mut self, black: &[u8]) -> Result<()> {
+        check_length(black, FRAME_BYTES)?;
+        self.driver.command(0x10, black)?; // Old Data
+
+        // The new data uses set bits for black.
+        let inverted: Vec<u8> = black.iter().map(|b| !b).collect();
+        self.driver.command(0x13, &inverted) // New Data
+    }
+
+    /// Show the frame that was last written.
+    pub async fn turn_on(&mut self) -> Result<RefreshReport> {
+        self.start_refresh()?;
+        self.finish_refresh().await
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        self.driver.start_refresh(0x12, &[])?; // Display Refresh
+        self.driver.delay(100);
+        Ok(())
+    }
+    async fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        self.driver.finish_refresh().await?;
+        Ok(self.driver.report())
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.driver.command(0x10, &[0xFF; FRAME_BYTES])?;
+        self.driver.command(0x13, &[0x00; FRAME_BYTES])?;
+        block_on(self.turn_on())?;
+        Ok(())
+    }
+
+    pub fn sleep(&mut self) -> Result<()> {
+        self.driver.command(0x02, &[])?; // Power OFF
+        self.driver.read_busy()?;
+        self.driver.command(0x07, &[0xA5]) // Deep Sleep
+    }
+
+    /// Reset the panel and check the connection to the controller. The panel
+    /// must be initialized again before use.
+    pub fn diagnose(&mut self) -> Result<Health> {
+        self.reset()?;
+        let health = self.driver.diagnose()?;
+        info!("{health:?}");
+        Ok(health)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.driver
+            .reset(Duration::from_millis(20), Duration::from_millis(2))
+    }
+}
+
+impl<I: Interface> Display for Epd<I> {
+    fn width(&self) -> usize {
+        EPD_WIDTH
+    }
+    fn height(&self) -> usize {
+        EPD_HEIGHT
+    }
+    fn inks(&self) -> &'static [Ink] {
+        &[Ink::Black, Ink::White]
+    }
+
+    fn init(&mut self) -> Result<()> {
+        Epd::init(self)
+    }
+    fn write_frame(&mut self, black: &[u8], _red: &[u8]) -> Result<()> {
+        self.write(black)
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        Epd::start_refresh(self)
+    }
+    fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        block_on(Epd::finish_refresh(self))
+    }
+    fn clear(&mut self) -> Result<()> {
+        Epd::clear(self)
+    }
+    fn sleep(&mut self) -> Result<()> {
+        Epd::sleep(self)
+    }
+    fn diagnose(&mut self) -> Result<Vec<Health>> {
+        Epd::diagnose(self).map(|health| vec![health])
+    }
+}
@@ -1,5 +1,8 @@
+use crate::interface::Controller;
 #[cfg(feature = "esp")]
 use esp_idf_hal::sys::EspError;
+#[cfg(feature = "sim")]
+use image::ImageError;
 #[cfg(feature = "rpi")]
 use rppal::{gpio, spi};
 use thiserror::Error;
@@ -14,7 +17,81 @@ pub enum EpdError {
     #[cfg(feature = "rpi")]
     #[error(transparent)]
     Spi(#[from] spi::Error),
+    #[cfg(feature = "linux")]
+    #[error(transparent)]
+    Cdev(#[from] gpio_cdev::Error),
     #[cfg(feature = "esp")]
     #[error(transparent)]
     Esp(#[from] EspError),
+    #[cfg(feature = "sim")]
+    #[error(transparent)]
+    Image(#[from] ImageError),
+    #[cfg(feature = "config")]
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The panel stayed busy for too long.
+    #[error("{} still busy after {}", describe_controller(.controller), describe_command(.command))]
+    Timeout {
+        /// Controller that was busy, for panels with more than one.
+        controller: Option<Controller>,
+        /// Last command sent, if any since reset.
+        command: Option<u8>,
+    },
+    /// An image doesn't match the size of the panel.
+    #[error("invalid dimensions {}x{}, expected {}x{}", .actual.0, .actual.1, .expected.0, .expected.1)]
+    InvalidDimensions {
+        expected: (usize, usize),
+        actual: (usize, usize),
+    },
+    /// A controller is outside the temperature range the panel is rated for.
+    #[error("{} is at {celsius} °C, outside the rated range", describe_controller(.controller))]
+    Temperature {
+        controller: Option<Controller>,
+        celsius: i8,
+    },
+    /// A configuration doesn't match the board.
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+    /// The backend can't do what the driver asked for.
+    #[error("{0} is not supported by this backend")]
+    Unsupported(&'static str),
+    /// A window isn't aligned to 8 pixels horizontally, or doesn't fit the
+    /// panel.
+    #[error("invalid window {width}x{height} at ({x}, {y})")]
+    InvalidWindow {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    },
+    /// A refresh came sooner than the panel allows, see
+    /// [`Guard`](crate::guard::Guard).
+    #[error("refreshed too early, {wait:?} before the next refresh is allowed")]
+    TooEarly { wait: std::time::Duration },
+    /// A trace can't be parsed, or replayed.
+    #[error("invalid trace: {0}")]
+    InvalidTrace(String),
+    /// A tile of a [`Canvas`](crate::canvas::Canvas) overlaps another, by
+    /// index in the order they were added.
+    #[error("tile {tile} overlaps tile {other}")]
+    OverlappingTiles { tile: usize, other: usize },
+    /// A buffer doesn't match the size of the panel's memory.
+    #[error("invalid buffer length {actual}, expected {expected}")]
+    InvalidLength { expected: usize, actual: usize },
+}
+
+fn describe_controller(controller: &Option<Controller>) -> String {
+    match controller {
+        Some(controller) => format!("{controller:?}"),
+        None => "e-Paper".to_string(),
+    }
+}
+
+fn describe_command(command: &Option<u8>) -> String {
+    match command {
+        Some(command) => format!("command {command:#04x}"),
+        None => "reset".to_string(),
+    }
 }
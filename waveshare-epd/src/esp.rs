@@ -0,0 +1,309 @@
+//! ESP32 backend for panels driven by several controllers, based on esp-idf-hal.
+
+use crate::{
+    interface::{CascadeInterface, Controller},
+    Result,
+};
+#[cfg(esp32)]
+use esp_idf_hal::{gpio, spi::SPI3};
+use esp_idf_hal::{
+    gpio::{AnyInputPin, AnyOutputPin, Input, InputPin, Output, OutputPin, PinDriver},
+    peripheral::{Peripheral, PeripheralRef},
+    spi::{config::Duplex, SpiAnyPins, SpiConfig, SpiDeviceDriver, SpiDriver, SpiDriverConfig},
+    sys::{
+        esp, esp_timer_create, esp_timer_create_args_t, esp_timer_delete,
+        esp_timer_dispatch_t_ESP_TIMER_TASK, esp_timer_handle_t, esp_timer_start_once,
+        esp_timer_stop, ESP_OK,
+    },
+};
+use futures::{
+    future::{self, Either},
+    task::AtomicWaker,
+    Future,
+};
+use std::{
+    ffi::c_void,
+    pin::{pin, Pin},
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    thread,
+    time::Duration,
+};
+
+pub struct EspInterface<'d> {
+    spi: SpiDriver<'d>,
+    /// Chip select of each controller, in the order of [`Controller::ALL`].
+    cs: [PeripheralRef<'d, AnyOutputPin>; 4],
+    m1s1_dc: PinDriver<'d, AnyOutputPin, Output>,
+    m2s2_dc: PinDriver<'d, AnyOutputPin, Output>,
+    m1s1_rst: PinDriver<'d, AnyOutputPin, Output>,
+    m2s2_rst: Option<PinDriver<'d, AnyOutputPin, Output>>,
+    /// Busy line of each controller, in the order of [`Controller::ALL`].
+    busy: [PinDriver<'d, AnyInputPin, Input>; 4],
+}
+
+impl<'d> EspInterface<'d> {
+    /// Wiring of Waveshare's ESP32 e-Paper driver board.
+    #[cfg(esp32)]
+    pub fn waveshare(spi: impl Peripheral<P = SPI3> + 'd, pins: gpio::Pins) -> Result<Self> {
+        Ok(Self {
+            spi: SpiDriver::new(
+                spi,
+                pins.gpio13,
+                pins.gpio14,
+                None::<gpio::Gpio12>,
+                &SpiDriverConfig::new(),
+            )?,
+            cs: [
+                pins.gpio23.downgrade_output().into_ref(),
+                pins.gpio22.downgrade_output().into_ref(),
+                pins.gpio16.downgrade_output().into_ref(),
+                pins.gpio19.downgrade_output().into_ref(),
+            ],
+            m1s1_dc: PinDriver::output(pins.gpio25.downgrade_output())?,
+            m2s2_dc: PinDriver::output(pins.gpio17.downgrade_output())?,
+            m1s1_rst: PinDriver::output(pins.gpio33.downgrade_output())?,
+            m2s2_rst: Some(PinDriver::output(pins.gpio5.downgrade_output())?),
+            busy: [
+                PinDriver::input(pins.gpio32.downgrade_input())?,
+                PinDriver::input(pins.gpio26.downgrade_input())?,
+                PinDriver::input(pins.gpio18.downgrade_input())?,
+                PinDriver::input(pins.gpio4.downgrade_input())?,
+            ],
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn custom(
+        spi: impl Peripheral<P = impl SpiAnyPins> + 'd,
+        sclk: impl OutputPin + 'd,
+        sdo: impl OutputPin + 'd,
+        m1_cs: impl OutputPin + 'd,
+        s1_cs: impl OutputPin + 'd,
+        m2_cs: impl OutputPin + 'd,
+        s2_cs: impl OutputPin + 'd,
+        m1s1_dc: impl OutputPin + 'd,
+        m2s2_dc: impl OutputPin + 'd,
+        m1s1_rst: impl OutputPin + 'd,
+        m2s2_rst: Option<impl OutputPin + 'd>,
+        m1_busy: impl InputPin + 'd,
+        s1_busy: impl InputPin + 'd,
+        m2_busy: impl InputPin + 'd,
+        s2_busy: impl InputPin + 'd,
+    ) -> Result<Self> {
+        Ok(Self {
+            spi: SpiDriver::new(spi, sclk, sdo, None::<AnyInputPin>, &SpiDriverConfig::new())?,
+            cs: [
+                m1_cs.downgrade_output().into_ref(),
+                s1_cs.downgrade_output().into_ref(),
+                m2_cs.downgrade_output().into_ref(),
+                s2_cs.downgrade_output().into_ref(),
+            ],
+            m1s1_dc: PinDriver::output(m1s1_dc.downgrade_output())?,
+            m2s2_dc: PinDriver::output(m2s2_dc.downgrade_output())?,
+            m1s1_rst: PinDriver::output(m1s1_rst.downgrade_output())?,
+            m2s2_rst: match m2s2_rst {
+                Some(pin) => Some(PinDriver::output(pin.downgrade_output())?),
+                None => None,
+            },
+            busy: [
+                PinDriver::input(m1_busy.downgrade_input())?,
+                PinDriver::input(s1_busy.downgrade_input())?,
+                PinDriver::input(m2_busy.downgrade_input())?,
+                PinDriver::input(s2_busy.downgrade_input())?,
+            ],
+        })
+    }
+
+    /// Select the given controllers, and write to them with DC set to `data`.
+    fn write(&mut self, to: &[Controller], data: bool, bytes: &[u8]) -> Result<()> {
+        self.transfer(to, data, &SpiConfig::new(), |spi| Ok(spi.write(bytes)?))
+    }
+
+    /// Select a controller, and read its answer on the data line.
+    fn read(&mut self, from: Controller, bytes: &mut [u8]) -> Result<()> {
+        let config = SpiConfig::new().duplex(Duplex::Half3Wire);
+        self.transfer(&[from], true, &config, |spi| Ok(spi.read(bytes)?))
+    }
+
+    /// Run `transfer` with the given controllers selected, and DC set to
+    /// `data`.
+    fn transfer(
+        &mut self,
+        to: &[Controller],
+        data: bool,
+        config: &SpiConfig,
+        transfer: impl FnOnce(&mut SpiDeviceDriver<'_, &SpiDriver<'_>>) -> Result<()>,
+    ) -> Result<()> {
+        let mut spi = SpiDeviceDriver::new(&self.spi, AnyOutputPin::none(), config)?;
+        if to.contains(&Controller::M1) || to.contains(&Controller::S1) {
+            self.m1s1_dc.set_level(data.into())?;
+        }
+        if to.contains(&Controller::M2) || to.contains(&Controller::S2) {
+            self.m2s2_dc.set_level(data.into())?;
+        }
+        let mut cs = self
+            .cs
+            .iter_mut()
+            .zip(Controller::ALL)
+            .filter(|(_, controller)| to.contains(controller))
+            .map(|(pin, _)| PinDriver::output(pin.reborrow()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        for pin in &mut cs {
+            pin.set_low()?;
+        }
+        transfer(&mut spi)?;
+        for pin in &mut cs {
+            pin.set_high()?;
+        }
+        Ok(())
+    }
+}
+
+impl CascadeInterface for EspInterface<'_> {
+    fn set_reset(&mut self, high: bool) -> Result<()> {
+        self.m1s1_rst.set_level(high.into())?;
+        if let Some(pin) = &mut self.m2s2_rst {
+            pin.set_level(high.into())?;
+        }
+        Ok(())
+    }
+
+    fn send_command(&mut self, to: &[Controller], command: u8) -> Result<()> {
+        self.write(to, false, &[command])
+    }
+
+    fn send_data(&mut self, to: &[Controller], data: &[u8]) -> Result<()> {
+        self.write(to, true, data)
+    }
+
+    fn read_data(&mut self, from: Controller, buf: &mut [u8]) -> Result<()> {
+        self.read(from, buf)
+    }
+
+    fn busy_high(&mut self, controller: Controller) -> Result<bool> {
+        Ok(self.busy[controller as usize].is_high())
+    }
+
+    fn delay(&mut self, duration: Duration) {
+        thread::sleep(duration);
+    }
+
+    fn wait_idle(
+        &mut self,
+        controllers: &[Controller],
+        idle_high: bool,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<Option<Controller>>> {
+        async move {
+            {
+                let waits = self
+                    .busy
+                    .iter_mut()
+                    .zip(Controller::ALL)
+                    .filter(|(_, controller)| controllers.contains(controller))
+                    .map(|(pin, _)| {
+                        if idle_high {
+                            Either::Left(pin.wait_for_high())
+                        } else {
+                            Either::Right(pin.wait_for_low())
+                        }
+                    });
+                let waits = pin!(future::try_join_all(waits));
+                if let Either::Left((result, _)) =
+                    future::select(waits, Timeout::new(timeout)?).await
+                {
+                    result?;
+                    return Ok(None);
+                }
+            }
+            Ok(controllers
+                .iter()
+                .copied()
+                .find(|&controller| self.busy[controller as usize].is_high() != idle_high))
+        }
+    }
+}
+
+/// A future that completes after a duration, on an `esp_timer`. Dropping it
+/// stops the timer.
+///
+/// The timer holds a reference to the state of its own, which the callback
+/// releases once done with it. Stopping the timer doesn't wait for a callback
+/// already running, so the reference is only released on drop if the timer
+/// was stopped before firing.
+struct Timeout {
+    handle: esp_timer_handle_t,
+    state: Arc<TimeoutState>,
+}
+#[derive(Default)]
+struct TimeoutState {
+    elapsed: AtomicBool,
+    waker: AtomicWaker,
+}
+impl Timeout {
+    fn new(duration: Duration) -> Result<Self> {
+        let state = Arc::new(TimeoutState::default());
+        let arg = Arc::into_raw(state.clone()) as *mut c_void;
+        let args = esp_timer_create_args_t {
+            callback: Some(Self::elapsed),
+            arg,
+            dispatch_method: esp_timer_dispatch_t_ESP_TIMER_TASK,
+            name: b"epd timeout\0".as_ptr().cast(),
+            skip_unhandled_events: true,
+        };
+        let mut handle = ptr::null_mut();
+        if let Err(error) = esp!(unsafe { esp_timer_create(&args, &mut handle) }) {
+            // No timer was created to hold the reference.
+            drop(unsafe { Arc::from_raw(arg as *const TimeoutState) });
+            return Err(error.into());
+        }
+        let micros = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+        if let Err(error) = esp!(unsafe { esp_timer_start_once(handle, micros) }) {
+            // Nor will the callback ever run to release it.
+            unsafe {
+                esp_timer_delete(handle);
+                drop(Arc::from_raw(arg as *const TimeoutState));
+            }
+            return Err(error.into());
+        }
+        Ok(Self { handle, state })
+    }
+
+    unsafe extern "C" fn elapsed(arg: *mut c_void) {
+        // The timer's reference, the timer firing only once.
+        let state = unsafe { Arc::from_raw(arg as *const TimeoutState) };
+        state.elapsed.store(true, Ordering::Release);
+        state.waker.wake();
+    }
+}
+impl Future for Timeout {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.state.waker.register(cx.waker());
+        if self.state.elapsed.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+impl Drop for Timeout {
+    fn drop(&mut self) {
+        unsafe {
+            // Fails if the timer already fired, in which case the callback
+            // releases the timer's reference.
+            let stopped = esp_timer_stop(self.handle) == ESP_OK;
+            esp_timer_delete(self.handle);
+            if stopped {
+                drop(Arc::from_raw(Arc::as_ptr(&self.state)));
+            }
+        }
+    }
+}
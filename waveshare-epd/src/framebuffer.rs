@@ -0,0 +1,189 @@
+//! In-memory frame for Black/White/Red panels.
+//!
+//! With the `graphics` feature, [`Framebuffer`] is an
+//! [`embedded_graphics_core::draw_target::DrawTarget`], so text and shapes can
+//! be drawn with `embedded-graphics`.
+
+pub use crate::packing::Rotation;
+use crate::Ink;
+#[cfg(feature = "graphics")]
+use embedded_graphics_core::{
+    draw_target::DrawTarget,
+    geometry::{OriginDimensions, Point, Size},
+    pixelcolor::{BinaryColor, PixelColor},
+    Pixel,
+};
+#[cfg(feature = "graphics")]
+use std::convert::Infallible;
+
+/// Color of a Black/White/Red panel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TriColor {
+    Black,
+    #[default]
+    White,
+    Red,
+}
+impl From<TriColor> for Ink {
+    fn from(color: TriColor) -> Self {
+        match color {
+            TriColor::Black => Ink::Black,
+            TriColor::White => Ink::White,
+            TriColor::Red => Ink::Red,
+        }
+    }
+}
+#[cfg(feature = "graphics")]
+impl PixelColor for TriColor {
+    type Raw = ();
+}
+#[cfg(feature = "graphics")]
+impl From<BinaryColor> for TriColor {
+    fn from(color: BinaryColor) -> Self {
+        match color {
+            BinaryColor::On => TriColor::Black,
+            BinaryColor::Off => TriColor::White,
+        }
+    }
+}
+
+/// Black and red planes of a full frame, in the format expected by
+/// [`Display::write_frame`](crate::Display::write_frame).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    /// Size of the panel, in its native orientation.
+    width: usize,
+    height: usize,
+    rotation: Rotation,
+    black: Vec<u8>,
+    red: Vec<u8>,
+}
+impl Framebuffer {
+    /// A white frame for a panel of the given native size.
+    pub fn new(width: usize, height: usize) -> Self {
+        let size = width.div_ceil(8) * height;
+        Self {
+            width,
+            height,
+            rotation: Rotation::Rotate0,
+            black: vec![0xFF; size],
+            red: vec![0xFF; size],
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Width and height of the panel, in its native orientation.
+    pub fn native_dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Width and height of the drawing, after rotation.
+    pub fn dimensions(&self) -> (usize, usize) {
+        match self.rotation {
+            Rotation::Rotate0 | Rotation::Rotate180 => (self.width, self.height),
+            Rotation::Rotate90 | Rotation::Rotate270 => (self.height, self.width),
+        }
+    }
+
+    pub fn black(&self) -> &[u8] {
+        &self.black
+    }
+
+    pub fn red(&self) -> &[u8] {
+        &self.red
+    }
+
+    /// Fill the whole frame with `color`.
+    pub fn fill(&mut self, color: TriColor) {
+        let (black, red) = match color {
+            TriColor::Black => (0x00, 0xFF),
+            TriColor::White => (0xFF, 0xFF),
+            TriColor::Red => (0xFF, 0x00),
+        };
+        self.black.fill(black);
+        self.red.fill(red);
+    }
+
+    /// Set the pixel at `(x, y)` of the drawing. Pixels outside of it are
+    /// ignored.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: TriColor) {
+        let Some((x, y)) = self.to_native(x, y) else {
+            return;
+        };
+        let index = x / 8 + y * self.width.div_ceil(8);
+        let mask = 0x80 >> (x % 8);
+        let (black, red) = match color {
+            TriColor::Black => (true, false),
+            TriColor::White => (false, false),
+            TriColor::Red => (false, true),
+        };
+        for (plane, ink) in [(&mut self.black, black), (&mut self.red, red)] {
+            if ink {
+                plane[index] &= !mask;
+            } else {
+                plane[index] |= mask;
+            }
+        }
+    }
+
+    /// Color of the pixel at `(x, y)` of the drawing.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<TriColor> {
+        let (x, y) = self.to_native(x, y)?;
+        let index = x / 8 + y * self.width.div_ceil(8);
+        let mask = 0x80 >> (x % 8);
+        Some(if self.red[index] & mask == 0 {
+            TriColor::Red
+        } else if self.black[index] & mask == 0 {
+            TriColor::Black
+        } else {
+            TriColor::White
+        })
+    }
+
+    fn to_native(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let (width, height) = self.dimensions();
+        if x >= width || y >= height {
+            return None;
+        }
+        Some(self.rotation.to_native(x, y, self.width, self.height))
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl OriginDimensions for Framebuffer {
+    fn size(&self) -> Size {
+        let (width, height) = self.dimensions();
+        Size::new(width as u32, height as u32)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl DrawTarget for Framebuffer {
+    type Color = TriColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(Point { x, y }, color) in pixels {
+            if let (Ok(x), Ok(y)) = (x.try_into(), y.try_into()) {
+                self.set_pixel(x, y, color);
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill(color);
+        Ok(())
+    }
+}
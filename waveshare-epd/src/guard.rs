@@ -0,0 +1,257 @@
+//! Limits on how often a panel refreshes, and how much it has.
+//!
+//! Waveshare asks for at least 180 s between refreshes of tri-color panels,
+//! and a full refresh at least every 24 h. A [`Guard`] enforces the former and
+//! reports the latter, keeping its [`Record`] in a [`Store`] so that the
+//! limits hold across runs.
+//!
+//! A deep clean is checked against the least interval once, as a whole: its
+//! steps refresh back to back, and each counts as a refresh once done.
+
+use crate::{clean, Display, Error, Health, Ink, RefreshReport, Result};
+#[cfg(feature = "config")]
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+#[cfg(feature = "config")]
+use std::{fs, path::PathBuf};
+use std::{
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// Least time between refreshes Waveshare recommends.
+pub const MIN_INTERVAL: Duration = Duration::from_secs(180);
+/// Most time between refreshes Waveshare recommends.
+pub const MAX_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Refreshes of a panel over its lifetime.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
+pub struct Record {
+    /// When the last refresh started.
+    pub last_refresh: Option<SystemTime>,
+    /// Refreshes, clears and each step of deep cleans included.
+    pub refreshes: u64,
+    /// Deep cleans.
+    pub cleans: u64,
+    /// When the next deep clean is due, see [`Policy`](clean::Policy).
+    #[cfg_attr(feature = "config", serde(default))]
+    pub clean: clean::State,
+}
+
+/// Where a [`Record`] is kept across runs.
+pub trait Store<T = Record> {
+    fn load(&mut self) -> Result<T>;
+    fn save(&mut self, record: &T) -> Result<()>;
+}
+
+/// Keeps the record for as long as the process runs.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore(Record);
+impl Store for MemoryStore {
+    fn load(&mut self) -> Result<Record> {
+        Ok(self.0)
+    }
+    fn save(&mut self, record: &Record) -> Result<()> {
+        self.0 = *record;
+        Ok(())
+    }
+}
+
+/// Keeps the record in a TOML file, which is created on the first save. Until
+/// then, the record loaded is the default one.
+#[cfg(feature = "config")]
+#[derive(Clone, Debug)]
+pub struct FileStore {
+    path: PathBuf,
+}
+#[cfg(feature = "config")]
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+#[cfg(feature = "config")]
+impl<T: Default + Serialize + DeserializeOwned> Store<T> for FileStore {
+    fn load(&mut self) -> Result<T> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+    fn save(&mut self, record: &T) -> Result<()> {
+        let text = toml::to_string(record).expect("records are valid TOML tables");
+        fs::write(&self.path, text)?;
+        Ok(())
+    }
+}
+
+/// What a [`Guard`] does with refreshes that come too early.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Early {
+    /// Fail with [`Error::TooEarly`].
+    #[default]
+    Reject,
+    /// Wait until the refresh is allowed.
+    Delay,
+}
+
+/// A [`Display`] that refreshes no more often than allowed, and counts its
+/// refreshes.
+pub struct Guard<D, S> {
+    display: D,
+    store: S,
+    min_interval: Duration,
+    max_interval: Duration,
+    early: Early,
+    /// When the refresh that was started, and not yet finished, did.
+    started: Option<SystemTime>,
+}
+impl<D: Display, S: Store> Guard<D, S> {
+    pub fn new(display: D, store: S) -> Self {
+        Self {
+            display,
+            store,
+            min_interval: MIN_INTERVAL,
+            max_interval: MAX_INTERVAL,
+            early: Early::Reject,
+            started: None,
+        }
+    }
+
+    /// Least time between the starts of two refreshes.
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = interval;
+        self
+    }
+
+    /// Time after which a refresh is due, see
+    /// [`past_max_interval`](Guard::past_max_interval).
+    pub fn with_max_interval(mut self, interval: Duration) -> Self {
+        self.max_interval = interval;
+        self
+    }
+
+    pub fn with_early(mut self, early: Early) -> Self {
+        self.early = early;
+        self
+    }
+
+    pub fn display(&self) -> &D {
+        &self.display
+    }
+
+    pub fn into_inner(self) -> D {
+        self.display
+    }
+
+    /// Refreshes so far, as stored.
+    pub fn record(&mut self) -> Result<Record> {
+        self.store.load()
+    }
+
+    /// Whether the panel went unrefreshed for longer than the most interval,
+    /// and should be refreshed as soon as possible. A panel never refreshed
+    /// didn't. The least interval is checked on refreshing instead.
+    pub fn past_max_interval(&mut self) -> Result<bool> {
+        let record = self.store.load()?;
+        Ok(record.last_refresh.is_some_and(|last| {
+            SystemTime::now()
+                .duration_since(last)
+                .is_ok_and(|elapsed| elapsed > self.max_interval)
+        }))
+    }
+
+    /// Check that a refresh is allowed now, waiting if so configured.
+    fn check(&mut self) -> Result<()> {
+        let record = self.store.load()?;
+        let Some(last) = record.last_refresh else {
+            return Ok(());
+        };
+        // A clock set back counts as no time having passed.
+        let elapsed = SystemTime::now()
+            .duration_since(last)
+            .unwrap_or(Duration::ZERO);
+        if let Some(wait) = self.min_interval.checked_sub(elapsed) {
+            if wait > Duration::ZERO {
+                match self.early {
+                    Early::Reject => return Err(Error::TooEarly { wait }),
+                    Early::Delay => thread::sleep(wait),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Run `refresh` if allowed, recording it once done.
+    fn guard<T>(&mut self, refresh: impl FnOnce(&mut D) -> Result<T>, clean: bool) -> Result<T> {
+        self.check()?;
+        let start = SystemTime::now();
+        let result = refresh(&mut self.display)?;
+        self.record_refresh(start, clean)?;
+        Ok(result)
+    }
+
+    fn record_refresh(&mut self, start: SystemTime, clean: bool) -> Result<()> {
+        let mut record = self.store.load()?;
+        record.last_refresh = Some(start);
+        if clean {
+            record.refreshes += clean::steps(self.display.inks()).len() as u64;
+            record.cleans += 1;
+            record.clean.record_clean(start);
+        } else {
+            record.refreshes += 1;
+            record.clean.record_refresh();
+        }
+        self.store.save(&record)
+    }
+}
+
+impl<D: Display, S: Store> Display for Guard<D, S> {
+    fn width(&self) -> usize {
+        self.display.width()
+    }
+    fn height(&self) -> usize {
+        self.display.height()
+    }
+    fn inks(&self) -> &'static [Ink] {
+        self.display.inks()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.display.init()
+    }
+    fn write_frame(&mut self, black: &[u8], red: &[u8]) -> Result<()> {
+        self.display.write_frame(black, red)
+    }
+    fn refresh(&mut self) -> Result<RefreshReport> {
+        self.guard(|display| display.refresh(), false)
+    }
+    /// Checks that the refresh is allowed, which is only recorded once
+    /// finished.
+    fn start_refresh(&mut self) -> Result<()> {
+        self.check()?;
+        let start = SystemTime::now();
+        self.display.start_refresh()?;
+        self.started = Some(start);
+        Ok(())
+    }
+    fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        let report = self.display.finish_refresh()?;
+        let start = self.started.take().unwrap_or_else(SystemTime::now);
+        self.record_refresh(start, false)?;
+        Ok(report)
+    }
+    fn clear(&mut self) -> Result<()> {
+        self.guard(|display| display.clear(), false)
+    }
+    fn deep_clean(&mut self) -> Result<()> {
+        self.guard(|display| display.deep_clean(), true)
+    }
+    fn sleep(&mut self) -> Result<()> {
+        self.display.sleep()
+    }
+    fn diagnose(&mut self) -> Result<Vec<Health>> {
+        self.display.diagnose()
+    }
+}
@@ -0,0 +1,143 @@
+//! Hardware abstraction over the controllers' 4-wire SPI interface.
+//!
+//! Drivers only encode the command sequences, and are generic over the
+//! backend that actually moves the bytes: real hardware, or the simulator.
+
+use crate::{Error, Result};
+use futures::Future;
+#[cfg(feature = "config")]
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+/// Access to a panel driven by a single controller.
+pub trait Interface {
+    /// Drive the reset line.
+    fn set_reset(&mut self, high: bool) -> Result<()>;
+    /// Send a command byte.
+    fn send_command(&mut self, command: u8) -> Result<()>;
+    /// Send parameters or pixel data for the last command.
+    ///
+    /// Backends split large buffers into as few transfers as they can.
+    fn send_data(&mut self, data: &[u8]) -> Result<()>;
+    /// Read the response to the last command.
+    ///
+    /// Controllers answer on the data line, which not all backends can read.
+    fn read_data(&mut self, _buf: &mut [u8]) -> Result<()> {
+        Err(Error::Unsupported("reading from the controller"))
+    }
+    /// Level of the busy line, `true` when high.
+    fn busy_high(&mut self) -> Result<bool>;
+    /// Wait for the given time.
+    fn delay(&mut self, duration: Duration);
+    /// Time since some fixed point, to measure how long the controller takes.
+    /// Wall time by default, which the simulator replaces with its own.
+    fn now(&self) -> Duration {
+        wall_clock()
+    }
+
+    /// Wait until the busy line is back to its idle level, high if
+    /// `idle_high`, or until `timeout` has elapsed.
+    ///
+    /// Returns whether the line is still busy after `timeout`. The default
+    /// implementation polls the line.
+    fn wait_idle(
+        &mut self,
+        idle_high: bool,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<bool>> {
+        const POLL: Duration = Duration::from_millis(10);
+        async move {
+            let mut waited = Duration::ZERO;
+            while self.busy_high()? != idle_high {
+                if waited >= timeout {
+                    return Ok(true);
+                }
+                self.delay(POLL);
+                waited += POLL;
+            }
+            Ok(false)
+        }
+    }
+}
+
+/// One of the controllers of a panel that is driven by several of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "config", derive(Serialize, Deserialize))]
+pub enum Controller {
+    M1,
+    S1,
+    M2,
+    S2,
+}
+impl Controller {
+    pub const ALL: [Self; 4] = [Self::M1, Self::S1, Self::M2, Self::S2];
+}
+
+/// Access to a panel driven by several controllers sharing the same bus.
+///
+/// Commands and data can be sent to several controllers at once, by selecting
+/// all of them for the transfer.
+pub trait CascadeInterface {
+    /// Drive the reset lines of all controllers.
+    fn set_reset(&mut self, high: bool) -> Result<()>;
+    /// Send a command byte to the given controllers.
+    fn send_command(&mut self, to: &[Controller], command: u8) -> Result<()>;
+    /// Send parameters or pixel data for the last command to the given
+    /// controllers.
+    fn send_data(&mut self, to: &[Controller], data: &[u8]) -> Result<()>;
+    /// Read the response of the given controller to its last command.
+    ///
+    /// Controllers answer on the data line, which not all backends can read.
+    fn read_data(&mut self, _from: Controller, _buf: &mut [u8]) -> Result<()> {
+        Err(Error::Unsupported("reading from the controller"))
+    }
+    /// Level of the busy line of the given controller, `true` when high.
+    fn busy_high(&mut self, controller: Controller) -> Result<bool>;
+    /// Wait for the given time.
+    fn delay(&mut self, duration: Duration);
+    /// Time since some fixed point, to measure how long the controllers take.
+    /// Wall time by default, which the simulator replaces with its own.
+    fn now(&self) -> Duration {
+        wall_clock()
+    }
+
+    /// Wait until the busy lines of all the given controllers are back to
+    /// their idle level, high if `idle_high`, or until `timeout` has elapsed.
+    ///
+    /// Returns a controller whose line is still busy after `timeout`, if any.
+    /// The default implementation polls the lines.
+    fn wait_idle(
+        &mut self,
+        controllers: &[Controller],
+        idle_high: bool,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<Option<Controller>>> {
+        const POLL: Duration = Duration::from_millis(10);
+        async move {
+            let mut waited = Duration::ZERO;
+            for &controller in controllers {
+                while self.busy_high(controller)? != idle_high {
+                    if waited >= timeout {
+                        return Ok(Some(controller));
+                    }
+                    self.delay(POLL);
+                    waited += POLL;
+                }
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Time since the first call, which never goes back.
+fn wall_clock() -> Duration {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed()
+}
+
+/// Largest transfer spidev accepts, unless its `bufsiz` parameter is raised.
+#[cfg(any(feature = "rpi", feature = "linux"))]
+pub(crate) const SPIDEV_BUFSIZ: usize = 4096;
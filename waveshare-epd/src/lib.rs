@@ -1,7 +1,54 @@
+pub mod canvas;
+pub mod clean;
+pub mod config;
+pub mod diagnose;
+mod display;
+#[cfg(any(
+    feature = "epd_2in13_v4",
+    feature = "epd_2in7",
+    feature = "epd_2in7b",
+    feature = "epd_4in2",
+    feature = "epd_5in65f",
+    feature = "epd_7in5_v2",
+    feature = "epd_7in5b_v2"
+))]
+mod driver;
 #[cfg(feature = "epd_12in48b")]
 pub mod epd_12in48b;
+#[cfg(feature = "epd_2in13_v4")]
+pub mod epd_2in13_v4;
+#[cfg(feature = "epd_2in7")]
+pub mod epd_2in7;
 #[cfg(feature = "epd_2in7b")]
 pub mod epd_2in7b;
+#[cfg(feature = "epd_4in2")]
+pub mod epd_4in2;
+#[cfg(feature = "epd_5in65f")]
+pub mod epd_5in65f;
+#[cfg(feature = "epd_7in5_v2")]
+pub mod epd_7in5_v2;
+#[cfg(feature = "epd_7in5b_v2")]
+pub mod epd_7in5b_v2;
 mod error;
+#[cfg(feature = "esp")]
+pub mod esp;
+pub mod framebuffer;
+pub mod guard;
+pub mod interface;
+#[cfg(feature = "linux")]
+pub mod linux;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod packing;
+pub mod pattern;
+pub mod power;
+#[cfg(feature = "rpi")]
+pub mod rpi;
+#[cfg(feature = "sim")]
+pub mod sim;
+#[cfg(feature = "trace")]
+pub mod trace;
 
+pub use diagnose::Health;
+pub use display::{Display, Ink, RefreshReport, Timings};
 pub use error::{EpdError as Error, Result};
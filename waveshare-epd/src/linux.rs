@@ -0,0 +1,152 @@
+//! Generic Linux backend, through `/dev/spidevX.Y` and `/dev/gpiochipN`.
+//!
+//! Works on any board whose kernel exposes the SPI bus and the GPIO lines,
+//! such as Rockchip or Allwinner ones. Pins are line offsets of the GPIO
+//! controller, and a chip select pin can't be one the SPI driver already
+//! claimed.
+
+use crate::{
+    config::Config,
+    interface::{Interface, SPIDEV_BUFSIZ},
+    Result,
+};
+use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+use log::debug;
+use spidev::{SpiModeFlags, Spidev, SpidevOptions};
+use std::{io::Write, thread, time::Duration};
+
+const CONSUMER: &str = "waveshare-epd";
+
+/// Reading from the controller isn't supported, as most SPI controllers lack
+/// the bidirectional mode the controller answers in.
+pub struct LinuxInterface {
+    reset: LineHandle,
+    dc: LineHandle,
+    cs: Option<LineHandle>,
+    busy: LineHandle,
+    spi: Spidev,
+}
+impl LinuxInterface {
+    pub fn with_config(config: &Config) -> Result<Self> {
+        let mut spi = Spidev::open(format!(
+            "/dev/spidev{}.{}",
+            config.spi_bus, config.spi_device
+        ))?;
+        let mut mode = SpiModeFlags::SPI_MODE_0;
+        if config.cs_pin.is_some() {
+            mode |= SpiModeFlags::SPI_NO_CS;
+        }
+        spi.configure(
+            &SpidevOptions::new()
+                .bits_per_word(8)
+                .max_speed_hz(config.spi_speed_hz)
+                .mode(mode)
+                .build(),
+        )?;
+
+        let mut chip = Chip::new(format!("/dev/gpiochip{}", config.gpio_chip))?;
+        let mut output = |line, default| {
+            chip.get_line(line)?
+                .request(LineRequestFlags::OUTPUT, default, CONSUMER)
+        };
+        let reset = output(config.reset_pin, 1)?;
+        let dc = output(config.dc_pin, 0)?;
+        let cs = config.cs_pin.map(|line| output(line, 1)).transpose()?;
+        let busy = chip
+            .get_line(config.busy_pin)?
+            .request(LineRequestFlags::INPUT, 0, CONSUMER)?;
+        Ok(Self {
+            reset,
+            dc,
+            cs,
+            busy,
+            spi,
+        })
+    }
+
+    fn transfer(&mut self, dc: u8, data: &[u8]) -> Result<()> {
+        self.dc.set_value(dc)?;
+        if let Some(cs) = &self.cs {
+            cs.set_value(0)?;
+        }
+        write_chunked(&mut self.spi, data)?;
+        if let Some(cs) = &self.cs {
+            cs.set_value(1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Interface for LinuxInterface {
+    fn set_reset(&mut self, high: bool) -> Result<()> {
+        self.reset.set_value(high.into())?;
+        Ok(())
+    }
+
+    fn send_command(&mut self, command: u8) -> Result<()> {
+        self.transfer(0, &[command])
+    }
+
+    fn send_data(&mut self, data: &[u8]) -> Result<()> {
+        self.transfer(1, data)
+    }
+
+    fn busy_high(&mut self) -> Result<bool> {
+        Ok(self.busy.get_value()? != 0)
+    }
+
+    fn delay(&mut self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Write `data` in as few transfers as spidev accepts, one `write` syscall
+/// each.
+fn write_chunked(spi: &mut impl Write, data: &[u8]) -> Result<()> {
+    for chunk in data.chunks(SPIDEV_BUFSIZ) {
+        spi.write_all(chunk)?;
+    }
+    Ok(())
+}
+
+impl Drop for LinuxInterface {
+    fn drop(&mut self) {
+        debug!("close 5V, Module enters 0 power consumption ...");
+        let _ = self.reset.set_value(0);
+        let _ = self.dc.set_value(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the length of each write.
+    #[derive(Default)]
+    struct Writes(Vec<usize>);
+    impl Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.push(buf.len());
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn chunks() {
+        let mut writes = Writes::default();
+        // A frame of the 2.7" panel.
+        write_chunked(&mut writes, &[0xFF; 5808]).unwrap();
+        assert_eq!(writes.0, [4096, 1712]);
+
+        let mut writes = Writes::default();
+        write_chunked(&mut writes, &[0xFF; 2 * 4096]).unwrap();
+        assert_eq!(writes.0, [4096, 4096]);
+
+        let mut writes = Writes::default();
+        write_chunked(&mut writes, &[0x12]).unwrap();
+        assert_eq!(writes.0, [1]);
+    }
+}
@@ -0,0 +1,178 @@
+//! Backend recording the traffic on the bus, for testing drivers.
+
+use crate::{
+    interface::{CascadeInterface, Controller, Interface},
+    Error, Result,
+};
+use std::{collections::HashMap, time::Duration};
+
+/// Something a driver did on the bus.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Reset(bool),
+    Command(Vec<Controller>, u8),
+    /// Data sent to the same controllers in a row, merged.
+    Data(Vec<Controller>, Vec<u8>),
+    /// Bytes read from a controller.
+    Read(Controller, Vec<u8>),
+    Delay(Duration),
+}
+
+/// A panel that never gets busy, and records all the traffic.
+///
+/// Its busy lines stay at the idle level, high unless configured otherwise.
+///
+/// Implements both [`Interface`] and [`CascadeInterface`]. The former talks
+/// to [`Controller::M1`].
+#[derive(Debug)]
+pub struct Mock {
+    transcript: Vec<Event>,
+    transfers: usize,
+    idle_high: bool,
+    /// Answer to each command, when read.
+    responses: HashMap<u8, Vec<u8>>,
+    /// Whether reading is supported, like on most backends.
+    reads: bool,
+    command: Option<u8>,
+}
+impl Default for Mock {
+    fn default() -> Self {
+        Self {
+            transcript: Vec::new(),
+            transfers: 0,
+            idle_high: true,
+            responses: HashMap::new(),
+            reads: true,
+            command: None,
+        }
+    }
+}
+impl Mock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Level of the busy lines.
+    pub fn with_idle_high(mut self, idle_high: bool) -> Self {
+        self.idle_high = idle_high;
+        self
+    }
+
+    /// Answer `response` when reading after `command`. Reads are zeroes
+    /// otherwise.
+    pub fn with_response(mut self, command: u8, response: &[u8]) -> Self {
+        self.responses.insert(command, response.to_vec());
+        self
+    }
+
+    /// Fail reads with [`Error::Unsupported`], as the Raspberry Pi backend
+    /// does.
+    pub fn with_reads(mut self, reads: bool) -> Self {
+        self.reads = reads;
+        self
+    }
+
+    pub fn transcript(&self) -> &[Event] {
+        &self.transcript
+    }
+
+    /// Forget the transcript so far.
+    pub fn clear(&mut self) {
+        self.transcript.clear();
+        self.transfers = 0;
+    }
+
+    /// Number of SPI transfers, commands included.
+    pub fn transfers(&self) -> usize {
+        self.transfers
+    }
+
+    /// Commands received by `controller`, in order.
+    pub fn commands(&self, controller: Controller) -> Vec<u8> {
+        self.transcript
+            .iter()
+            .filter_map(|event| match event {
+                Event::Command(to, command) if to.contains(&controller) => Some(*command),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Data received by `controller` after each `command`, concatenated.
+    pub fn data(&self, controller: Controller, command: u8) -> Vec<u8> {
+        let mut last = None;
+        let mut data = Vec::new();
+        for event in &self.transcript {
+            match event {
+                Event::Command(to, c) if to.contains(&controller) => last = Some(*c),
+                Event::Data(to, bytes) if to.contains(&controller) && last == Some(command) => {
+                    data.extend_from_slice(bytes)
+                }
+                _ => {}
+            }
+        }
+        data
+    }
+}
+
+impl CascadeInterface for Mock {
+    fn set_reset(&mut self, high: bool) -> Result<()> {
+        self.transcript.push(Event::Reset(high));
+        Ok(())
+    }
+    fn send_command(&mut self, to: &[Controller], command: u8) -> Result<()> {
+        self.transfers += 1;
+        self.command = Some(command);
+        self.transcript.push(Event::Command(to.to_vec(), command));
+        Ok(())
+    }
+    fn send_data(&mut self, to: &[Controller], data: &[u8]) -> Result<()> {
+        self.transfers += 1;
+        match self.transcript.last_mut() {
+            Some(Event::Data(last, bytes)) if last == to => bytes.extend_from_slice(data),
+            _ => self
+                .transcript
+                .push(Event::Data(to.to_vec(), data.to_vec())),
+        }
+        Ok(())
+    }
+    fn read_data(&mut self, from: Controller, buf: &mut [u8]) -> Result<()> {
+        if !self.reads {
+            return Err(Error::Unsupported("reading from the controller"));
+        }
+        self.transfers += 1;
+        let response = self.command.and_then(|c| self.responses.get(&c));
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = response.and_then(|r| r.get(i)).copied().unwrap_or_default();
+        }
+        self.transcript.push(Event::Read(from, buf.to_vec()));
+        Ok(())
+    }
+    fn busy_high(&mut self, _controller: Controller) -> Result<bool> {
+        Ok(self.idle_high)
+    }
+    fn delay(&mut self, duration: Duration) {
+        self.transcript.push(Event::Delay(duration));
+    }
+}
+
+impl Interface for Mock {
+    fn set_reset(&mut self, high: bool) -> Result<()> {
+        CascadeInterface::set_reset(self, high)
+    }
+    fn send_command(&mut self, command: u8) -> Result<()> {
+        CascadeInterface::send_command(self, &[Controller::M1], command)
+    }
+    fn send_data(&mut self, data: &[u8]) -> Result<()> {
+        CascadeInterface::send_data(self, &[Controller::M1], data)
+    }
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<()> {
+        CascadeInterface::read_data(self, Controller::M1, buf)
+    }
+    fn busy_high(&mut self) -> Result<bool> {
+        CascadeInterface::busy_high(self, Controller::M1)
+    }
+    fn delay(&mut self, duration: Duration) {
+        CascadeInterface::delay(self, duration)
+    }
+}
@@ -0,0 +1,222 @@
+//! Conversion between images and the packed planes panels take.
+//!
+//! Planes are stored row by row in the panel's native orientation, each row
+//! padded to a whole number of bytes. Pixels take 1 bit, or 2 for grayscale.
+
+use crate::{Error, Result};
+use image::{GrayImage, Luma};
+
+/// Clockwise rotation of the drawing relative to the panel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    #[default]
+    Rotate0,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+impl Rotation {
+    /// Position on a panel of native size `width`×`height` of the pixel at
+    /// `(x, y)` of the drawing.
+    pub fn to_native(self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Rotation::Rotate0 => (x, y),
+            Rotation::Rotate90 => (width - y - 1, x),
+            Rotation::Rotate180 => (width - x - 1, height - y - 1),
+            Rotation::Rotate270 => (y, height - x - 1),
+        }
+    }
+
+    /// Position in the drawing of the pixel at `(x, y)` of a panel of native
+    /// size `width`×`height`.
+    pub fn to_drawing(self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Rotation::Rotate0 => (x, y),
+            Rotation::Rotate90 => (y, width - x - 1),
+            Rotation::Rotate180 => (width - x - 1, height - y - 1),
+            Rotation::Rotate270 => (height - y - 1, x),
+        }
+    }
+}
+
+/// Order of the pixels within each byte.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BitOrder {
+    /// The most significant bit is the leftmost pixel.
+    #[default]
+    MsbFirst,
+    LsbFirst,
+}
+
+/// Bits per pixel of a plane.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Depth {
+    /// 1 bit: ink, or not.
+    #[default]
+    Mono,
+    /// 2 bits: a level from 0, black, to 3, white. Images are quantized to
+    /// the nearest level.
+    Gray4,
+}
+impl Depth {
+    pub fn bits(self) -> usize {
+        match self {
+            Depth::Mono => 1,
+            Depth::Gray4 => 2,
+        }
+    }
+
+    /// Highest level, white.
+    fn max(self) -> u8 {
+        (1 << self.bits()) - 1
+    }
+
+    /// Level of a pixel of value `luma`. Only black is ink in 1 bit.
+    fn level(self, luma: u8) -> u8 {
+        match self {
+            Depth::Mono => u8::from(luma != 0),
+            Depth::Gray4 => ((u16::from(luma) + 42) / 85) as u8,
+        }
+    }
+}
+
+/// Layout of a plane, and how images map to it.
+///
+/// By default the plane has the format expected by
+/// [`Display::write_frame`](crate::Display::write_frame): most significant
+/// bit first, and a cleared bit where the ink is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Packing {
+    /// Size of the panel, in its native orientation.
+    width: usize,
+    height: usize,
+    rotation: Rotation,
+    mirror: bool,
+    bit_order: BitOrder,
+    inverted: bool,
+    depth: Depth,
+}
+impl Packing {
+    /// Planes of a panel of the given native size.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            rotation: Rotation::Rotate0,
+            mirror: false,
+            bit_order: BitOrder::MsbFirst,
+            inverted: false,
+            depth: Depth::Mono,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Flip images horizontally, before rotating them.
+    pub fn with_mirror(mut self, mirror: bool) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    pub fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Set bits where the ink is, instead of clearing them. Gray levels
+    /// count from white instead.
+    pub fn with_inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    pub fn with_depth(mut self, depth: Depth) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Width and height of images, after rotation.
+    pub fn dimensions(&self) -> (usize, usize) {
+        match self.rotation {
+            Rotation::Rotate0 | Rotation::Rotate180 => (self.width, self.height),
+            Rotation::Rotate90 | Rotation::Rotate270 => (self.height, self.width),
+        }
+    }
+
+    /// Size of a plane, in bytes.
+    pub fn len(&self) -> usize {
+        self.row_bytes() * self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Pack `image`, whose black pixels are the ink. Other values are white
+    /// in 1 bit, and gray levels otherwise.
+    pub fn pack(&self, image: &GrayImage) -> Result<Vec<u8>> {
+        self.check_dimensions(image)?;
+        let max = self.depth.max();
+        let mut plane = vec![if self.inverted { 0x00 } else { 0xFF }; self.len()];
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let level = self.depth.level(pixel.0[0]);
+            let value = if self.inverted { max - level } else { level };
+            let (index, shift) = self.locate(x as usize, y as usize);
+            plane[index] = plane[index] & !(max << shift) | value << shift;
+        }
+        Ok(plane)
+    }
+
+    /// Unpack `plane` into an image, black where the ink is and white
+    /// elsewhere, or evenly spaced grays.
+    pub fn unpack(&self, plane: &[u8]) -> Result<GrayImage> {
+        if plane.len() != self.len() {
+            return Err(Error::InvalidLength {
+                expected: self.len(),
+                actual: plane.len(),
+            });
+        }
+        let (width, height) = self.dimensions();
+        let max = self.depth.max();
+        Ok(GrayImage::from_fn(width as u32, height as u32, |x, y| {
+            let (index, shift) = self.locate(x as usize, y as usize);
+            let value = plane[index] >> shift & max;
+            let level = if self.inverted { max - value } else { value };
+            Luma([level * (255 / max)])
+        }))
+    }
+
+    fn check_dimensions(&self, image: &GrayImage) -> Result<()> {
+        let expected = self.dimensions();
+        let actual = (image.width() as usize, image.height() as usize);
+        if actual != expected {
+            return Err(Error::InvalidDimensions { expected, actual });
+        }
+        Ok(())
+    }
+
+    fn row_bytes(&self) -> usize {
+        (self.width * self.depth.bits()).div_ceil(8)
+    }
+
+    /// Byte of the pixel at `(x, y)` of an image, and the offset of its bits.
+    fn locate(&self, x: usize, y: usize) -> (usize, u32) {
+        let x = if self.mirror {
+            self.dimensions().0 - x - 1
+        } else {
+            x
+        };
+        let (x, y) = self.rotation.to_native(x, y, self.width, self.height);
+        let bits = self.depth.bits();
+        let per_byte = 8 / bits;
+        let slot = x % per_byte;
+        let shift = match self.bit_order {
+            BitOrder::MsbFirst => 8 - bits * (slot + 1),
+            BitOrder::LsbFirst => bits * slot,
+        };
+        (x / per_byte + y * self.row_bytes(), shift as u32)
+    }
+}
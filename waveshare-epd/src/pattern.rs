@@ -0,0 +1,198 @@
+//! Test patterns, to bring up and check panels.
+
+use crate::{
+    framebuffer::{Framebuffer, TriColor},
+    Ink,
+};
+
+/// Side of the dither matrix of [`Pattern::Ramp`].
+const BAYER_SIZE: usize = 4;
+const BAYER: [[u8; BAYER_SIZE]; BAYER_SIZE] =
+    [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Size of the glyphs of the quadrant labels, before scaling.
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+
+/// A picture that shows what is wrong with a panel, or its wiring.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// Alternating black and white squares of the given side, in pixels.
+    Checkerboard(usize),
+    /// Each quadrant framed and labeled, to check that the controllers of a
+    /// panel drive the right part of it.
+    Quadrants {
+        /// Where the quadrants meet.
+        x: usize,
+        y: usize,
+        /// Top left, top right, bottom left and bottom right labels.
+        labels: [&'static str; 4],
+    },
+    /// A vertical bar of each ink.
+    ColorBars,
+    /// Gradients from each ink to white, dithered.
+    Ramp,
+    /// Lines every given number of pixels, and around the edges.
+    Grid(usize),
+}
+impl Pattern {
+    /// Quadrants of equal size, labeled 1 to 4.
+    pub fn quadrants(width: usize, height: usize) -> Self {
+        Self::Quadrants {
+            x: width / 2,
+            y: height / 2,
+            labels: ["1", "2", "3", "4"],
+        }
+    }
+
+    /// Quadrants of the 12.48" panel, labeled with their controller.
+    #[cfg(feature = "epd_12in48b")]
+    pub fn epd_12in48b_quadrants() -> Self {
+        use crate::epd_12in48b::{HALF_HEIGHT, LEFT_WIDTH};
+        Self::Quadrants {
+            x: LEFT_WIDTH,
+            y: HALF_HEIGHT,
+            labels: ["S2", "M2", "M1", "S1"],
+        }
+    }
+
+    /// Draw the pattern for a panel of the given native size, using only the
+    /// `inks` it is able to show.
+    pub fn render(&self, width: usize, height: usize, inks: &[Ink]) -> Framebuffer {
+        let mut frame = Framebuffer::new(width, height);
+        let accent = if inks.contains(&Ink::Red) {
+            TriColor::Red
+        } else {
+            TriColor::Black
+        };
+        match *self {
+            Pattern::Checkerboard(size) => {
+                let size = size.max(1);
+                fill(&mut frame, |x, y| {
+                    ((x / size + y / size) % 2 == 0).then_some(TriColor::Black)
+                });
+            }
+            Pattern::Quadrants { x, y, labels } => {
+                let areas = [
+                    (0, 0, x, y),
+                    (x, 0, width - x, y),
+                    (0, y, x, height - y),
+                    (x, y, width - x, height - y),
+                ];
+                for ((x0, y0, w, h), label) in areas.into_iter().zip(labels) {
+                    frame_rect(&mut frame, x0, y0, w, h, accent);
+                    draw_label(&mut frame, x0, y0, w, h, label);
+                }
+            }
+            Pattern::ColorBars => {
+                let bars: Vec<_> = [TriColor::Black, TriColor::White, TriColor::Red]
+                    .into_iter()
+                    .filter(|&color| inks.contains(&color.into()))
+                    .collect();
+                fill(&mut frame, |x, _| Some(bars[x * bars.len() / width]));
+            }
+            Pattern::Ramp => {
+                let ramps: Vec<_> = [TriColor::Black, TriColor::Red]
+                    .into_iter()
+                    .filter(|&color| inks.contains(&color.into()))
+                    .collect();
+                fill(&mut frame, |x, y| {
+                    let color = ramps[y * ramps.len() / height];
+                    // Ink coverage from 16 at the left edge down to 0.
+                    let coverage = (width - x) * (BAYER_SIZE * BAYER_SIZE) / width;
+                    let threshold = BAYER[y % BAYER_SIZE][x % BAYER_SIZE];
+                    (usize::from(threshold) < coverage).then_some(color)
+                });
+            }
+            Pattern::Grid(spacing) => {
+                let spacing = spacing.max(1);
+                fill(&mut frame, |x, y| {
+                    let line = x % spacing == 0 || y % spacing == 0;
+                    let edge = x == width - 1 || y == height - 1;
+                    (line || edge).then_some(TriColor::Black)
+                });
+                // Mark the origin, to tell the orientation.
+                let side = (spacing / 2).max(1);
+                for y in 1..side.min(height) {
+                    for x in 1..side.min(width) {
+                        frame.set_pixel(x, y, accent);
+                    }
+                }
+            }
+        }
+        frame
+    }
+}
+
+/// Set the pixels `color` returns an ink for, leaving the others white.
+fn fill(frame: &mut Framebuffer, color: impl Fn(usize, usize) -> Option<TriColor>) {
+    let (width, height) = frame.dimensions();
+    for y in 0..height {
+        for x in 0..width {
+            if let Some(color) = color(x, y) {
+                frame.set_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+/// Draw the outline of a rectangle.
+fn frame_rect(frame: &mut Framebuffer, x0: usize, y0: usize, w: usize, h: usize, color: TriColor) {
+    if w == 0 || h == 0 {
+        return;
+    }
+    for x in x0..x0 + w {
+        frame.set_pixel(x, y0, color);
+        frame.set_pixel(x, y0 + h - 1, color);
+    }
+    for y in y0..y0 + h {
+        frame.set_pixel(x0, y, color);
+        frame.set_pixel(x0 + w - 1, y, color);
+    }
+}
+
+/// Draw `label` in black, as large as fits centered in a rectangle.
+fn draw_label(frame: &mut Framebuffer, x0: usize, y0: usize, w: usize, h: usize, label: &str) {
+    let glyphs: Vec<_> = label.chars().map(glyph).collect();
+    // One blank column between glyphs, and a margin of half a glyph.
+    let columns = glyphs.len() * (GLYPH_WIDTH + 1) + GLYPH_WIDTH;
+    let scale = (w / columns).min(h / (2 * GLYPH_HEIGHT));
+    if scale == 0 {
+        return;
+    }
+    let left = x0 + (w - (glyphs.len() * (GLYPH_WIDTH + 1) - 1) * scale) / 2;
+    let top = y0 + (h - GLYPH_HEIGHT * scale) / 2;
+    for (i, rows) in glyphs.iter().enumerate() {
+        let left = left + i * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (0x10 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        frame.set_pixel(
+                            left + column * scale + dx,
+                            top + row * scale + dy,
+                            TriColor::Black,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Rows of the 5×7 glyph of `c`, most significant bit to the left. Only the
+/// characters of the labels are drawn; the others are blank.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c {
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
@@ -0,0 +1,217 @@
+//! Power states of a panel, tracked by the type system.
+//!
+//! A [`Panel`] only offers the operations that are valid in its current
+//! state, and puts the panel into deep sleep when dropped, so that neither an
+//! error nor a panic leaves high voltage applied.
+//!
+//! ```
+//! use waveshare_epd::{power::Panel, Display, Result};
+//!
+//! fn show(display: impl Display, black: &[u8], red: &[u8]) -> Result<()> {
+//!     let mut panel = Panel::new(display).init()?;
+//!     panel.write_frame(black, red)?;
+//!     panel.refresh()?;
+//!     panel.sleep()?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Frames can't be written before initializing:
+//!
+//! ```compile_fail
+//! # use waveshare_epd::{power::Panel, Display, Result};
+//! fn show(display: impl Display, black: &[u8], red: &[u8]) -> Result<()> {
+//!     Panel::new(display).write_frame(black, red)
+//! }
+//! ```
+//!
+//! Nor after putting the panel to sleep:
+//!
+//! ```compile_fail
+//! # use waveshare_epd::{power::Panel, Display, Result};
+//! fn show(display: impl Display, black: &[u8], red: &[u8]) -> Result<()> {
+//!     let mut panel = Panel::new(display).init()?.sleep()?;
+//!     panel.write_frame(black, red)
+//! }
+//! ```
+
+use crate::{Display, Health, RefreshReport, Result};
+use log::warn;
+use std::marker::PhantomData;
+
+/// Fresh out of [`Panel::new`], in whatever state the controller was left.
+pub enum Uninitialized {}
+/// Initialized, and able to take frames.
+pub enum Ready {}
+/// Showing a frame, which must be waited for before doing anything else.
+pub enum Refreshing {}
+/// In deep sleep, with the power off.
+pub enum Sleeping {}
+
+/// One of the states of a [`Panel`].
+pub trait State: private::Sealed {
+    /// Whether the panel is known to be in deep sleep.
+    #[doc(hidden)]
+    const ASLEEP: bool;
+    /// Whether a refresh may still be running.
+    #[doc(hidden)]
+    const REFRESHING: bool = false;
+}
+impl State for Uninitialized {
+    const ASLEEP: bool = false;
+}
+impl State for Ready {
+    const ASLEEP: bool = false;
+}
+impl State for Refreshing {
+    const ASLEEP: bool = false;
+    const REFRESHING: bool = true;
+}
+impl State for Sleeping {
+    const ASLEEP: bool = true;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for super::Uninitialized {}
+    impl Sealed for super::Ready {}
+    impl Sealed for super::Refreshing {}
+    impl Sealed for super::Sleeping {}
+}
+
+/// A [`Display`] in power state `S`.
+///
+/// Unless it is [`Sleeping`], dropping it powers the panel off and puts it
+/// into deep sleep. A panel dropped while [`Refreshing`] is first waited for,
+/// as long as the driver's timeout allows, since sleeping in the middle of a
+/// refresh can damage it.
+pub struct Panel<D: Display, S: State> {
+    /// Only taken when moving to another state.
+    display: Option<D>,
+    state: PhantomData<S>,
+}
+
+impl<D: Display> Panel<D, Uninitialized> {
+    pub fn new(display: D) -> Self {
+        Self {
+            display: Some(display),
+            state: PhantomData,
+        }
+    }
+
+    /// Reset the panel and check the connection to each of its controllers.
+    pub fn diagnose(&mut self) -> Result<Vec<Health>> {
+        self.inner_mut().diagnose()
+    }
+
+    /// Wake the panel up and configure it.
+    pub fn init(mut self) -> Result<Panel<D, Ready>> {
+        self.inner_mut().init()?;
+        Ok(self.into_state())
+    }
+}
+
+impl<D: Display> Panel<D, Ready> {
+    /// Write a full frame to the panel's memory, see
+    /// [`Display::write_frame`].
+    pub fn write_frame(&mut self, black: &[u8], red: &[u8]) -> Result<()> {
+        self.inner_mut().write_frame(black, red)
+    }
+
+    /// The display, to write frames in formats of its own, such as the
+    /// grayscale frames of the 2.7" panel.
+    pub fn display_mut(&mut self) -> &mut D {
+        self.inner_mut()
+    }
+
+    /// Show the frame that was last written, waiting until it is.
+    pub fn refresh(&mut self) -> Result<RefreshReport> {
+        self.inner_mut().refresh()
+    }
+
+    /// Start showing the frame that was last written.
+    pub fn start_refresh(mut self) -> Result<Panel<D, Refreshing>> {
+        self.inner_mut().start_refresh()?;
+        Ok(self.into_state())
+    }
+
+    /// Turn the whole panel white.
+    pub fn clear(&mut self) -> Result<()> {
+        self.inner_mut().clear()
+    }
+
+    /// Cycle the whole panel through its inks, see [`Display::deep_clean`].
+    pub fn deep_clean(&mut self) -> Result<()> {
+        self.inner_mut().deep_clean()
+    }
+
+    /// Power the panel off and put it into deep sleep.
+    pub fn sleep(mut self) -> Result<Panel<D, Sleeping>> {
+        self.inner_mut().sleep()?;
+        Ok(self.into_state())
+    }
+}
+
+impl<D: Display> Panel<D, Refreshing> {
+    /// Wait for the refresh to end.
+    pub fn finish_refresh(mut self) -> Result<(Panel<D, Ready>, RefreshReport)> {
+        let report = self.inner_mut().finish_refresh()?;
+        Ok((self.into_state(), report))
+    }
+}
+
+impl<D: Display> Panel<D, Sleeping> {
+    /// Wake the panel up and configure it again.
+    pub fn init(mut self) -> Result<Panel<D, Ready>> {
+        self.inner_mut().init()?;
+        Ok(self.into_state())
+    }
+
+    /// Give the display back, now that it is safe to.
+    pub fn into_inner(mut self) -> D {
+        self.display
+            .take()
+            .expect("display is only taken when dropped")
+    }
+}
+
+impl<D: Display, S: State> Panel<D, S> {
+    pub fn display(&self) -> &D {
+        self.display
+            .as_ref()
+            .expect("display is only taken when dropped")
+    }
+
+    fn inner_mut(&mut self) -> &mut D {
+        self.display
+            .as_mut()
+            .expect("display is only taken when dropped")
+    }
+
+    fn into_state<T: State>(mut self) -> Panel<D, T> {
+        Panel {
+            display: self.display.take(),
+            state: PhantomData,
+        }
+    }
+}
+
+impl<D: Display, S: State> Drop for Panel<D, S> {
+    fn drop(&mut self) {
+        if S::ASLEEP {
+            return;
+        }
+        if let Some(display) = &mut self.display {
+            if S::REFRESHING {
+                // Sleep anyway after a timeout, rather than leave the panel
+                // powered.
+                if let Err(error) = display.finish_refresh() {
+                    warn!("Failed to wait for the refresh: {error}");
+                }
+            }
+            if let Err(error) = display.sleep() {
+                warn!("Failed to put the panel to sleep: {error}");
+            }
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Raspberry Pi backend.
+
+use crate::{
+    config::Config,
+    interface::{Interface, SPIDEV_BUFSIZ},
+    Error, Result,
+};
+use futures::{
+    future::{self, Either},
+    task::AtomicWaker,
+    Future,
+};
+use log::debug;
+use rppal::{
+    gpio::{Gpio, InputPin, Level, OutputPin, Trigger},
+    spi::{Bus, Mode, SlaveSelect, Spi},
+};
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    task::{Context, Poll},
+    thread,
+    time::Duration,
+};
+
+/// Reading from the controller isn't supported: the Pi's SPI driver lacks the
+/// bidirectional mode the controller answers in.
+pub struct RpiInterface {
+    reset_pin: OutputPin,
+    dc_pin: OutputPin,
+    cs_pin: Option<OutputPin>,
+    busy_pin: InputPin,
+    spi: Spi,
+}
+impl RpiInterface {
+    /// Wired as Waveshare's e-Paper HAT.
+    pub fn new() -> Result<Self> {
+        Self::with_config(&Config::default())
+    }
+
+    pub fn with_config(config: &Config) -> Result<Self> {
+        let gpio = Gpio::new()?;
+        let pin = |pin: u32| {
+            u8::try_from(pin)
+                .map_err(|_| Error::InvalidConfig(format!("no GPIO {pin}")))
+                .and_then(|pin| Ok(gpio.get(pin)?))
+        };
+        let spi = Spi::new(
+            bus(config.spi_bus)?,
+            slave_select(config.spi_device)?,
+            config.spi_speed_hz,
+            Mode::Mode0,
+        )?;
+        Ok(Self {
+            reset_pin: pin(config.reset_pin)?.into_output(),
+            dc_pin: pin(config.dc_pin)?.into_output(),
+            cs_pin: config
+                .cs_pin
+                .map(|cs_pin| Ok::<_, Error>(pin(cs_pin)?.into_output()))
+                .transpose()?,
+            busy_pin: pin(config.busy_pin)?.into_input(),
+            spi,
+        })
+    }
+
+    fn select(&mut self, selected: bool) {
+        if let Some(cs_pin) = &mut self.cs_pin {
+            cs_pin.write((!selected).into());
+        }
+    }
+}
+
+impl Interface for RpiInterface {
+    fn set_reset(&mut self, high: bool) -> Result<()> {
+        self.reset_pin.write(high.into());
+        Ok(())
+    }
+
+    fn send_command(&mut self, command: u8) -> Result<()> {
+        self.dc_pin.set_low();
+        self.select(true);
+        self.spi.write(&[command])?;
+        self.select(false);
+        Ok(())
+    }
+
+    fn send_data(&mut self, data: &[u8]) -> Result<()> {
+        self.dc_pin.set_high();
+        self.select(true);
+        for chunk in data.chunks(SPIDEV_BUFSIZ) {
+            self.spi.write(chunk)?;
+        }
+        self.select(false);
+        Ok(())
+    }
+
+    fn busy_high(&mut self) -> Result<bool> {
+        Ok(self.busy_pin.is_high())
+    }
+
+    fn delay(&mut self, duration: Duration) {
+        thread::sleep(duration);
+    }
+
+    /// Waits for an edge interrupt on the busy line, without blocking.
+    fn wait_idle(
+        &mut self,
+        idle_high: bool,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<bool>> {
+        let (idle, trigger) = if idle_high {
+            (Level::High, Trigger::RisingEdge)
+        } else {
+            (Level::Low, Trigger::FallingEdge)
+        };
+        async move {
+            if self.busy_pin.read() == idle {
+                return Ok(false);
+            }
+            let edge = Arc::new(Signal::default());
+            let signal = edge.clone();
+            self.busy_pin
+                .set_async_interrupt(trigger, move |_| signal.set())?;
+            let mut timeout = Timeout::new(timeout);
+            // The line may have settled before the interrupt was set, or
+            // bounced since.
+            while self.busy_pin.read() != idle {
+                match future::select(edge.wait(), &mut timeout).await {
+                    Either::Left(_) => edge.reset(),
+                    Either::Right(_) => break,
+                }
+            }
+            self.busy_pin.clear_async_interrupt()?;
+            Ok(self.busy_pin.read() != idle)
+        }
+    }
+}
+
+/// Set from another thread, waking the task waiting for it.
+#[derive(Default)]
+struct Signal {
+    set: AtomicBool,
+    waker: AtomicWaker,
+}
+impl Signal {
+    fn set(&self) {
+        self.set.store(true, Ordering::Release);
+        self.waker.wake();
+    }
+
+    fn reset(&self) {
+        self.set.store(false, Ordering::Release);
+    }
+
+    fn poll(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.waker.register(cx.waker());
+        if self.set.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn wait(&self) -> impl Future<Output = ()> + Unpin + '_ {
+        future::poll_fn(|cx| self.poll(cx))
+    }
+}
+
+/// A future that completes after a duration, on a thread of its own. Dropping
+/// it stops the thread.
+struct Timeout {
+    signal: Arc<Signal>,
+    /// Dropped to wake the thread early, without signalling.
+    _cancel: mpsc::Sender<()>,
+}
+impl Timeout {
+    fn new(duration: Duration) -> Self {
+        let signal = Arc::new(Signal::default());
+        let (cancel, cancelled) = mpsc::channel();
+        let elapsed = signal.clone();
+        thread::spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = cancelled.recv_timeout(duration) {
+                elapsed.set();
+            }
+        });
+        Self {
+            signal,
+            _cancel: cancel,
+        }
+    }
+}
+impl Future for Timeout {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.signal.poll(cx)
+    }
+}
+
+impl Drop for RpiInterface {
+    fn drop(&mut self) {
+        debug!("close 5V, Module enters 0 power consumption ...");
+        self.reset_pin.set_low();
+        self.dc_pin.set_low();
+    }
+}
+
+fn bus(bus: u8) -> Result<Bus> {
+    Ok(match bus {
+        0 => Bus::Spi0,
+        1 => Bus::Spi1,
+        2 => Bus::Spi2,
+        3 => Bus::Spi3,
+        4 => Bus::Spi4,
+        5 => Bus::Spi5,
+        6 => Bus::Spi6,
+        _ => return Err(Error::InvalidConfig(format!("no SPI{bus}"))),
+    })
+}
+
+fn slave_select(device: u8) -> Result<SlaveSelect> {
+    Ok(match device {
+        0 => SlaveSelect::Ss0,
+        1 => SlaveSelect::Ss1,
+        2 => SlaveSelect::Ss2,
+        3 => SlaveSelect::Ss3,
+        4 => SlaveSelect::Ss4,
+        5 => SlaveSelect::Ss5,
+        6 => SlaveSelect::Ss6,
+        7 => SlaveSelect::Ss7,
+        8 => SlaveSelect::Ss8,
+        9 => SlaveSelect::Ss9,
+        10 => SlaveSelect::Ss10,
+        11 => SlaveSelect::Ss11,
+        12 => SlaveSelect::Ss12,
+        13 => SlaveSelect::Ss13,
+        14 => SlaveSelect::Ss14,
+        15 => SlaveSelect::Ss15,
+        _ => return Err(Error::InvalidConfig(format!("no chip select {device}"))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::time::Instant;
+
+    #[test]
+    fn timeout() {
+        let start = Instant::now();
+        block_on(Timeout::new(Duration::from_millis(50)));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn signal_before_timeout() {
+        let signal = Arc::new(Signal::default());
+        let setter = signal.clone();
+        thread::spawn(move || setter.set());
+        let start = Instant::now();
+        let mut timeout = Timeout::new(Duration::from_secs(10));
+        let won = block_on(future::select(signal.wait(), &mut timeout));
+        assert!(matches!(won, Either::Left(_)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
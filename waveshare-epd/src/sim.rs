@@ -0,0 +1,493 @@
+//! Virtual panel, rendering to PNG.
+//!
+//! The simulator decodes the command stream the way the controllers do, keeps
+//! their memory, and renders it at each refresh. Time is virtual by default:
+//! delays and refreshes complete immediately, but are accounted for in
+//! [`Simulator::elapsed`].
+
+use crate::{
+    interface::{CascadeInterface, Controller, Interface},
+    Result,
+};
+use image::{Rgb, RgbImage};
+use log::{debug, warn};
+use std::{collections::HashMap, path::PathBuf, thread, time::Duration};
+
+const BLACK: [f32; 3] = [0.0, 0.0, 0.0];
+const WHITE: [f32; 3] = [255.0, 255.0, 255.0];
+const RED: [f32; 3] = [255.0, 0.0, 0.0];
+
+/// Time the controllers stay busy while powering on or off.
+const POWER_TIME: Duration = Duration::from_millis(80);
+/// Time the controllers stay busy while measuring their temperature.
+const SENSOR_TIME: Duration = Duration::from_millis(40);
+/// Answer to the Revision command.
+const REVISION: [u8; 2] = [0x0A, 0x01];
+
+/// Length of the window parameters of the partial commands.
+const WINDOW_LEN: usize = 8;
+
+/// Decode the `(x, y, width, height)` parameters of the partial commands.
+fn window(params: &[u8]) -> (usize, usize, usize, usize) {
+    let word = |i: usize| usize::from(params[i]) << 8 | usize::from(params[i + 1]);
+    (word(0) & !7, word(2), word(4) & !7, word(6))
+}
+
+/// Simulated controller.
+#[derive(Debug)]
+struct Chip {
+    /// Position of the top left pixel of the chip on the panel.
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    /// Black/white (DTM1) and red (DTM2) memory.
+    ram: [Vec<u8>; 2],
+    /// Last command received, and how many data bytes followed it.
+    command: Option<u8>,
+    offset: usize,
+    /// Last parameters of every command.
+    registers: HashMap<u8, Vec<u8>>,
+    busy_until: Duration,
+    /// Reading of the temperature sensor, in °C.
+    temperature: i8,
+    /// Answer to the last command.
+    response: Vec<u8>,
+    /// Whether high voltage is applied to the panel.
+    powered: bool,
+    asleep: bool,
+    /// Whether the busy line is stuck busy.
+    disconnected: bool,
+}
+impl Chip {
+    fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        let size = width * height / 8;
+        Self {
+            x,
+            y,
+            width,
+            height,
+            ram: [vec![0xFF; size], vec![0x00; size]],
+            command: None,
+            offset: 0,
+            registers: HashMap::new(),
+            busy_until: Duration::ZERO,
+            temperature: 20,
+            response: Vec::new(),
+            powered: false,
+            asleep: false,
+            disconnected: false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.command = None;
+        self.offset = 0;
+        self.registers.clear();
+        self.powered = false;
+        self.asleep = false;
+    }
+
+    fn color(&self, x: usize, y: usize) -> [f32; 3] {
+        let index = (x + y * self.width) / 8;
+        let mask = 0x80 >> (x % 8);
+        if self.ram[1][index] & mask != 0 {
+            RED
+        } else if self.ram[0][index] & mask != 0 {
+            WHITE
+        } else {
+            BLACK
+        }
+    }
+}
+
+/// A simulated panel.
+///
+/// Implements both [`Interface`] and [`CascadeInterface`], so it can back any
+/// driver. The former talks to the first controller.
+#[derive(Debug)]
+pub struct Simulator {
+    chips: Vec<(Controller, Chip)>,
+    /// Rendered panel, including ghosting.
+    shown: Vec<[f32; 3]>,
+    width: usize,
+    height: usize,
+    now: Duration,
+    refreshes: usize,
+    refresh_time: Duration,
+    ghosting: f32,
+    realtime: bool,
+    idle_high: bool,
+    output: Option<PathBuf>,
+}
+impl Simulator {
+    /// A panel driven by a single controller.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::cascade(width, height, [(Controller::M1, 0, 0, width, height)])
+    }
+
+    /// A panel driven by several controllers, each with its own position and
+    /// resolution.
+    pub fn cascade(
+        width: usize,
+        height: usize,
+        chips: impl IntoIterator<Item = (Controller, usize, usize, usize, usize)>,
+    ) -> Self {
+        Self {
+            chips: chips
+                .into_iter()
+                .map(|(controller, x, y, w, h)| (controller, Chip::new(x, y, w, h)))
+                .collect(),
+            shown: vec![WHITE; width * height],
+            width,
+            height,
+            now: Duration::ZERO,
+            refreshes: 0,
+            refresh_time: Duration::from_secs(15),
+            ghosting: 0.0,
+            realtime: false,
+            idle_high: true,
+            output: None,
+        }
+    }
+
+    #[cfg(feature = "epd_2in7b")]
+    pub fn epd_2in7b() -> Self {
+        use crate::epd_2in7b::{EPD_HEIGHT, EPD_WIDTH};
+        Self::new(EPD_WIDTH, EPD_HEIGHT)
+    }
+
+    #[cfg(feature = "epd_12in48b")]
+    pub fn epd_12in48b() -> Self {
+        use crate::epd_12in48b::{EPD_HEIGHT, EPD_WIDTH, HALF_HEIGHT, LEFT_WIDTH, RIGHT_WIDTH};
+        Self::cascade(
+            EPD_WIDTH,
+            EPD_HEIGHT,
+            [
+                (Controller::M1, 0, HALF_HEIGHT, LEFT_WIDTH, HALF_HEIGHT),
+                (
+                    Controller::S1,
+                    LEFT_WIDTH,
+                    HALF_HEIGHT,
+                    RIGHT_WIDTH,
+                    HALF_HEIGHT,
+                ),
+                (Controller::M2, LEFT_WIDTH, 0, RIGHT_WIDTH, HALF_HEIGHT),
+                (Controller::S2, 0, 0, LEFT_WIDTH, HALF_HEIGHT),
+            ],
+        )
+        .with_refresh_time(Duration::from_secs(24))
+    }
+
+    /// Write a PNG to `path` at each refresh.
+    pub fn with_output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.output = Some(path.into());
+        self
+    }
+
+    /// Time the controllers stay busy while refreshing.
+    pub fn with_refresh_time(mut self, refresh_time: Duration) -> Self {
+        self.refresh_time = refresh_time;
+        self
+    }
+
+    /// Fraction of the previous image that remains visible after a refresh.
+    pub fn with_ghosting(mut self, ghosting: f32) -> Self {
+        self.ghosting = ghosting.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Actually wait for delays and refreshes, instead of only accounting for
+    /// them.
+    pub fn with_realtime(mut self, realtime: bool) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    /// Simulate a badly seated controller, whose busy line stays busy.
+    pub fn with_disconnected(mut self, controller: Controller) -> Self {
+        self.chip_mut(controller).disconnected = true;
+        self
+    }
+
+    /// Level of the busy lines while the controllers are idle, high by
+    /// default.
+    pub fn with_idle_high(mut self, idle_high: bool) -> Self {
+        self.idle_high = idle_high;
+        self
+    }
+
+    /// Temperature measured by `controller`, 20 °C by default.
+    pub fn with_temperature(mut self, controller: Controller, celsius: i8) -> Self {
+        self.chip_mut(controller).temperature = celsius;
+        self
+    }
+
+    /// Virtual time elapsed since the simulator was created.
+    pub fn elapsed(&self) -> Duration {
+        self.now
+    }
+
+    /// Number of refreshes so far.
+    pub fn refreshes(&self) -> usize {
+        self.refreshes
+    }
+
+    /// Whether any controller applies high voltage to the panel.
+    pub fn powered(&self) -> bool {
+        self.chips.iter().any(|(_, chip)| chip.powered)
+    }
+
+    /// Whether all controllers are in deep sleep.
+    pub fn asleep(&self) -> bool {
+        self.chips.iter().all(|(_, chip)| chip.asleep)
+    }
+
+    /// What the panel currently shows.
+    pub fn frame(&self) -> RgbImage {
+        RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let [r, g, b] = self.shown[x as usize + y as usize * self.width];
+            Rgb([r.round() as u8, g.round() as u8, b.round() as u8])
+        })
+    }
+
+    /// Last parameters sent to `controller` for `command`, if any.
+    pub fn register(&self, controller: Controller, command: u8) -> Option<&[u8]> {
+        self.chip(controller)
+            .registers
+            .get(&command)
+            .map(Vec::as_slice)
+    }
+
+    fn chip(&self, controller: Controller) -> &Chip {
+        self.chips
+            .iter()
+            .find_map(|(c, chip)| (*c == controller).then_some(chip))
+            .unwrap_or_else(|| panic!("{controller:?} is not simulated"))
+    }
+
+    fn chip_mut(&mut self, controller: Controller) -> &mut Chip {
+        self.chips
+            .iter_mut()
+            .find_map(|(c, chip)| (*c == controller).then_some(chip))
+            .unwrap_or_else(|| panic!("{controller:?} is not simulated"))
+    }
+
+    fn command(&mut self, to: &[Controller], command: u8) -> Result<()> {
+        let now = self.now;
+        let mut refresh = false;
+        for (_, chip) in self.chips.iter_mut().filter(|(c, _)| to.contains(c)) {
+            if chip.asleep {
+                warn!("Command {command:#04x} while in deep sleep");
+                continue;
+            }
+            chip.command = Some(command);
+            chip.offset = 0;
+            chip.response.clear();
+            match command {
+                // Power ON/OFF
+                0x04 | 0x02 => {
+                    chip.busy_until = now + POWER_TIME;
+                    chip.powered = command == 0x04;
+                }
+                // Temperature Sensor Calibration
+                0x40 => {
+                    chip.busy_until = now + SENSOR_TIME;
+                    chip.response = vec![chip.temperature as u8];
+                }
+                // Revision
+                0x70 => chip.response = REVISION.to_vec(),
+                // Get Status, only telling whether the chip is busy
+                0x71 => chip.response = vec![u8::from(now >= chip.busy_until)],
+                // Display Refresh
+                0x12 => {
+                    chip.busy_until = now + self.refresh_time;
+                    refresh = true;
+                }
+                _ => {}
+            }
+        }
+        if refresh {
+            self.refresh(None)?;
+        }
+        Ok(())
+    }
+
+    fn data(&mut self, to: &[Controller], data: &[u8]) -> Result<()> {
+        let now = self.now;
+        let mut partial = Vec::new();
+        for (controller, chip) in self.chips.iter_mut().filter(|(c, _)| to.contains(c)) {
+            if chip.asleep {
+                continue;
+            }
+            let Some(command) = chip.command else {
+                warn!("Data without command");
+                continue;
+            };
+            match command {
+                // Data Start Transmission 1/2
+                0x10 | 0x13 => {
+                    let ram = &mut chip.ram[usize::from(command == 0x13)];
+                    let start = chip.offset.min(ram.len());
+                    let end = (chip.offset + data.len()).min(ram.len());
+                    ram[start..end].copy_from_slice(&data[..end - start]);
+                }
+                // Partial Data Start Transmission 1/2
+                0x14 | 0x15 => {
+                    if chip.offset == 0 {
+                        chip.registers.remove(&command);
+                    }
+                    for (offset, &byte) in (chip.offset..).zip(data) {
+                        if offset < WINDOW_LEN {
+                            chip.registers.entry(command).or_default().push(byte);
+                            continue;
+                        }
+                        let (x, y, width, height) = window(&chip.registers[&command]);
+                        let (row, column) = (
+                            (offset - WINDOW_LEN) / (width / 8),
+                            (offset - WINDOW_LEN) % (width / 8),
+                        );
+                        if row >= height || x + column * 8 >= chip.width || y + row >= chip.height {
+                            continue;
+                        }
+                        let index = (x + (y + row) * chip.width) / 8 + column;
+                        chip.ram[usize::from(command == 0x15)][index] = byte;
+                    }
+                }
+                // Partial Display Refresh
+                0x16 => {
+                    let registers = chip.registers.entry(command).or_default();
+                    registers.truncate(chip.offset);
+                    registers.extend_from_slice(data);
+                    if chip.offset < WINDOW_LEN && registers.len() >= WINDOW_LEN {
+                        chip.busy_until = now + self.refresh_time;
+                        partial.push((*controller, window(registers)));
+                    }
+                }
+                // Deep Sleep
+                0x07 if data == [0xA5] => chip.asleep = true,
+                _ => {
+                    let registers = chip.registers.entry(command).or_default();
+                    registers.truncate(chip.offset);
+                    registers.extend_from_slice(data);
+                }
+            }
+            chip.offset += data.len();
+        }
+        for (controller, window) in partial {
+            self.refresh(Some((controller, window)))?;
+        }
+        Ok(())
+    }
+
+    /// Render the memory of all chips, or only a window of one of them.
+    fn refresh(&mut self, only: Option<(Controller, (usize, usize, usize, usize))>) -> Result<()> {
+        self.refreshes += 1;
+        debug!("Refresh {}", self.refreshes);
+        for (controller, chip) in &self.chips {
+            let (x0, y0, width, height) = match only {
+                Some((c, _)) if c != *controller => continue,
+                Some((_, window)) => window,
+                None => (0, 0, chip.width, chip.height),
+            };
+            for y in y0..(y0 + height).min(chip.height) {
+                for x in x0..(x0 + width).min(chip.width) {
+                    let new = chip.color(x, y);
+                    let old = &mut self.shown[chip.x + x + (chip.y + y) * self.width];
+                    for (o, n) in old.iter_mut().zip(new) {
+                        *o = *o * self.ghosting + n * (1.0 - self.ghosting);
+                    }
+                }
+            }
+        }
+        if let Some(path) = &self.output {
+            self.frame().save(path)?;
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, from: Controller, buf: &mut [u8]) {
+        let chip = self.chip_mut(from);
+        if chip.disconnected {
+            buf.fill(0xFF);
+            return;
+        }
+        for (dst, src) in buf
+            .iter_mut()
+            .zip(chip.response.iter().chain(std::iter::repeat(&0)))
+        {
+            *dst = *src;
+        }
+    }
+
+    fn busy(&self, controller: Controller) -> bool {
+        let chip = self.chip(controller);
+        chip.disconnected || self.now < chip.busy_until
+    }
+
+    fn wait(&mut self, duration: Duration) {
+        if self.realtime {
+            thread::sleep(duration);
+        }
+        self.now += duration;
+    }
+
+    fn first(&self) -> Controller {
+        self.chips[0].0
+    }
+}
+
+impl Interface for Simulator {
+    fn set_reset(&mut self, high: bool) -> Result<()> {
+        CascadeInterface::set_reset(self, high)
+    }
+    fn send_command(&mut self, command: u8) -> Result<()> {
+        self.command(&[self.first()], command)
+    }
+    fn send_data(&mut self, data: &[u8]) -> Result<()> {
+        self.data(&[self.first()], data)
+    }
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.read(self.first(), buf);
+        Ok(())
+    }
+    fn busy_high(&mut self) -> Result<bool> {
+        Ok(self.busy(self.first()) != self.idle_high)
+    }
+    fn delay(&mut self, duration: Duration) {
+        self.wait(duration)
+    }
+    fn now(&self) -> Duration {
+        self.now
+    }
+}
+
+impl CascadeInterface for Simulator {
+    fn set_reset(&mut self, high: bool) -> Result<()> {
+        if !high {
+            for (_, chip) in &mut self.chips {
+                chip.reset();
+            }
+        }
+        Ok(())
+    }
+    fn send_command(&mut self, to: &[Controller], command: u8) -> Result<()> {
+        self.command(to, command)
+    }
+    fn send_data(&mut self, to: &[Controller], data: &[u8]) -> Result<()> {
+        self.data(to, data)
+    }
+    fn read_data(&mut self, from: Controller, buf: &mut [u8]) -> Result<()> {
+        self.read(from, buf);
+        Ok(())
+    }
+    fn busy_high(&mut self, controller: Controller) -> Result<bool> {
+        Ok(self.busy(controller) != self.idle_high)
+    }
+    fn delay(&mut self, duration: Duration) {
+        self.wait(duration)
+    }
+    fn now(&self) -> Duration {
+        self.now
+    }
+}
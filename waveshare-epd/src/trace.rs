@@ -0,0 +1,723 @@
+//! Recording of the traffic on the bus, to replay or compare it later.
+//!
+//! A [`Recorder`] wraps a backend and logs what the driver does with it:
+//! commands, data lengths and hashes, reset and busy pin transitions, and how
+//! long each busy wait took. Traces are text, one [`Entry`] per line:
+//!
+//! ```text
+//! # waveshare-epd trace
+//! reset high
+//! delay 200000us
+//! command 04
+//! wait idle-high 15230us
+//! command M1+S1 10
+//! data M1+S1 5808 9f3c5a1e07b2d4c8
+//! ```
+//!
+//! Data payloads are only kept if asked for, since they are needed to
+//! [`replay`](Trace::replay) a trace but not to [`diff`] it.
+
+use crate::{
+    interface::{CascadeInterface, Controller, Interface},
+    Error, Result,
+};
+use futures::executor::block_on;
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File},
+    io::{LineWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+const HEADER: &str = "# waveshare-epd trace";
+
+/// Something a driver did on the bus.
+///
+/// Controllers are only listed for panels with more than one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Entry {
+    Reset(bool),
+    Command {
+        to: Vec<Controller>,
+        command: u8,
+    },
+    Data {
+        to: Vec<Controller>,
+        len: usize,
+        hash: u64,
+        /// The bytes themselves, if recorded.
+        payload: Option<Vec<u8>>,
+    },
+    Read {
+        from: Option<Controller>,
+        len: usize,
+        hash: u64,
+    },
+    /// The busy line was seen at a new level, outside of waits.
+    Busy {
+        controller: Option<Controller>,
+        high: bool,
+    },
+    /// A wait for the busy lines to be idle.
+    Wait {
+        to: Vec<Controller>,
+        idle_high: bool,
+        waited: Duration,
+        timed_out: bool,
+    },
+    Delay(Duration),
+}
+impl Entry {
+    /// Whether both did the same, however long they took.
+    pub fn same(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Entry::Data { to, len, hash, .. },
+                Entry::Data {
+                    to: other_to,
+                    len: other_len,
+                    hash: other_hash,
+                    ..
+                },
+            ) => (to, len, hash) == (other_to, other_len, other_hash),
+            // What a controller answers, its temperature say, varies.
+            (
+                Entry::Read { from, len, .. },
+                Entry::Read {
+                    from: other_from,
+                    len: other_len,
+                    ..
+                },
+            ) => (from, len) == (other_from, other_len),
+            (
+                Entry::Wait {
+                    to,
+                    idle_high,
+                    timed_out,
+                    ..
+                },
+                Entry::Wait {
+                    to: other_to,
+                    idle_high: other_idle_high,
+                    timed_out: other_timed_out,
+                    ..
+                },
+            ) => (to, idle_high, timed_out) == (other_to, other_idle_high, other_timed_out),
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Reset(high) => write!(f, "reset {}", level(*high)),
+            Entry::Command { to, command } => {
+                write!(f, "command {}{command:02x}", controllers(to))
+            }
+            Entry::Data {
+                to,
+                len,
+                hash,
+                payload,
+            } => {
+                write!(f, "data {}{len} {hash:016x}", controllers(to))?;
+                if let Some(payload) = payload {
+                    write!(f, " ")?;
+                    for byte in payload {
+                        write!(f, "{byte:02x}")?;
+                    }
+                }
+                Ok(())
+            }
+            Entry::Read { from, len, hash } => {
+                let from = from.as_slice();
+                write!(f, "read {}{len} {hash:016x}", controllers(from))
+            }
+            Entry::Busy { controller, high } => {
+                let controller = controller.as_slice();
+                write!(f, "busy {}{}", controllers(controller), level(*high))
+            }
+            Entry::Wait {
+                to,
+                idle_high,
+                waited,
+                timed_out,
+            } => {
+                write!(
+                    f,
+                    "wait {}idle-{} {}us",
+                    controllers(to),
+                    level(*idle_high),
+                    waited.as_micros()
+                )?;
+                if *timed_out {
+                    write!(f, " timeout")?;
+                }
+                Ok(())
+            }
+            Entry::Delay(duration) => write!(f, "delay {}us", duration.as_micros()),
+        }
+    }
+}
+
+fn level(high: bool) -> &'static str {
+    if high {
+        "high"
+    } else {
+        "low"
+    }
+}
+
+/// Controllers followed by a space, or nothing if there are none.
+fn controllers(controllers: &[Controller]) -> String {
+    if controllers.is_empty() {
+        return String::new();
+    }
+    let names: Vec<_> = controllers.iter().map(|c| format!("{c:?}")).collect();
+    names.join("+") + " "
+}
+
+/// A recorded sequence of [`Entry`]s.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub entries: Vec<Entry>,
+}
+impl Trace {
+    /// Parse a trace in the text format.
+    pub fn parse(text: &str) -> Result<Self> {
+        let entries = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|(i, line)| {
+                parse_entry(line)
+                    .ok_or_else(|| Error::InvalidTrace(format!("line {}: {line:?}", i + 1)))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { entries })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Whether the trace talks to several controllers.
+    pub fn is_cascade(&self) -> bool {
+        self.entries.iter().any(|entry| match entry {
+            Entry::Command { to, .. } | Entry::Data { to, .. } | Entry::Wait { to, .. } => {
+                !to.is_empty()
+            }
+            Entry::Read { from, .. } => from.is_some(),
+            _ => false,
+        })
+    }
+
+    /// Play the trace into `interface`, waiting for each busy wait at most
+    /// `timeout`.
+    pub fn replay(&self, interface: &mut impl Interface, timeout: Duration) -> Result<()> {
+        let mut last = None;
+        for entry in &self.entries {
+            match entry {
+                Entry::Reset(high) => interface.set_reset(*high)?,
+                Entry::Command { command, .. } => {
+                    last = Some(*command);
+                    interface.send_command(*command)?;
+                }
+                Entry::Data { .. } => interface.send_data(payload(entry)?)?,
+                Entry::Read { len, .. } => {
+                    ignore_unsupported(interface.read_data(&mut vec![0; *len]))?
+                }
+                Entry::Busy { .. } => {}
+                Entry::Wait { idle_high, .. } => {
+                    if block_on(interface.wait_idle(*idle_high, timeout))? {
+                        return Err(Error::Timeout {
+                            controller: None,
+                            command: last,
+                        });
+                    }
+                }
+                Entry::Delay(duration) => interface.delay(*duration),
+            }
+        }
+        Ok(())
+    }
+
+    /// Play the trace into the controllers of `interface`, see
+    /// [`Trace::replay`].
+    pub fn replay_cascade(
+        &self,
+        interface: &mut impl CascadeInterface,
+        timeout: Duration,
+    ) -> Result<()> {
+        let mut last = None;
+        for entry in &self.entries {
+            match entry {
+                Entry::Reset(high) => interface.set_reset(*high)?,
+                Entry::Command { to, command } => {
+                    last = Some(*command);
+                    interface.send_command(to, *command)?;
+                }
+                Entry::Data { to, .. } => interface.send_data(to, payload(entry)?)?,
+                Entry::Read { from, len, .. } => {
+                    let from = from.unwrap_or(Controller::M1);
+                    ignore_unsupported(interface.read_data(from, &mut vec![0; *len]))?
+                }
+                Entry::Busy { .. } => {}
+                Entry::Wait { to, idle_high, .. } => {
+                    if let Some(controller) =
+                        block_on(interface.wait_idle(to, *idle_high, timeout))?
+                    {
+                        return Err(Error::Timeout {
+                            controller: Some(controller),
+                            command: last,
+                        });
+                    }
+                }
+                Entry::Delay(duration) => interface.delay(*duration),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+        for entry in &self.entries {
+            writeln!(f, "{entry}")?;
+        }
+        Ok(())
+    }
+}
+
+fn payload(entry: &Entry) -> Result<&[u8]> {
+    match entry {
+        Entry::Data {
+            payload: Some(payload),
+            ..
+        } => Ok(payload),
+        _ => Err(Error::InvalidTrace(format!(
+            "no payload to replay in {entry}"
+        ))),
+    }
+}
+
+/// Reads are only replayed for their side effects, which backends that can't
+/// read don't have.
+fn ignore_unsupported(result: Result<()>) -> Result<()> {
+    match result {
+        Err(Error::Unsupported(_)) => Ok(()),
+        result => result,
+    }
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    let mut words = line.split_whitespace().peekable();
+    let kind = words.next()?;
+    let mut to = Vec::new();
+    if let Some(parsed) = words.peek().and_then(|word| parse_controllers(word)) {
+        to = parsed;
+        words.next();
+    }
+    let words: Vec<_> = words.collect();
+    let entry = match (kind, &words[..]) {
+        ("reset", [high]) => Entry::Reset(parse_level(high)?),
+        ("command", [command]) => Entry::Command {
+            to,
+            command: u8::from_str_radix(command, 16).ok()?,
+        },
+        ("data", [len, hash, payload @ ..]) => {
+            let payload = match payload {
+                [] => None,
+                [hex] => Some(parse_hex(hex)?),
+                _ => return None,
+            };
+            Entry::Data {
+                to,
+                len: len.parse().ok()?,
+                hash: u64::from_str_radix(hash, 16).ok()?,
+                payload,
+            }
+        }
+        ("read", [len, hash]) => Entry::Read {
+            from: single(to)?,
+            len: len.parse().ok()?,
+            hash: u64::from_str_radix(hash, 16).ok()?,
+        },
+        ("busy", [high]) => Entry::Busy {
+            controller: single(to)?,
+            high: parse_level(high)?,
+        },
+        ("wait", [idle, waited, timeout @ ..]) => Entry::Wait {
+            to,
+            idle_high: parse_level(idle.strip_prefix("idle-")?)?,
+            waited: parse_micros(waited)?,
+            timed_out: match timeout {
+                [] => false,
+                ["timeout"] => true,
+                _ => return None,
+            },
+        },
+        ("delay", [duration]) => Entry::Delay(parse_micros(duration)?),
+        _ => return None,
+    };
+    Some(entry)
+}
+
+fn parse_controllers(word: &str) -> Option<Vec<Controller>> {
+    word.split('+')
+        .map(|name| {
+            Controller::ALL
+                .into_iter()
+                .find(|c| format!("{c:?}") == name)
+        })
+        .collect()
+}
+
+/// At most one controller.
+fn single(controllers: Vec<Controller>) -> Option<Option<Controller>> {
+    match controllers[..] {
+        [] => Some(None),
+        [controller] => Some(Some(controller)),
+        _ => None,
+    }
+}
+
+fn parse_level(word: &str) -> Option<bool> {
+    match word {
+        "high" => Some(true),
+        "low" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_micros(word: &str) -> Option<Duration> {
+    Some(Duration::from_micros(
+        word.strip_suffix("us")?.parse().ok()?,
+    ))
+}
+
+fn parse_hex(word: &str) -> Option<Vec<u8>> {
+    if !word.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..word.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(word.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// FNV-1a, which is stable across platforms and builds.
+pub fn hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// An entry that differs between two traces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Difference<'a> {
+    /// Index of the left entry, busy levels aside, or of where the right one
+    /// would go.
+    pub index: usize,
+    /// The entry of each trace, if it has one there.
+    pub left: Option<&'a Entry>,
+    pub right: Option<&'a Entry>,
+}
+
+/// Entries that differ between `left` and `right`.
+///
+/// Traces are aligned on the entries they have in common, so an entry only
+/// one of them has is reported alone. Timings, busy levels and the bytes read
+/// are ignored, since they change from run to run.
+pub fn diff<'a>(left: &'a Trace, right: &'a Trace) -> Vec<Difference<'a>> {
+    let significant = |trace: &'a Trace| -> Vec<&'a Entry> {
+        trace
+            .entries
+            .iter()
+            .filter(|entry| !matches!(entry, Entry::Busy { .. }))
+            .collect()
+    };
+    let (left, right) = (significant(left), significant(right));
+    let mut differences = Vec::new();
+    let (mut l, mut r) = (0, 0);
+    let end = (left.len(), right.len());
+    for (next_l, next_r) in common(&left, &right).into_iter().chain([end]) {
+        // Entries between two in common differ, paired up as far as they go.
+        let (removed, added) = (&left[l..next_l], &right[r..next_r]);
+        for i in 0..removed.len().max(added.len()) {
+            differences.push(Difference {
+                index: l + i.min(removed.len()),
+                left: removed.get(i).copied(),
+                right: added.get(i).copied(),
+            });
+        }
+        (l, r) = (next_l + 1, next_r + 1);
+    }
+    differences
+}
+
+/// Most entries [`diff`] aligns traces around, which keeps it to a few
+/// megabytes.
+const MAX_EDITS: usize = 1024;
+
+/// Indices of the entries `left` and `right` have in common, in order, by
+/// Myers' algorithm. Traces further apart than [`MAX_EDITS`] are compared
+/// position by position.
+fn common(left: &[&Entry], right: &[&Entry]) -> Vec<(usize, usize)> {
+    let (n, m) = (left.len() as isize, right.len() as isize);
+    let max = (left.len() + right.len()).min(MAX_EDITS) as isize;
+    let at = |k: isize| (k + max + 1) as usize;
+    // Furthest x reached on each diagonal k = x - y, and its value after
+    // each number of edits, to walk back from the end.
+    let mut furthest = vec![0isize; at(max + 1) + 1];
+    let mut history: Vec<Vec<isize>> = Vec::new();
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && furthest[at(k - 1)] < furthest[at(k + 1)]) {
+                furthest[at(k + 1)]
+            } else {
+                furthest[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && left[x as usize].same(right[y as usize]) {
+                (x, y) = (x + 1, y + 1);
+            }
+            furthest[at(k)] = x;
+            if x >= n && y >= m {
+                return backtrack(&history, n, m);
+            }
+        }
+        history.push(furthest[at(-d)..=at(d)].to_vec());
+    }
+    (0..left.len().min(right.len()))
+        .filter(|&i| left[i].same(right[i]))
+        .map(|i| (i, i))
+        .collect()
+}
+
+/// The diagonals [`common`] took to the end, from what it reached after each
+/// number of edits.
+fn backtrack(history: &[Vec<isize>], n: isize, m: isize) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, furthest) in history.iter().enumerate().rev() {
+        let edits = d as isize + 1;
+        let reached = |k: isize| furthest[(k + d as isize) as usize];
+        let k = x - y;
+        let previous = if k == -edits || (k != edits && reached(k - 1) < reached(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let (previous_x, previous_y) = (reached(previous), reached(previous) - previous);
+        while x > previous_x && y > previous_y {
+            (x, y) = (x - 1, y - 1);
+            pairs.push((x as usize, y as usize));
+        }
+        (x, y) = (previous_x, previous_y);
+    }
+    while x > 0 && y > 0 {
+        (x, y) = (x - 1, y - 1);
+        pairs.push((x as usize, y as usize));
+    }
+    pairs.reverse();
+    pairs
+}
+
+/// Time since `start`, to the microsecond traces keep.
+fn elapsed(start: Instant) -> Duration {
+    let micros = start.elapsed().as_micros();
+    Duration::from_micros(micros.try_into().unwrap_or(u64::MAX))
+}
+
+/// A backend that records what goes through it.
+pub struct Recorder<I> {
+    inner: I,
+    trace: Trace,
+    payloads: bool,
+    /// Last level seen of each busy line.
+    busy: HashMap<Option<Controller>, bool>,
+    output: Option<LineWriter<File>>,
+}
+impl<I> Recorder<I> {
+    pub fn new(inner: I) -> Self {
+        Self {
+            inner,
+            trace: Trace::default(),
+            payloads: false,
+            busy: HashMap::new(),
+            output: None,
+        }
+    }
+
+    /// Keep the data sent, so that the trace can be replayed.
+    pub fn with_payloads(mut self, payloads: bool) -> Self {
+        self.payloads = payloads;
+        self
+    }
+
+    /// Also write the trace to `path` as it is recorded, so that it survives
+    /// a crash.
+    pub fn with_output(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        let mut output = LineWriter::new(File::create(path)?);
+        writeln!(output, "{HEADER}")?;
+        self.output = Some(output);
+        Ok(self)
+    }
+
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    pub fn into_trace(self) -> Trace {
+        self.trace
+    }
+
+    fn record(&mut self, entry: Entry) {
+        if let Some(output) = &mut self.output {
+            // A trace is only a debugging aid, so failing to write it
+            // mustn't fail the driver.
+            if let Err(error) = writeln!(output, "{entry}") {
+                log::warn!("Failed to write the trace: {error}");
+                self.output = None;
+            }
+        }
+        self.trace.entries.push(entry);
+    }
+
+    fn record_data(&mut self, to: &[Controller], data: &[u8]) {
+        self.record(Entry::Data {
+            to: to.to_vec(),
+            len: data.len(),
+            hash: hash(data),
+            payload: self.payloads.then(|| data.to_vec()),
+        });
+    }
+
+    fn record_busy(&mut self, controller: Option<Controller>, high: bool) {
+        if self.busy.insert(controller, high) != Some(high) {
+            self.record(Entry::Busy { controller, high });
+        }
+    }
+}
+
+impl<I: Interface> Interface for Recorder<I> {
+    fn set_reset(&mut self, high: bool) -> Result<()> {
+        self.record(Entry::Reset(high));
+        self.inner.set_reset(high)
+    }
+    fn send_command(&mut self, command: u8) -> Result<()> {
+        self.record(Entry::Command {
+            to: Vec::new(),
+            command,
+        });
+        self.inner.send_command(command)
+    }
+    fn send_data(&mut self, data: &[u8]) -> Result<()> {
+        self.record_data(&[], data);
+        self.inner.send_data(data)
+    }
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.inner.read_data(buf)?;
+        self.record(Entry::Read {
+            from: None,
+            len: buf.len(),
+            hash: hash(buf),
+        });
+        Ok(())
+    }
+    fn busy_high(&mut self) -> Result<bool> {
+        let high = self.inner.busy_high()?;
+        self.record_busy(None, high);
+        Ok(high)
+    }
+    fn delay(&mut self, duration: Duration) {
+        self.record(Entry::Delay(duration));
+        self.inner.delay(duration)
+    }
+    fn now(&self) -> Duration {
+        self.inner.now()
+    }
+    async fn wait_idle(&mut self, idle_high: bool, timeout: Duration) -> Result<bool> {
+        let start = Instant::now();
+        let timed_out = self.inner.wait_idle(idle_high, timeout).await?;
+        self.record(Entry::Wait {
+            to: Vec::new(),
+            idle_high,
+            waited: elapsed(start),
+            timed_out,
+        });
+        Ok(timed_out)
+    }
+}
+
+impl<I: CascadeInterface> CascadeInterface for Recorder<I> {
+    fn set_reset(&mut self, high: bool) -> Result<()> {
+        self.record(Entry::Reset(high));
+        self.inner.set_reset(high)
+    }
+    fn send_command(&mut self, to: &[Controller], command: u8) -> Result<()> {
+        self.record(Entry::Command {
+            to: to.to_vec(),
+            command,
+        });
+        self.inner.send_command(to, command)
+    }
+    fn send_data(&mut self, to: &[Controller], data: &[u8]) -> Result<()> {
+        self.record_data(to, data);
+        self.inner.send_data(to, data)
+    }
+    fn read_data(&mut self, from: Controller, buf: &mut [u8]) -> Result<()> {
+        self.inner.read_data(from, buf)?;
+        self.record(Entry::Read {
+            from: Some(from),
+            len: buf.len(),
+            hash: hash(buf),
+        });
+        Ok(())
+    }
+    fn busy_high(&mut self, controller: Controller) -> Result<bool> {
+        let high = self.inner.busy_high(controller)?;
+        self.record_busy(Some(controller), high);
+        Ok(high)
+    }
+    fn delay(&mut self, duration: Duration) {
+        self.record(Entry::Delay(duration));
+        self.inner.delay(duration)
+    }
+    fn now(&self) -> Duration {
+        self.inner.now()
+    }
+    async fn wait_idle(
+        &mut self,
+        controllers: &[Controller],
+        idle_high: bool,
+        timeout: Duration,
+    ) -> Result<Option<Controller>> {
+        let start = Instant::now();
+        let busy = self
+            .inner
+            .wait_idle(controllers, idle_high, timeout)
+            .await?;
+        self.record(Entry::Wait {
+            to: controllers.to_vec(),
+            idle_high,
+            waited: elapsed(start),
+            timed_out: busy.is_some(),
+        });
+        Ok(busy)
+    }
+}
@@ -0,0 +1,281 @@
+use image::Rgb;
+use std::{cell::RefCell, rc::Rc};
+use waveshare_epd::{
+    canvas::{Canvas, Schedule},
+    epd_2in7b::{self, EPD_HEIGHT, EPD_WIDTH},
+    epd_7in5_v2,
+    framebuffer::{Framebuffer, Rotation, TriColor},
+    interface::Controller,
+    mock::Mock,
+    sim::Simulator,
+    Display, Error, Health, Ink, RefreshReport, Result, Timings,
+};
+
+const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+const RED: Rgb<u8> = Rgb([255, 0, 0]);
+
+fn panel() -> epd_2in7b::Epd<Simulator> {
+    epd_2in7b::Epd::with_interface(Simulator::epd_2in7b())
+}
+
+#[test]
+fn side_by_side() {
+    let mut canvas = Canvas::new()
+        .with_tile(panel(), 0, 0, Rotation::Rotate0)
+        .unwrap()
+        .with_tile(panel(), EPD_WIDTH, 0, Rotation::Rotate0)
+        .unwrap();
+    assert_eq!(
+        (canvas.width(), canvas.height()),
+        (2 * EPD_WIDTH, EPD_HEIGHT)
+    );
+    assert_eq!(canvas.inks(), [Ink::Black, Ink::White, Ink::Red]);
+
+    let mut frame = canvas.framebuffer();
+    frame.set_pixel(0, 0, TriColor::Black);
+    frame.set_pixel(EPD_WIDTH + 5, 10, TriColor::Red);
+    canvas.init().unwrap();
+    canvas.display_framebuffer(&frame).unwrap();
+
+    let [left, right] = &canvas.tiles() else {
+        unreachable!()
+    };
+    let (left, right) = (left.display.interface(), right.display.interface());
+    assert_eq!(left.refreshes(), 1);
+    assert_eq!(right.refreshes(), 1);
+    assert_eq!(left.frame()[(0, 0)], BLACK);
+    assert_eq!(right.frame()[(5, 10)], RED);
+    assert_eq!(
+        left.frame()
+            .pixels()
+            .filter(|&&pixel| pixel == WHITE)
+            .count(),
+        EPD_WIDTH * EPD_HEIGHT - 1
+    );
+    assert_eq!(
+        right
+            .frame()
+            .pixels()
+            .filter(|&&pixel| pixel == WHITE)
+            .count(),
+        EPD_WIDTH * EPD_HEIGHT - 1
+    );
+}
+
+#[test]
+fn rotation() {
+    // Landscape, below a portrait panel.
+    let mut canvas = Canvas::new()
+        .with_tile(panel(), 0, 0, Rotation::Rotate0)
+        .unwrap()
+        .with_tile(panel(), 0, EPD_HEIGHT, Rotation::Rotate90)
+        .unwrap();
+    assert_eq!(
+        (canvas.width(), canvas.height()),
+        (EPD_HEIGHT, EPD_HEIGHT + EPD_WIDTH)
+    );
+
+    let mut frame = canvas.framebuffer();
+    frame.set_pixel(1, EPD_HEIGHT, TriColor::Black);
+    canvas.init().unwrap();
+    canvas.display_framebuffer(&frame).unwrap();
+
+    let below = canvas.tiles()[1].display.interface().frame();
+    assert_eq!(below[(EPD_WIDTH as u32 - 1, 1)], BLACK);
+
+    // Frames must cover the whole canvas.
+    let frame = Framebuffer::new(EPD_WIDTH, EPD_HEIGHT);
+    assert!(matches!(
+        canvas.display_framebuffer(&frame),
+        Err(Error::InvalidDimensions { .. })
+    ));
+    assert!(matches!(
+        canvas.write_frame(&[0xFF; 8], &[0xFF; 8]),
+        Err(Error::InvalidLength { .. })
+    ));
+}
+
+#[test]
+fn mixed_panels() {
+    let mut small = epd_2in7b::Epd::with_interface(Mock::new());
+    let mut large = epd_7in5_v2::Epd::with_interface(Mock::new());
+    let mut canvas = Canvas::<&mut dyn Display>::new()
+        .with_tile(&mut large, 0, 0, Rotation::Rotate0)
+        .unwrap()
+        .with_tile(&mut small, epd_7in5_v2::EPD_WIDTH, 0, Rotation::Rotate0)
+        .unwrap();
+    assert_eq!(
+        (canvas.width(), canvas.height()),
+        (epd_7in5_v2::EPD_WIDTH + EPD_WIDTH, epd_7in5_v2::EPD_HEIGHT)
+    );
+    // Red is only shown if all panels can.
+    assert_eq!(canvas.inks(), [Ink::Black, Ink::White]);
+    canvas.init().unwrap();
+    // Nor written to the panels that could.
+    let len = canvas.width().div_ceil(8) * canvas.height();
+    canvas
+        .write_frame(&vec![0xFF; len], &vec![0x00; len])
+        .unwrap();
+    canvas.clear().unwrap();
+    canvas.sleep().unwrap();
+    drop(canvas);
+    // The controller uses set bits for red.
+    let red = small.interface().data(Controller::M1, 0x13);
+    assert!(red.iter().all(|&byte| byte == 0x00));
+}
+
+#[test]
+fn overlapping() {
+    let canvas = Canvas::new()
+        .with_tile(panel(), 0, 0, Rotation::Rotate0)
+        .unwrap();
+    // Sharing an edge is fine, a pixel is not.
+    let canvas = canvas
+        .with_tile(panel(), EPD_WIDTH, 0, Rotation::Rotate0)
+        .unwrap();
+    let error = canvas
+        .with_tile(panel(), 0, EPD_HEIGHT - 1, Rotation::Rotate90)
+        .err()
+        .unwrap();
+    assert!(
+        matches!(error, Error::OverlappingTiles { tile: 2, other: 0 }),
+        "{error:?}"
+    );
+}
+
+/// A panel logging what it is asked to do.
+struct Log {
+    name: &'static str,
+    log: Rc<RefCell<Vec<String>>>,
+}
+impl Log {
+    fn push(&self, operation: &str) -> Result<()> {
+        self.log
+            .borrow_mut()
+            .push(format!("{} {operation}", self.name));
+        Ok(())
+    }
+}
+impl Display for Log {
+    fn width(&self) -> usize {
+        8
+    }
+    fn height(&self) -> usize {
+        1
+    }
+    fn inks(&self) -> &'static [Ink] {
+        &[Ink::Black, Ink::White]
+    }
+    fn init(&mut self) -> Result<()> {
+        self.push("init")
+    }
+    fn write_frame(&mut self, black: &[u8], _red: &[u8]) -> Result<()> {
+        self.push(&format!("write {:02x}", black[0]))
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        self.push("start")
+    }
+    fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        self.push("finish")?;
+        Ok(RefreshReport {
+            temperatures: vec![(None, 20)],
+            lut: Some("stock".into()),
+            timings: vec![Timings::default()],
+        })
+    }
+    fn clear(&mut self) -> Result<()> {
+        self.push("clear")
+    }
+    fn sleep(&mut self) -> Result<()> {
+        self.push("sleep")
+    }
+    fn diagnose(&mut self) -> Result<Vec<Health>> {
+        Ok(Vec::new())
+    }
+}
+
+fn logged(schedule: Schedule) -> (Canvas<Log>, Rc<RefCell<Vec<String>>>) {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let canvas = Canvas::new()
+        .with_tile(
+            Log {
+                name: "a",
+                log: log.clone(),
+            },
+            0,
+            0,
+            Rotation::Rotate0,
+        )
+        .unwrap()
+        .with_tile(
+            Log {
+                name: "b",
+                log: log.clone(),
+            },
+            8,
+            0,
+            Rotation::Rotate180,
+        )
+        .unwrap()
+        .with_schedule(schedule);
+    (canvas, log)
+}
+
+#[test]
+fn together() {
+    let (mut canvas, log) = logged(Schedule::Together);
+    canvas.write_frame(&[0x0F, 0xFF], &[0xFF; 2]).unwrap();
+    let report = canvas.refresh().unwrap();
+    // One entry per panel.
+    assert_eq!(report.temperatures, [(None, 20), (None, 20)]);
+    assert_eq!(report.timings.len(), 2);
+    assert_eq!(report.lut.as_deref(), Some("stock"));
+    canvas.clear().unwrap();
+    assert_eq!(
+        *log.borrow(),
+        [
+            "a write 0f",
+            "b write ff",
+            "a start",
+            "b start",
+            "a finish",
+            "b finish",
+            "a write ff",
+            "b write ff",
+            "a start",
+            "b start",
+            "a finish",
+            "b finish",
+        ]
+    );
+}
+
+#[test]
+fn sequence() {
+    let (mut canvas, log) = logged(Schedule::Sequence);
+    canvas.write_frame(&[0xFF, 0xF0], &[0xFF; 2]).unwrap();
+    canvas.refresh().unwrap();
+    // Reports of panels refreshed while starting aren't lost.
+    canvas.start_refresh().unwrap();
+    assert_eq!(canvas.finish_refresh().unwrap().timings.len(), 2);
+    canvas.clear().unwrap();
+    assert_eq!(
+        *log.borrow(),
+        [
+            "a write ff",
+            // Upside down.
+            "b write 0f",
+            "a start",
+            "a finish",
+            "b start",
+            "b finish",
+            "a start",
+            "a finish",
+            "b start",
+            "b finish",
+            "a clear",
+            "b clear",
+        ]
+    );
+}
@@ -0,0 +1,110 @@
+use std::time::{Duration, SystemTime};
+use waveshare_epd::{
+    clean::{Policy, State},
+    epd_12in48b, epd_2in7b, epd_7in5_v2,
+    guard::{FileStore, Record, Store},
+    interface::Controller,
+    mock::Mock,
+    sim::Simulator,
+    Display,
+};
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+#[test]
+fn policy() {
+    let now = SystemTime::UNIX_EPOCH + 1000 * HOUR;
+    let mut state = State::default();
+    assert!(!Policy::new().is_due(&state, now));
+
+    let policy = Policy::new().with_refreshes(3);
+    for _ in 0..3 {
+        assert!(!policy.is_due(&state, now));
+        state.record_refresh();
+    }
+    assert!(policy.is_due(&state, now));
+    state.record_clean(now);
+    assert_eq!(state.refreshes, 0);
+    assert!(!policy.is_due(&state, now));
+
+    let policy = Policy::new().with_interval(24 * HOUR);
+    assert!(policy.is_due(&State::default(), now));
+    assert!(!policy.is_due(&state, now + 23 * HOUR));
+    assert!(policy.is_due(&state, now + 24 * HOUR));
+    // The clock was set back.
+    assert!(policy.is_due(&state, now - HOUR));
+}
+
+#[test]
+fn state() {
+    let path = std::env::temp_dir().join("waveshare-epd-clean-state.toml");
+    let _ = std::fs::remove_file(&path);
+    let mut store = FileStore::new(&path);
+    let mut record: Record = store.load().unwrap();
+    assert_eq!(record.clean, State::default());
+
+    record.clean.record_clean(SystemTime::UNIX_EPOCH + HOUR);
+    record.clean.record_refresh();
+    store.save(&record).unwrap();
+    assert_eq!(store.load().ok(), Some(record));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn epd_2in7b() {
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b());
+    epd.init().unwrap();
+    Display::deep_clean(&mut epd).unwrap();
+    let sim = epd.interface();
+    assert_eq!(sim.refreshes(), 4);
+    assert!(sim
+        .frame()
+        .pixels()
+        .all(|&pixel| pixel == image::Rgb([255, 255, 255])));
+}
+
+#[test]
+fn epd_12in48b() {
+    let mut epd = epd_12in48b::Epd::with_interface(Mock::new());
+    futures::executor::block_on(epd.deep_clean()).unwrap();
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::S1)
+            .iter()
+            .filter(|&&command| command == 0x12)
+            .count(),
+        4
+    );
+    // Black, white, red and white: the controllers set bits for white and red.
+    let quadrant = epd_12in48b::quadrant_bytes(Controller::S1);
+    let white = mock.data(Controller::S1, 0x10);
+    let red = mock.data(Controller::S1, 0x13);
+    for (step, (white_byte, red_byte)) in [(0x00, 0x00), (0xFF, 0x00), (0xFF, 0xFF), (0xFF, 0x00)]
+        .into_iter()
+        .enumerate()
+    {
+        let range = step * quadrant..(step + 1) * quadrant;
+        assert!(
+            white[range.clone()].iter().all(|&b| b == white_byte),
+            "{step}"
+        );
+        assert!(red[range].iter().all(|&b| b == red_byte), "{step}");
+    }
+}
+
+#[test]
+fn black_and_white() {
+    // Panels without red skip its step.
+    let mut epd = epd_7in5_v2::Epd::with_interface(Mock::new());
+    Display::deep_clean(&mut epd).unwrap();
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x10, 0x13, 0x12, 0x10, 0x13, 0x12]
+    );
+    let frame = epd_7in5_v2::EPD_WIDTH / 8 * epd_7in5_v2::EPD_HEIGHT;
+    assert_eq!(
+        mock.data(Controller::M1, 0x10),
+        [vec![0x00; frame], vec![0xFF; frame]].concat()
+    );
+}
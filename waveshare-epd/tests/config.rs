@@ -0,0 +1,63 @@
+use waveshare_epd::{
+    config::{Config, PROFILES},
+    Error,
+};
+
+#[test]
+fn profiles() {
+    assert_eq!(Config::from_toml("").unwrap(), Config::waveshare_hat());
+    let config = Config::from_toml(r#"profile = "waveshare-hat-ce1""#).unwrap();
+    assert_eq!(config, Config::waveshare_hat_ce1());
+    assert_eq!((config.spi_bus, config.spi_device), (0, 1));
+
+    let error = Config::from_toml(r#"profile = "nope""#).unwrap_err();
+    assert!(matches!(error, Error::InvalidConfig(_)), "{error:?}");
+
+    // The Linux backend can't drive the chip select lines the SPI driver
+    // claims, which the profiles are wired to.
+    for name in PROFILES {
+        assert_eq!(Config::profile(name).unwrap().cs_pin, None, "{name}");
+    }
+}
+
+#[test]
+fn overrides() {
+    let config = Config::from_toml(
+        r#"
+        profile = "waveshare-hat"
+        spi_bus = 1
+        spi_speed_hz = 4_000_000
+        gpio_chip = 2
+        busy_pin = 23
+        cs_pin = "bus"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        config,
+        Config {
+            spi_bus: 1,
+            spi_speed_hz: 4_000_000,
+            gpio_chip: 2,
+            busy_pin: 23,
+            cs_pin: None,
+            ..Config::waveshare_hat()
+        }
+    );
+
+    let error = Config::from_toml("reset = 3").unwrap_err();
+    assert!(matches!(error, Error::Toml(_)), "{error:?}");
+}
+
+#[test]
+fn load() {
+    let path = std::env::temp_dir().join("waveshare-epd-config.toml");
+    std::fs::write(&path, "cs_pin = 22\n").unwrap();
+    assert_eq!(
+        Config::load(&path).unwrap(),
+        Config {
+            cs_pin: Some(22),
+            ..Config::default()
+        }
+    );
+}
@@ -0,0 +1,300 @@
+use std::time::Duration;
+use waveshare_epd::{
+    epd_12in48b::{
+        lut::{Lut, LUT_BYTES},
+        quadrant_bytes, Epd, Plane, Revision, EPD_HEIGHT, EPD_WIDTH, HALF_HEIGHT, LEFT_WIDTH,
+    },
+    framebuffer::{Framebuffer, Rotation, TriColor},
+    interface::Controller,
+    mock::Mock,
+    Display, Error,
+};
+
+const ROW_BYTES: usize = EPD_WIDTH / 8;
+
+#[test]
+fn quadrants() {
+    // Every byte tells its row and which half it comes from.
+    let white: Vec<u8> = (0..EPD_HEIGHT)
+        .flat_map(|y| (0..ROW_BYTES).map(move |x| (y % 128) as u8 | u8::from(x >= 81) << 7))
+        .collect();
+    let red = vec![0x0F; white.len()];
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.display_frame(&white, &red, Rotation::Rotate0).unwrap();
+
+    let mock = epd.interface();
+    for (controller, x, y) in [
+        (Controller::S2, 0, 0),
+        (Controller::M2, LEFT_WIDTH, 0),
+        (Controller::M1, 0, HALF_HEIGHT),
+        (Controller::S1, LEFT_WIDTH, HALF_HEIGHT),
+    ] {
+        let width = if x == 0 {
+            LEFT_WIDTH
+        } else {
+            EPD_WIDTH - LEFT_WIDTH
+        };
+        let expected: Vec<u8> = (y..y + HALF_HEIGHT)
+            .flat_map(|y| &white[y * ROW_BYTES + x / 8..][..width / 8])
+            .copied()
+            .collect();
+        assert_eq!(mock.data(controller, 0x10), expected, "{controller:?}");
+        assert_eq!(
+            mock.data(controller, 0x13),
+            vec![0x0F; expected.len()],
+            "{controller:?}"
+        );
+    }
+}
+
+#[test]
+fn rotation() {
+    for rotation in [
+        Rotation::Rotate0,
+        Rotation::Rotate90,
+        Rotation::Rotate180,
+        Rotation::Rotate270,
+    ] {
+        let mut frame = Framebuffer::new(EPD_WIDTH, EPD_HEIGHT).with_rotation(rotation);
+        let (width, height) = frame.dimensions();
+        let mut white = vec![0xFF; width / 8 * height];
+        for y in 0..height {
+            for x in (y % 7..width).step_by(7) {
+                frame.set_pixel(x, y, TriColor::Black);
+                white[(x + y * width) / 8] &= !(0x80 >> (x % 8));
+            }
+        }
+
+        let mut planes = Epd::with_interface(Mock::new());
+        planes
+            .display_frame(&white, &vec![0x00; white.len()], rotation)
+            .unwrap();
+        let mut framebuffer = Epd::with_interface(Mock::new());
+        framebuffer.display_framebuffer(&frame).unwrap();
+        assert_eq!(
+            planes.interface().transcript(),
+            framebuffer.interface().transcript(),
+            "{rotation:?}"
+        );
+    }
+}
+
+#[test]
+fn framebuffer_dimensions() {
+    let mut epd = Epd::with_interface(Mock::new());
+    let error = epd
+        .display_framebuffer(&Framebuffer::new(EPD_HEIGHT, EPD_WIDTH))
+        .unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::InvalidDimensions {
+                expected: (EPD_WIDTH, EPD_HEIGHT),
+                actual: (EPD_HEIGHT, EPD_WIDTH)
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn streaming() {
+    let data: Vec<u8> = (0..quadrant_bytes(Controller::M1))
+        .map(|i| i as u8)
+        .collect();
+    let mut streamed = Epd::with_interface(Mock::new());
+    let mut writer = streamed.begin(Controller::M1, Plane::White).unwrap();
+    for chunk in data.chunks(1000) {
+        writer.write(chunk).unwrap();
+    }
+    assert_eq!(writer.remaining(), 0);
+    writer.end().unwrap();
+
+    let mut whole = Epd::with_interface(Mock::new());
+    whole
+        .m1_display_white(&data.clone().try_into().unwrap())
+        .unwrap();
+    assert_eq!(
+        streamed.interface().transcript(),
+        whole.interface().transcript()
+    );
+    assert_eq!(streamed.interface().data(Controller::M1, 0x10), data);
+}
+
+#[test]
+fn streaming_length() {
+    let expected = quadrant_bytes(Controller::S1);
+    let mut epd = Epd::with_interface(Mock::new());
+
+    let mut writer = epd.begin(Controller::S1, Plane::Red).unwrap();
+    writer.write(&[0; 100]).unwrap();
+    let error = writer.end().unwrap_err();
+    assert!(
+        matches!(error, Error::InvalidLength { expected: e, actual: 100 } if e == expected),
+        "{error:?}"
+    );
+
+    let mut writer = epd.begin(Controller::S1, Plane::Red).unwrap();
+    writer.write(&vec![0; expected - 1]).unwrap();
+    let error = writer.write(&[0; 2]).unwrap_err();
+    assert!(
+        matches!(error, Error::InvalidLength { expected: e, actual } if e == expected && actual == expected + 1),
+        "{error:?}"
+    );
+    // Nothing past the end is sent.
+    assert_eq!(
+        epd.interface().data(Controller::S1, 0x13).len(),
+        100 + expected - 1
+    );
+}
+
+#[test]
+fn init_v1() {
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.init().unwrap();
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [
+            0x00, 0x01, 0x06, 0x61, 0x15, 0x30, 0x50, 0x60, 0xE0, 0xE3, 0x82, 0x20, 0x21, 0x22,
+            0x23, 0x24, 0x25
+        ]
+    );
+    assert_eq!(
+        mock.commands(Controller::S1),
+        [0x00, 0x61, 0x15, 0x30, 0x50, 0x60, 0xE3, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25]
+    );
+    assert_eq!(mock.data(Controller::S1, 0x00), [0x2F]);
+    assert_eq!(mock.data(Controller::M2, 0x00), [0x23]);
+    assert_eq!(mock.data(Controller::S1, 0x61), [0x02, 0x90, 0x01, 0xEC]);
+}
+
+#[test]
+fn init_v2() {
+    let mut epd = Epd::with_interface(Mock::new().with_idle_high(false).with_response(0x40, &[23]))
+        .with_revision(Revision::V2);
+    epd.init().unwrap();
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x00, 0x06, 0x61, 0x15, 0x50, 0x60, 0xE3, 0x40, 0xE0, 0xE5]
+    );
+    assert_eq!(
+        mock.commands(Controller::S1),
+        [0x00, 0x61, 0x15, 0x50, 0x60, 0xE3, 0xE0, 0xE5]
+    );
+    assert_eq!(mock.data(Controller::S1, 0x00), [0x0F]);
+    assert_eq!(mock.data(Controller::M2, 0x00), [0x03]);
+    assert_eq!(mock.data(Controller::S2, 0x50), [0x11, 0x07]);
+    // Forced to M1's temperature.
+    assert_eq!(mock.data(Controller::S2, 0xE0), [0x03]);
+    assert_eq!(mock.data(Controller::S2, 0xE5), [23]);
+}
+
+#[test]
+fn init_v2_without_reads() {
+    let mut epd = Epd::with_interface(Mock::new().with_idle_high(false).with_reads(false))
+        .with_revision(Revision::V2);
+    epd.init().unwrap();
+    // The OTP tables follow the controllers' own sensors.
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x00, 0x06, 0x61, 0x15, 0x50, 0x60, 0xE3, 0x40]
+    );
+    assert_eq!(
+        mock.commands(Controller::S1),
+        [0x00, 0x61, 0x15, 0x50, 0x60, 0xE3]
+    );
+}
+
+#[test]
+fn busy_polarity() {
+    for (revision, idle_high) in [(Revision::V1, true), (Revision::V2, false)] {
+        let mut epd = Epd::with_interface(Mock::new().with_idle_high(idle_high))
+            .with_revision(revision)
+            .with_timeout(Duration::from_secs(1));
+        epd.init().unwrap();
+        Display::refresh(&mut epd).unwrap();
+        assert_eq!(epd.detect_revision().unwrap(), revision);
+
+        // The other revision's idle level means busy.
+        let mut epd = Epd::with_interface(Mock::new().with_idle_high(!idle_high))
+            .with_revision(revision)
+            .with_timeout(Duration::from_secs(1));
+        let error = Display::refresh(&mut epd).unwrap_err();
+        assert!(
+            matches!(
+                error,
+                Error::Timeout {
+                    command: Some(0x12),
+                    ..
+                }
+            ),
+            "{revision:?}: {error:?}"
+        );
+    }
+}
+
+#[test]
+fn lut_presets() {
+    let stock = Lut::stock();
+    let fast = Lut::fast();
+    let fast_bw = Lut::fast_bw();
+    assert!(fast.duration() < stock.duration());
+    assert!(fast_bw.duration() < fast.duration());
+    assert_eq!(fast_bw.red.iter().filter(|g| g.repeat > 0).count(), 5);
+
+    // The stock tables are the ones uploaded at init.
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.init().unwrap();
+    let uploaded: Vec<u8> = (0x20..=0x25)
+        .flat_map(|register| epd.interface().data(Controller::S2, register))
+        .collect();
+    assert_eq!(uploaded, stock.to_bytes());
+}
+
+#[test]
+fn lut_file() {
+    let path = std::env::temp_dir().join("waveshare-epd-lut.bin");
+    Lut::fast_bw().save(&path).unwrap();
+    assert_eq!(Lut::load(&path).unwrap(), Lut::fast_bw());
+
+    let error = Lut::from_bytes(&[0; 10]).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::InvalidLength {
+                expected: LUT_BYTES,
+                actual: 10
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn lut_per_refresh() {
+    for revision in [Revision::V1, Revision::V2] {
+        let mut epd = Epd::with_interface(Mock::new().with_idle_high(revision == Revision::V1))
+            .with_revision(revision);
+        epd.init().unwrap();
+        epd.interface_mut().clear();
+        futures::executor::block_on(epd.turn_on_with(&Lut::fast())).unwrap();
+
+        let mock = epd.interface();
+        let commands = mock.commands(Controller::M2);
+        let refresh = commands.iter().position(|&c| c == 0x12).unwrap();
+        assert!(commands[..refresh].contains(&0x24), "{revision:?}");
+        assert_eq!(
+            mock.data(Controller::M2, 0x24),
+            Lut::fast().to_bytes()[4 * 60..5 * 60]
+        );
+        // V2 panels switch to register LUTs.
+        let panel_setting = mock.data(Controller::M2, 0x00);
+        match revision {
+            Revision::V1 => assert!(panel_setting.is_empty()),
+            Revision::V2 => assert_eq!(panel_setting, [0x23]),
+        }
+    }
+}
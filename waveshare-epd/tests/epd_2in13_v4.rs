@@ -0,0 +1,59 @@
+use std::time::Duration;
+use waveshare_epd::{
+    epd_2in13_v4::{Epd, EPD_HEIGHT, EPD_WIDTH},
+    interface::Controller,
+    mock::Mock,
+    packing::Packing,
+    Display, Error,
+};
+
+const FRAME_BYTES: usize = 16 * EPD_HEIGHT;
+
+#[test]
+fn init() {
+    let mut epd = Epd::with_interface(Mock::new().with_idle_high(false));
+    epd.init().unwrap();
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x12, 0x01, 0x11, 0x44, 0x45, 0x4E, 0x4F, 0x3C, 0x21, 0x18]
+    );
+    assert_eq!(mock.data(Controller::M1, 0x01), [0xF9, 0x00, 0x00]);
+    assert_eq!(mock.data(Controller::M1, 0x44), [0x00, 0x0F]);
+    assert_eq!(mock.data(Controller::M1, 0x45), [0x00, 0x00, 0xF9, 0x00]);
+}
+
+#[test]
+fn display() {
+    // Rows are padded to whole bytes.
+    let packing = Packing::new(EPD_WIDTH, EPD_HEIGHT);
+    assert_eq!(packing.len(), FRAME_BYTES);
+    let black: Vec<u8> = (0..FRAME_BYTES).map(|i| i as u8).collect();
+
+    let mut epd = Epd::with_interface(Mock::new().with_idle_high(false));
+    epd.display(&black).unwrap();
+    epd.sleep().unwrap();
+
+    let mock = epd.interface();
+    assert_eq!(mock.commands(Controller::M1), [0x24, 0x22, 0x20, 0x10]);
+    assert_eq!(mock.data(Controller::M1, 0x24), black);
+    assert_eq!(mock.data(Controller::M1, 0x22), [0xF7]);
+    assert_eq!(mock.data(Controller::M1, 0x10), [0x01]);
+}
+
+#[test]
+fn busy_polarity() {
+    // The busy line is high while busy.
+    let mut epd = Epd::with_interface(Mock::new()).with_timeout(Duration::from_millis(100));
+    let error = Display::refresh(&mut epd).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::Timeout {
+                controller: None,
+                command: Some(0x20)
+            }
+        ),
+        "{error:?}"
+    );
+}
@@ -0,0 +1,81 @@
+use image::{GrayImage, Luma};
+use waveshare_epd::{
+    epd_2in7::{pack_buffer, Epd, EPD_HEIGHT, EPD_WIDTH, GRAY_BUFFER_SIZE},
+    interface::Controller,
+    mock::Mock,
+    Display,
+};
+
+const PLANE_BYTES: usize = EPD_WIDTH / 8 * EPD_HEIGHT;
+
+#[test]
+fn init() {
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.init().unwrap();
+    let mock = epd.interface();
+    let mut expected = vec![0x01, 0x06];
+    expected.extend([0xF8; 7]);
+    expected.extend([0x16, 0x04, 0x00, 0x30, 0x61, 0x82, 0x50]);
+    expected.extend(0x20..=0x25);
+    assert_eq!(mock.commands(Controller::M1), expected);
+    assert_eq!(mock.data(Controller::M1, 0x00), [0xBF]);
+    assert_eq!(mock.data(Controller::M1, 0x61), [0x00, 0xB0, 0x01, 0x08]);
+    assert_eq!(mock.data(Controller::M1, 0x20).len(), 44);
+    assert_eq!(
+        mock.data(Controller::M1, 0x25),
+        mock.data(Controller::M1, 0x21)
+    );
+}
+
+#[test]
+fn gray_levels() {
+    // Each row of the image steps through the levels, from black to white.
+    let image = GrayImage::from_fn(EPD_WIDTH as u32, EPD_HEIGHT as u32, |x, _| {
+        Luma([(x % 4) as u8 * 85])
+    });
+    let frame = pack_buffer(&image).unwrap();
+    assert_eq!(frame.len(), GRAY_BUFFER_SIZE);
+    assert!(frame.iter().all(|&byte| byte == 0b00_01_10_11));
+
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.display(&frame).unwrap();
+    epd.sleep().unwrap();
+
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x10, 0x13, 0x12, 0x50, 0x02, 0x07]
+    );
+    // The high bits of black, dark gray, light gray and white, then the low
+    // ones.
+    assert_eq!(
+        mock.data(Controller::M1, 0x10),
+        vec![0b0011_0011; PLANE_BYTES]
+    );
+    assert_eq!(
+        mock.data(Controller::M1, 0x13),
+        vec![0b0101_0101; PLANE_BYTES]
+    );
+}
+
+#[test]
+fn horizontal() {
+    let mut image = GrayImage::from_pixel(EPD_HEIGHT as u32, EPD_WIDTH as u32, Luma([255]));
+    image.put_pixel(0, 0, Luma([100]));
+    let frame = pack_buffer(&image).unwrap();
+    // Rotated clockwise, to the bottom left corner.
+    let row = EPD_WIDTH / 4;
+    assert_eq!(frame[(EPD_HEIGHT - 1) * row], 0b01_11_11_11);
+    assert_eq!(frame.iter().filter(|&&b| b != 0xFF).count(), 1);
+}
+
+#[test]
+fn write_frame() {
+    // Black and white frames only take the extreme levels.
+    let black: Vec<u8> = (0..PLANE_BYTES).map(|i| i as u8).collect();
+    let mut epd = Epd::with_interface(Mock::new());
+    Display::write_frame(&mut epd, &black, &[]).unwrap();
+    let mock = epd.interface();
+    assert_eq!(mock.data(Controller::M1, 0x10), black);
+    assert_eq!(mock.data(Controller::M1, 0x13), black);
+}
@@ -1,4 +1,5 @@
-#[cfg(feature = "epd_2in7b")]
+#![cfg(all(feature = "epd_2in7b", feature = "rpi"))]
+
 use waveshare_epd::epd_2in7b::{pack_buffer, Epd};
 
 #[test]
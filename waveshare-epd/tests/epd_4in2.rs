@@ -0,0 +1,58 @@
+use waveshare_epd::{
+    epd_4in2::{Epd, EPD_HEIGHT, EPD_WIDTH},
+    interface::Controller,
+    mock::Mock,
+    Display,
+};
+
+const FRAME_BYTES: usize = EPD_WIDTH / 8 * EPD_HEIGHT;
+
+#[test]
+fn init() {
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.init().unwrap();
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x01, 0x06, 0x04, 0x00, 0x30, 0x61, 0x82, 0x50, 0x20, 0x21, 0x22, 0x23, 0x24]
+    );
+    assert_eq!(mock.data(Controller::M1, 0x00), [0xBF, 0x0D]);
+    assert_eq!(mock.data(Controller::M1, 0x61), [0x01, 0x90, 0x01, 0x2C]);
+    // The panel refreshes with the LUTs from the registers.
+    assert_eq!(mock.data(Controller::M1, 0x20).len(), 44);
+    for lut in 0x21..=0x24 {
+        assert_eq!(mock.data(Controller::M1, lut).len(), 42, "{lut:#04x}");
+    }
+    assert_eq!(
+        mock.data(Controller::M1, 0x21),
+        mock.data(Controller::M1, 0x22)
+    );
+    assert_eq!(
+        mock.data(Controller::M1, 0x23),
+        mock.data(Controller::M1, 0x24)
+    );
+}
+
+#[test]
+fn display() {
+    let black: Vec<u8> = (0..FRAME_BYTES).map(|i| i as u8).collect();
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.display(&black).unwrap();
+    epd.sleep().unwrap();
+
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x10, 0x13, 0x12, 0x02, 0x07]
+    );
+    assert_eq!(mock.data(Controller::M1, 0x10), vec![0xFF; FRAME_BYTES]);
+    assert_eq!(mock.data(Controller::M1, 0x13), black);
+}
+
+#[test]
+fn clear() {
+    let mut epd = Epd::with_interface(Mock::new());
+    Display::clear(&mut epd).unwrap();
+    let mock = epd.interface();
+    assert_eq!(mock.data(Controller::M1, 0x13), vec![0xFF; FRAME_BYTES]);
+}
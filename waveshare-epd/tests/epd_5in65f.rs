@@ -0,0 +1,79 @@
+use waveshare_epd::{
+    epd_5in65f::{Color, Epd, EPD_HEIGHT, EPD_WIDTH},
+    interface::Controller,
+    mock::Mock,
+    Display, Error,
+};
+
+const PLANE_BYTES: usize = EPD_WIDTH / 8 * EPD_HEIGHT;
+
+#[test]
+fn init() {
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.init().unwrap();
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x00, 0x01, 0x03, 0x06, 0x30, 0x41, 0x50, 0x60, 0x61, 0xE3, 0x50]
+    );
+    assert_eq!(mock.data(Controller::M1, 0x00), [0xEF, 0x08]);
+    assert_eq!(mock.data(Controller::M1, 0x61), [0x02, 0x58, 0x01, 0xC0]);
+    assert_eq!(mock.data(Controller::M1, 0x50), [0x37, 0x37]);
+}
+
+#[test]
+fn display() {
+    let colors = [
+        Color::Black,
+        Color::White,
+        Color::Green,
+        Color::Blue,
+        Color::Red,
+        Color::Yellow,
+        Color::Orange,
+        Color::Clean,
+    ];
+    let frame: Vec<Color> = colors
+        .iter()
+        .copied()
+        .cycle()
+        .take(EPD_WIDTH * EPD_HEIGHT)
+        .collect();
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.display(&frame).unwrap();
+    epd.sleep().unwrap();
+
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x61, 0x10, 0x04, 0x12, 0x02, 0x07]
+    );
+    let data = mock.data(Controller::M1, 0x10);
+    assert_eq!(data.len(), EPD_WIDTH * EPD_HEIGHT / 2);
+    assert_eq!(data[..4], [0x01, 0x23, 0x45, 0x67]);
+}
+
+#[test]
+fn write_frame() {
+    let mut black = vec![0xFF; PLANE_BYTES];
+    let mut red = vec![0xFF; PLANE_BYTES];
+    black[0] = 0x3F;
+    red[0] = 0x5F;
+    let mut epd = Epd::with_interface(Mock::new());
+    Display::write_frame(&mut epd, &black, &red).unwrap();
+
+    let data = epd.interface().data(Controller::M1, 0x10);
+    // Red over black, black, red, then white.
+    assert_eq!(data[..3], [0x40, 0x41, 0x11]);
+    assert!(data[3..].iter().all(|&byte| byte == 0x11));
+}
+
+#[test]
+fn length() {
+    let mut epd = Epd::with_interface(Mock::new());
+    let error = epd.write(&[Color::White; 2]).unwrap_err();
+    assert!(
+        matches!(error, Error::InvalidLength { actual: 2, .. }),
+        "{error:?}"
+    );
+}
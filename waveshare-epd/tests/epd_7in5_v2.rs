@@ -0,0 +1,91 @@
+use waveshare_epd::{
+    epd_7in5_v2::{Epd, EPD_HEIGHT, EPD_WIDTH},
+    interface::Controller,
+    mock::{Event, Mock},
+    Display, Error,
+};
+
+const FRAME_BYTES: usize = EPD_WIDTH / 8 * EPD_HEIGHT;
+
+#[test]
+fn init() {
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.init().unwrap();
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x01, 0x06, 0x04, 0x00, 0x61, 0x15, 0x50, 0x60]
+    );
+    assert_eq!(mock.data(Controller::M1, 0x00), [0x1F]);
+    assert_eq!(mock.data(Controller::M1, 0x61), [0x03, 0x20, 0x01, 0xE0]);
+    assert_eq!(mock.data(Controller::M1, 0x50), [0x10, 0x07]);
+}
+
+#[test]
+fn display() {
+    let black: Vec<u8> = (0..FRAME_BYTES).map(|i| i as u8).collect();
+    let mut epd = Epd::with_interface(Mock::new());
+    let report = epd.display(&black).unwrap();
+    epd.sleep().unwrap();
+    // The panel is powered on at init and off on sleep, not per refresh.
+    let [timings] = &report.timings[..] else {
+        panic!("{report:?}");
+    };
+    assert_eq!(timings.power_on, None);
+    assert_eq!(timings.power_off, None);
+
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x10, 0x13, 0x12, 0x02, 0x07]
+    );
+    assert_eq!(mock.data(Controller::M1, 0x10), black);
+    // New data has set bits for black.
+    let inverted: Vec<u8> = black.iter().map(|b| !b).collect();
+    assert_eq!(mock.data(Controller::M1, 0x13), inverted);
+    assert_eq!(mock.data(Controller::M1, 0x07), [0xA5]);
+}
+
+#[test]
+fn write_frame() {
+    let black = vec![0xAA; FRAME_BYTES];
+    let mut planes = Epd::with_interface(Mock::new());
+    Display::write_frame(&mut planes, &black, &[]).unwrap();
+    let mut direct = Epd::with_interface(Mock::new());
+    direct.write(&black).unwrap();
+    assert_eq!(
+        planes.interface().transcript(),
+        direct.interface().transcript()
+    );
+}
+
+#[test]
+fn clear() {
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.clear().unwrap();
+    let mock = epd.interface();
+    // A white frame, as written by `write`.
+    assert_eq!(mock.data(Controller::M1, 0x10), vec![0xFF; FRAME_BYTES]);
+    assert_eq!(mock.data(Controller::M1, 0x13), vec![0x00; FRAME_BYTES]);
+    assert_eq!(
+        mock.transcript().last(),
+        Some(&Event::Delay(std::time::Duration::from_millis(100)))
+    );
+}
+
+#[test]
+fn length() {
+    let mut epd = Epd::with_interface(Mock::new());
+    let error = epd.write(&[0xFF; 100]).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::InvalidLength {
+                expected: FRAME_BYTES,
+                actual: 100
+            }
+        ),
+        "{error:?}"
+    );
+    assert!(epd.interface().transcript().is_empty());
+}
@@ -0,0 +1,52 @@
+use waveshare_epd::{
+    epd_7in5b_v2::{Epd, EPD_HEIGHT, EPD_WIDTH},
+    interface::Controller,
+    mock::Mock,
+    Display, Ink,
+};
+
+const FRAME_BYTES: usize = EPD_WIDTH / 8 * EPD_HEIGHT;
+
+#[test]
+fn init() {
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.init().unwrap();
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x01, 0x06, 0x04, 0x00, 0x61, 0x15, 0x50, 0x60, 0x65]
+    );
+    assert_eq!(mock.data(Controller::M1, 0x00), [0x0F]);
+    assert_eq!(mock.data(Controller::M1, 0x50), [0x11, 0x07]);
+    assert_eq!(mock.data(Controller::M1, 0x65), [0x00; 4]);
+}
+
+#[test]
+fn display() {
+    let black: Vec<u8> = (0..FRAME_BYTES).map(|i| i as u8).collect();
+    let red = vec![0x0F; FRAME_BYTES];
+    let mut epd = Epd::with_interface(Mock::new());
+    assert!(epd.inks().contains(&Ink::Red));
+    Display::write_frame(&mut epd, &black, &red).unwrap();
+    Display::refresh(&mut epd).unwrap();
+    epd.sleep().unwrap();
+
+    let mock = epd.interface();
+    assert_eq!(
+        mock.commands(Controller::M1),
+        [0x10, 0x13, 0x12, 0x02, 0x07]
+    );
+    assert_eq!(mock.data(Controller::M1, 0x10), black);
+    // The red plane has set bits for red.
+    assert_eq!(mock.data(Controller::M1, 0x13), vec![0xF0; FRAME_BYTES]);
+}
+
+#[test]
+fn clear() {
+    let mut epd = Epd::with_interface(Mock::new());
+    epd.clear().unwrap();
+    let mock = epd.interface();
+    assert_eq!(mock.commands(Controller::M1), [0x10, 0x13, 0x12]);
+    assert_eq!(mock.data(Controller::M1, 0x10), vec![0xFF; FRAME_BYTES]);
+    assert_eq!(mock.data(Controller::M1, 0x13), vec![0x00; FRAME_BYTES]);
+}
@@ -0,0 +1,151 @@
+use std::time::Duration;
+use waveshare_epd::{
+    epd_12in48b, epd_2in7b, interface::Controller, sim::Simulator, Display, Error,
+};
+
+#[test]
+fn epd_2in7b_timeout() {
+    let mut epd =
+        epd_2in7b::Epd::with_interface(Simulator::epd_2in7b().with_disconnected(Controller::M1))
+            .with_timeout(Duration::from_secs(5));
+    let error = epd.init().unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::Timeout {
+                controller: None,
+                command: None
+            }
+        ),
+        "{error:?}"
+    );
+    assert_eq!(error.to_string(), "e-Paper still busy after reset");
+    let elapsed = epd.interface().elapsed();
+    assert!(elapsed < Duration::from_secs(6), "{elapsed:?}");
+}
+
+#[test]
+fn epd_2in7b_refresh_timeout() {
+    // Refreshing takes longer than the timeout.
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b())
+        .with_timeout(Duration::from_secs(10));
+    epd.init().unwrap();
+    let error = Display::refresh(&mut epd).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::Timeout {
+                controller: None,
+                command: Some(0x12)
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn epd_12in48b_timeout() {
+    let mut epd = epd_12in48b::Epd::with_interface(
+        Simulator::epd_12in48b().with_disconnected(Controller::S2),
+    )
+    .with_timeout(Duration::from_secs(30));
+    epd.init().unwrap();
+    let error = Display::clear(&mut epd).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::Timeout {
+                controller: Some(Controller::S2),
+                command: Some(0x12)
+            }
+        ),
+        "{error:?}"
+    );
+    assert_eq!(error.to_string(), "S2 still busy after command 0x12");
+}
+
+#[test]
+fn pack_buffer_dimensions() {
+    let image = image::GrayImage::new(100, 100);
+    let error = epd_2in7b::pack_buffer(&image).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::InvalidDimensions {
+                expected: (176, 264),
+                actual: (100, 100)
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn write_frame_length() {
+    let mut epd = epd_12in48b::Epd::with_interface(Simulator::epd_12in48b());
+    let error = epd.write_frame(&[0xFF; 10], &[0xFF; 10]).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::InvalidLength {
+                expected: 160_392,
+                actual: 10
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn window_alignment() {
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b());
+    let window = epd_2in7b::Window {
+        x: 4,
+        y: 0,
+        width: 8,
+        height: 8,
+    };
+    let error = epd
+        .display_window(&window, &[0xFF; 8], &[0xFF; 8])
+        .unwrap_err();
+    assert!(
+        matches!(error, Error::InvalidWindow { x: 4, .. }),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn temperature_range() {
+    let mut epd = epd_12in48b::Epd::with_interface(
+        Simulator::epd_12in48b().with_temperature(Controller::S1, -3),
+    );
+    epd.init().unwrap();
+    let error = futures::executor::block_on(epd.turn_on_compensated()).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::Temperature {
+                controller: Some(Controller::S1),
+                celsius: -3
+            }
+        ),
+        "{error:?}"
+    );
+    assert_eq!(error.to_string(), "S1 is at -3 °C, outside the rated range");
+    assert_eq!(epd.interface().refreshes(), 0);
+
+    let mut epd =
+        epd_2in7b::Epd::with_interface(Simulator::epd_2in7b()).with_rated_temperature(25..=40);
+    epd.init().unwrap();
+    let error = epd.refresh_checked().unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::Temperature {
+                controller: None,
+                celsius: 20
+            }
+        ),
+        "{error:?}"
+    );
+}
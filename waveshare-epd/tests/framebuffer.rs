@@ -0,0 +1,98 @@
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::Text,
+};
+use image::Rgb;
+use waveshare_epd::{
+    epd_2in7b::{self, pack_buffer, EPD_HEIGHT, EPD_WIDTH},
+    framebuffer::{Framebuffer, Rotation, TriColor},
+    sim::Simulator,
+    Display,
+};
+
+#[test]
+fn planes() {
+    let mut frame = Framebuffer::new(16, 2);
+    frame.set_pixel(0, 0, TriColor::Black);
+    frame.set_pixel(9, 1, TriColor::Red);
+    assert_eq!(frame.black(), [0x7F, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(frame.red(), [0xFF, 0xFF, 0xFF, 0xBF]);
+
+    // Overwriting a pixel clears the other planes.
+    frame.set_pixel(0, 0, TriColor::Red);
+    frame.set_pixel(9, 1, TriColor::White);
+    assert_eq!(frame.black(), [0xFF; 4]);
+    assert_eq!(frame.red(), [0x7F, 0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+fn rotation() {
+    for (rotation, native) in [
+        (Rotation::Rotate0, (1, 0)),
+        (Rotation::Rotate90, (15, 1)),
+        (Rotation::Rotate180, (14, 3)),
+        (Rotation::Rotate270, (0, 2)),
+    ] {
+        let mut frame = Framebuffer::new(16, 4).with_rotation(rotation);
+        frame.set_pixel(1, 0, TriColor::Black);
+        assert_eq!(frame.pixel(1, 0), Some(TriColor::Black));
+
+        let mut expected = Framebuffer::new(16, 4);
+        expected.set_pixel(native.0, native.1, TriColor::Black);
+        assert_eq!(frame.black(), expected.black(), "{rotation:?}");
+    }
+}
+
+/// The 2.7" panel's landscape orientation matches `pack_buffer`'s.
+#[test]
+fn matches_pack_buffer() {
+    let mut frame = Framebuffer::new(EPD_WIDTH, EPD_HEIGHT).with_rotation(Rotation::Rotate270);
+    assert_eq!(frame.size(), Size::new(EPD_HEIGHT as u32, EPD_WIDTH as u32));
+    Rectangle::new(Point::new(10, 20), Size::new(30, 40))
+        .into_styled(PrimitiveStyle::with_fill(TriColor::Black))
+        .draw(&mut frame)
+        .unwrap();
+
+    let image = image::GrayImage::from_fn(EPD_HEIGHT as u32, EPD_WIDTH as u32, |x, y| {
+        if (10..40).contains(&x) && (20..60).contains(&y) {
+            image::Luma([0])
+        } else {
+            image::Luma([255])
+        }
+    });
+    assert_eq!(frame.black(), pack_buffer(&image).unwrap());
+}
+
+#[test]
+fn text() {
+    let mut frame = Framebuffer::new(EPD_WIDTH, EPD_HEIGHT).with_rotation(Rotation::Rotate90);
+    Text::new(
+        "Hello",
+        Point::new(5, 10),
+        MonoTextStyle::new(&FONT_6X10, TriColor::Red),
+    )
+    .draw(&mut frame)
+    .unwrap();
+    // Out of bounds.
+    Pixel(Point::new(-1, 0), TriColor::Black)
+        .draw(&mut frame)
+        .unwrap();
+
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b());
+    epd.init().unwrap();
+    Display::write_frame(&mut epd, frame.black(), frame.red()).unwrap();
+    Display::refresh(&mut epd).unwrap();
+
+    let shown = epd.interface().frame();
+    assert!(shown.pixels().all(|&p| p != Rgb([0, 0, 0])));
+    let red = shown.pixels().filter(|&&p| p == Rgb([255, 0, 0])).count();
+    assert!(red > 0);
+    let (width, height) = frame.dimensions();
+    let drawn = (0..width)
+        .flat_map(|x| (0..height).map(move |y| (x, y)))
+        .filter(|&(x, y)| frame.pixel(x, y) == Some(TriColor::Red))
+        .count();
+    assert_eq!(red, drawn);
+}
@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant, SystemTime};
+use waveshare_epd::{
+    epd_2in7b::Epd,
+    guard::{Early, FileStore, Guard, MemoryStore, Record, Store},
+    mock::Mock,
+    power::Panel,
+    sim::Simulator,
+    Display, Error,
+};
+
+#[test]
+fn reject() {
+    let mut guard = Guard::new(Epd::with_interface(Mock::new()), MemoryStore::default());
+    guard.refresh().unwrap();
+    let error = guard.refresh().unwrap_err();
+    assert!(
+        matches!(error, Error::TooEarly { wait } if wait > Duration::from_secs(170)),
+        "{error:?}"
+    );
+    // Clears refresh too.
+    assert!(matches!(guard.clear(), Err(Error::TooEarly { .. })));
+    assert_eq!(guard.record().unwrap().refreshes, 1);
+}
+
+#[test]
+fn delay() {
+    let interval = Duration::from_millis(200);
+    let mut guard = Guard::new(Epd::with_interface(Mock::new()), MemoryStore::default())
+        .with_min_interval(interval)
+        .with_early(Early::Delay);
+    let start = Instant::now();
+    guard.refresh().unwrap();
+    guard.refresh().unwrap();
+    assert!(start.elapsed() >= interval);
+    assert_eq!(guard.record().unwrap().refreshes, 2);
+}
+
+#[test]
+fn counts() {
+    let epd = Epd::with_interface(Simulator::epd_2in7b());
+    let mut guard = Guard::new(epd, MemoryStore::default()).with_min_interval(Duration::ZERO);
+    {
+        let mut panel = Panel::new(&mut guard).init().unwrap();
+        panel.refresh().unwrap();
+        let panel = panel.start_refresh().unwrap();
+        let (mut panel, _) = panel.finish_refresh().unwrap();
+        panel.deep_clean().unwrap();
+        panel.sleep().unwrap();
+    }
+    let record = guard.record().unwrap();
+    // The deep clean took four refreshes of the panel.
+    assert_eq!(record.refreshes, 6);
+    assert_eq!(guard.display().interface().refreshes(), 6);
+    assert_eq!(record.cleans, 1);
+    assert!(record.last_refresh.is_some());
+    assert_eq!(record.clean.refreshes, 0);
+    assert_eq!(record.clean.last_clean, record.last_refresh);
+}
+
+#[test]
+fn past_max_interval() {
+    let day = Duration::from_secs(24 * 60 * 60);
+    let mut store = MemoryStore::default();
+    let mut guard = Guard::new(Epd::with_interface(Mock::new()), store.clone());
+    assert!(!guard.past_max_interval().unwrap());
+
+    store
+        .save(&Record {
+            last_refresh: Some(SystemTime::now() - day - Duration::from_secs(1)),
+            ..Default::default()
+        })
+        .unwrap();
+    let mut guard = Guard::new(Epd::with_interface(Mock::new()), store);
+    assert!(guard.past_max_interval().unwrap());
+    guard.refresh().unwrap();
+    assert!(!guard.past_max_interval().unwrap());
+}
+
+#[test]
+fn file_store() {
+    let path = std::env::temp_dir().join("waveshare-epd-guard.toml");
+    let _ = std::fs::remove_file(&path);
+
+    let mut guard = Guard::new(Epd::with_interface(Mock::new()), FileStore::new(&path));
+    guard.refresh().unwrap();
+    // Another run is refused too.
+    let mut guard = Guard::new(Epd::with_interface(Mock::new()), FileStore::new(&path));
+    assert!(matches!(guard.refresh(), Err(Error::TooEarly { .. })));
+    assert_eq!(guard.record().unwrap().refreshes, 1);
+    std::fs::remove_file(&path).unwrap();
+}
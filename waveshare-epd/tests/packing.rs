@@ -0,0 +1,183 @@
+use image::{GrayImage, Luma};
+use proptest::prelude::*;
+use waveshare_epd::{
+    epd_2in7b::{pack_buffer, EPD_HEIGHT, EPD_WIDTH},
+    packing::{BitOrder, Depth, Packing, Rotation},
+    Error,
+};
+
+fn packing() -> impl Strategy<Value = Packing> {
+    (
+        1..40usize,
+        1..40usize,
+        prop_oneof![
+            Just(Rotation::Rotate0),
+            Just(Rotation::Rotate90),
+            Just(Rotation::Rotate180),
+            Just(Rotation::Rotate270),
+        ],
+        any::<bool>(),
+        prop_oneof![Just(BitOrder::MsbFirst), Just(BitOrder::LsbFirst)],
+        any::<bool>(),
+    )
+        .prop_map(|(width, height, rotation, mirror, bit_order, inverted)| {
+            Packing::new(width, height)
+                .with_rotation(rotation)
+                .with_mirror(mirror)
+                .with_bit_order(bit_order)
+                .with_inverted(inverted)
+        })
+}
+
+/// A packing, and a black and white image of its size.
+fn image() -> impl Strategy<Value = (Packing, GrayImage)> {
+    packing().prop_flat_map(|packing| {
+        let (width, height) = packing.dimensions();
+        prop::collection::vec(any::<bool>(), width * height).prop_map(move |pixels| {
+            let image = GrayImage::from_fn(width as u32, height as u32, |x, y| {
+                Luma([if pixels[x as usize + y as usize * width] {
+                    0
+                } else {
+                    255
+                }])
+            });
+            (packing, image)
+        })
+    })
+}
+
+proptest! {
+    #[test]
+    fn image_round_trip((packing, image) in image()) {
+        let plane = packing.pack(&image).unwrap();
+        prop_assert_eq!(plane.len(), packing.len());
+        prop_assert_eq!(packing.unpack(&plane).unwrap(), image);
+    }
+
+    #[test]
+    fn plane_round_trip(
+        (packing, plane) in packing().prop_flat_map(|packing| {
+            let len = packing.len();
+            (Just(packing), prop::collection::vec(any::<u8>(), len))
+        })
+    ) {
+        // Only the padding of the rows is lost.
+        let image = packing.unpack(&plane).unwrap();
+        let packed = packing.pack(&image).unwrap();
+        prop_assert_eq!(packing.pack(&image).unwrap(), packed.clone());
+        prop_assert_eq!(packing.unpack(&packed).unwrap(), image);
+    }
+
+    #[test]
+    fn gray_round_trip(
+        (packing, image) in packing().prop_flat_map(|packing| {
+            let packing = packing.with_depth(Depth::Gray4);
+            let (width, height) = packing.dimensions();
+            prop::collection::vec(0..4u8, width * height).prop_map(move |levels| {
+                let image = GrayImage::from_fn(width as u32, height as u32, |x, y| {
+                    Luma([levels[x as usize + y as usize * width] * 85])
+                });
+                (packing, image)
+            })
+        })
+    ) {
+        let plane = packing.pack(&image).unwrap();
+        prop_assert_eq!(plane.len(), packing.len());
+        prop_assert_eq!(packing.unpack(&plane).unwrap(), image);
+    }
+
+    #[test]
+    fn ink_count((packing, image) in image()) {
+        let ink = image.pixels().filter(|p| p.0 == [0]).count() as u32;
+        let plane = packing.pack(&image).unwrap();
+        let set: u32 = plane.iter().map(|b| b.count_ones()).sum();
+        if packing == packing.with_inverted(true) {
+            prop_assert_eq!(set, ink);
+        } else {
+            prop_assert_eq!(set, plane.len() as u32 * 8 - ink);
+        }
+    }
+}
+
+#[test]
+fn layout() {
+    // 10x2: the second byte of each row only has 2 pixels.
+    let mut image = GrayImage::from_pixel(10, 2, Luma([255]));
+    image.put_pixel(0, 0, Luma([0]));
+    image.put_pixel(9, 1, Luma([0]));
+    let packing = Packing::new(10, 2);
+    assert_eq!(packing.pack(&image).unwrap(), [0x7F, 0xFF, 0xFF, 0xBF]);
+    assert_eq!(
+        packing
+            .with_bit_order(BitOrder::LsbFirst)
+            .with_inverted(true)
+            .pack(&image)
+            .unwrap(),
+        [0x01, 0x00, 0x00, 0x02]
+    );
+    assert_eq!(
+        packing.with_mirror(true).pack(&image).unwrap(),
+        [0xFF, 0xBF, 0x7F, 0xFF]
+    );
+
+    let error = packing
+        .with_rotation(Rotation::Rotate90)
+        .pack(&image)
+        .unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::InvalidDimensions {
+                expected: (2, 10),
+                actual: (10, 2)
+            }
+        ),
+        "{error:?}"
+    );
+    let error = packing.unpack(&[0; 3]).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::InvalidLength {
+                expected: 4,
+                actual: 3
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn gray_layout() {
+    // 5x1, quantized to black, dark gray, light gray, white and white.
+    let image = GrayImage::from_raw(5, 1, vec![20, 100, 150, 230, 255]).unwrap();
+    let packing = Packing::new(5, 1).with_depth(Depth::Gray4);
+    assert_eq!(packing.len(), 2);
+    assert_eq!(
+        packing.pack(&image).unwrap(),
+        [0b00_01_10_11, 0b11_11_11_11]
+    );
+    assert_eq!(
+        packing
+            .with_bit_order(BitOrder::LsbFirst)
+            .with_inverted(true)
+            .pack(&image)
+            .unwrap(),
+        [0b00_01_10_11, 0b00_00_00_00]
+    );
+    assert_eq!(
+        packing.unpack(&[0b11_10_01_00, 0xFF]).unwrap().into_raw(),
+        [255, 170, 85, 0, 255]
+    );
+}
+
+#[test]
+fn epd_2in7b_horizontal() {
+    // Horizontal images are rotated clockwise.
+    let mut image = GrayImage::from_pixel(EPD_HEIGHT as u32, EPD_WIDTH as u32, Luma([255]));
+    image.put_pixel(0, 0, Luma([0]));
+    let plane = pack_buffer(&image).unwrap();
+    let row = EPD_WIDTH / 8;
+    assert_eq!(plane[(EPD_HEIGHT - 1) * row], 0x7F);
+    assert_eq!(plane.iter().filter(|&&b| b != 0xFF).count(), 1);
+}
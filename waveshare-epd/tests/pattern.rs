@@ -0,0 +1,78 @@
+use waveshare_epd::{framebuffer::TriColor, pattern::Pattern, Ink};
+
+const BLACK_WHITE: &[Ink] = &[Ink::Black, Ink::White];
+const BLACK_WHITE_RED: &[Ink] = &[Ink::Black, Ink::White, Ink::Red];
+
+#[test]
+fn checkerboard() {
+    let frame = Pattern::Checkerboard(2).render(16, 4, BLACK_WHITE);
+    assert_eq!(
+        frame.black(),
+        [0x33, 0x33, 0x33, 0x33, 0xCC, 0xCC, 0xCC, 0xCC]
+    );
+    assert_eq!(frame.red(), [0xFF; 8]);
+}
+
+#[test]
+fn quadrants() {
+    let frame = Pattern::epd_12in48b_quadrants().render(1304, 984, BLACK_WHITE_RED);
+    // Framed in red where the controllers meet.
+    for (x, y) in [(0, 0), (647, 0), (648, 491), (1303, 983), (0, 492)] {
+        assert_eq!(frame.pixel(x, y), Some(TriColor::Red), "({x}, {y})");
+    }
+    assert_eq!(frame.pixel(1, 1), Some(TriColor::White));
+    // Each quadrant is labeled.
+    for (x0, y0) in [(0, 0), (648, 0), (0, 492), (648, 492)] {
+        let inked = (y0 + 1..y0 + 491)
+            .flat_map(|y| (x0 + 1..x0 + 647).map(move |x| (x, y)))
+            .filter(|&(x, y)| frame.pixel(x, y) == Some(TriColor::Black))
+            .count();
+        assert!(inked > 0, "({x0}, {y0})");
+    }
+
+    // Without red, the frames are black.
+    let frame = Pattern::quadrants(176, 264).render(176, 264, BLACK_WHITE);
+    assert_eq!(frame.pixel(88, 0), Some(TriColor::Black));
+    assert_eq!(frame.red(), vec![0xFF; 176 / 8 * 264]);
+}
+
+#[test]
+fn color_bars() {
+    let frame = Pattern::ColorBars.render(24, 1, BLACK_WHITE_RED);
+    assert_eq!(frame.black(), [0x00, 0xFF, 0xFF]);
+    assert_eq!(frame.red(), [0xFF, 0xFF, 0x00]);
+
+    let frame = Pattern::ColorBars.render(16, 1, BLACK_WHITE);
+    assert_eq!(frame.black(), [0x00, 0xFF]);
+}
+
+#[test]
+fn ramp() {
+    let frame = Pattern::Ramp.render(64, 8, BLACK_WHITE_RED);
+    // Inked pixels of a 4×4 block, the size of the dither matrix.
+    let inked = |x0: usize, y0: usize, color| {
+        (y0..y0 + 4)
+            .flat_map(|y| (x0..x0 + 4).map(move |x| (x, y)))
+            .filter(|&(x, y)| frame.pixel(x, y) == Some(color))
+            .count()
+    };
+    // Full ink on the left, fading out to the right.
+    for (y0, color) in [(0, TriColor::Black), (4, TriColor::Red)] {
+        let coverage: Vec<_> = (0..64).step_by(4).map(|x0| inked(x0, y0, color)).collect();
+        assert_eq!(coverage[0], 16);
+        assert_eq!(coverage[15], 1);
+        assert!(coverage.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+}
+
+#[test]
+fn grid() {
+    let frame = Pattern::Grid(8).render(17, 9, BLACK_WHITE_RED);
+    for (x, y) in [(0, 5), (8, 5), (16, 5), (5, 0), (5, 8)] {
+        assert_eq!(frame.pixel(x, y), Some(TriColor::Black), "({x}, {y})");
+    }
+    assert_eq!(frame.pixel(5, 5), Some(TriColor::White));
+    // The origin is marked.
+    assert_eq!(frame.pixel(1, 1), Some(TriColor::Red));
+    assert_eq!(frame.pixel(14, 6), Some(TriColor::White));
+}
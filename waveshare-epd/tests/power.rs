@@ -0,0 +1,126 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use waveshare_epd::{
+    epd_12in48b, epd_2in7b, interface::Controller, mock::Mock, power::Panel, sim::Simulator,
+    Display, Health, Ink, RefreshReport, Result,
+};
+
+#[test]
+fn lifecycle() {
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b());
+    let frame = vec![0xFF; epd.width() * epd.height() / 8];
+
+    let mut panel = Panel::new(&mut epd).init().unwrap();
+    panel.write_frame(&frame, &frame).unwrap();
+    let panel = panel.start_refresh().unwrap();
+    assert!(panel.display().interface().powered());
+    let (panel, report) = panel.finish_refresh().unwrap();
+    assert_eq!(report.timings.len(), 1);
+    let panel = panel.sleep().unwrap();
+    assert!(panel.display().interface().asleep());
+    // Waking up takes another init.
+    panel.init().unwrap().sleep().unwrap();
+
+    let sim = epd.interface();
+    assert_eq!(sim.refreshes(), 1);
+    assert!(sim.asleep());
+    assert!(!sim.powered());
+}
+
+#[test]
+fn drop_on_panic() {
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b());
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let panel = Panel::new(&mut epd).init().unwrap();
+        let _panel = panel.start_refresh().unwrap();
+        panic!("between init and sleep");
+    }));
+    assert!(result.is_err());
+
+    let sim = epd.interface();
+    assert!(sim.asleep());
+    assert!(!sim.powered());
+}
+
+#[test]
+fn drop_epd_12in48b() {
+    let mut epd = epd_12in48b::Epd::with_interface(Mock::new());
+    let panel = Panel::new(&mut epd).init().unwrap();
+    drop(panel.start_refresh().unwrap());
+
+    let mock = epd.interface();
+    let commands = mock.commands(Controller::S2);
+    assert_eq!(commands[commands.len() - 4..], [0x12, 0x71, 0x02, 0x07]);
+    assert_eq!(mock.data(Controller::S2, 0x07), [0xA5]);
+
+    // Nothing more once asleep.
+    let mut epd = epd_12in48b::Epd::with_interface(Mock::new());
+    drop(Panel::new(&mut epd).init().unwrap().sleep().unwrap());
+    assert_eq!(
+        epd.interface()
+            .commands(Controller::M1)
+            .iter()
+            .filter(|&&c| c == 0x07)
+            .count(),
+        1
+    );
+}
+
+/// A panel logging what it is asked to do.
+#[derive(Default)]
+struct Log(Vec<&'static str>);
+impl Display for Log {
+    fn width(&self) -> usize {
+        8
+    }
+    fn height(&self) -> usize {
+        1
+    }
+    fn inks(&self) -> &'static [Ink] {
+        &[Ink::Black, Ink::White]
+    }
+    fn init(&mut self) -> Result<()> {
+        self.0.push("init");
+        Ok(())
+    }
+    fn write_frame(&mut self, _black: &[u8], _red: &[u8]) -> Result<()> {
+        self.0.push("write");
+        Ok(())
+    }
+    fn start_refresh(&mut self) -> Result<()> {
+        self.0.push("start");
+        Ok(())
+    }
+    fn finish_refresh(&mut self) -> Result<RefreshReport> {
+        self.0.push("finish");
+        Ok(RefreshReport::default())
+    }
+    fn clear(&mut self) -> Result<()> {
+        self.0.push("clear");
+        Ok(())
+    }
+    fn sleep(&mut self) -> Result<()> {
+        self.0.push("sleep");
+        Ok(())
+    }
+    fn diagnose(&mut self) -> Result<Vec<Health>> {
+        Ok(Vec::new())
+    }
+}
+
+#[test]
+fn drop_refreshing() {
+    let mut log = Log::default();
+    drop(
+        Panel::new(&mut log)
+            .init()
+            .unwrap()
+            .start_refresh()
+            .unwrap(),
+    );
+    // The refresh is waited for before sleeping.
+    assert_eq!(log.0, ["init", "start", "finish", "sleep"]);
+
+    let mut log = Log::default();
+    drop(Panel::new(&mut log).init().unwrap());
+    assert_eq!(log.0, ["init", "sleep"]);
+}
@@ -0,0 +1,344 @@
+use image::{GrayImage, Rgb};
+use std::{iter::repeat, time::Duration};
+use waveshare_epd::{
+    epd_12in48b::{self, lut::Lut, Compensation, TemperatureLut},
+    epd_2in7b,
+    interface::Controller,
+    sim::Simulator,
+    Display, RefreshReport, Timings,
+};
+
+const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+const RED: Rgb<u8> = Rgb([255, 0, 0]);
+
+fn load_bmp(bytes: &[u8]) -> GrayImage {
+    image::io::Reader::with_format(std::io::Cursor::new(bytes), image::ImageFormat::Bmp)
+        .decode()
+        .unwrap()
+        .into_luma8()
+}
+
+#[test]
+fn epd_2in7b_image() {
+    let black = load_bmp(include_bytes!("wBJwq8ap6D-b.bmp"));
+    let red = load_bmp(include_bytes!("wBJwq8ap6D-r.bmp"));
+
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b());
+    epd.init().unwrap();
+    epd.display(
+        epd_2in7b::pack_buffer(&black).unwrap().iter().copied(),
+        epd_2in7b::pack_buffer(&red).unwrap().iter().copied(),
+    )
+    .unwrap();
+    epd.sleep().unwrap();
+
+    let sim = epd.interface();
+    assert_eq!(sim.refreshes(), 1);
+    assert!(sim.elapsed() >= Duration::from_secs(15));
+    let frame = sim.frame();
+    // The images are horizontal, and get rotated clockwise.
+    for (x, y, pixel) in frame.enumerate_pixels() {
+        let (u, v) = (epd_2in7b::EPD_HEIGHT as u32 - y - 1, x);
+        let expected = if red[(u, v)].0 == [0] {
+            RED
+        } else if black[(u, v)].0 == [0] {
+            BLACK
+        } else {
+            WHITE
+        };
+        assert_eq!(*pixel, expected, "({x}, {y})");
+    }
+}
+
+#[test]
+fn epd_2in7b_sleep() {
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b());
+    epd.init().unwrap();
+    epd.sleep().unwrap();
+    // Ignored until the next reset.
+    epd.clear().unwrap();
+    assert_eq!(epd.interface().refreshes(), 0);
+    epd.init().unwrap();
+    epd.clear().unwrap();
+    assert_eq!(epd.interface().refreshes(), 1);
+}
+
+#[test]
+fn epd_2in7b_async() {
+    let black: Vec<u8> = (0..epd_2in7b::EPD_WIDTH * epd_2in7b::EPD_HEIGHT / 8)
+        .map(|i| i as u8)
+        .collect();
+    let red = vec![0xF0; black.len()];
+
+    let mut blocking = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b());
+    blocking.init().unwrap();
+    blocking.write_frame(&black, &red).unwrap();
+    Display::refresh(&mut blocking).unwrap();
+
+    let mut asynchronous = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b());
+    asynchronous.init().unwrap();
+    asynchronous.write_frame(&black, &red).unwrap();
+    futures::executor::block_on(asynchronous.turn_on()).unwrap();
+
+    let (blocking, asynchronous) = (blocking.interface(), asynchronous.interface());
+    assert_eq!(asynchronous.refreshes(), 1);
+    assert!(!asynchronous.powered());
+    assert_eq!(asynchronous.frame(), blocking.frame());
+}
+
+#[test]
+fn epd_2in7b_window() {
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b());
+    epd.init().unwrap();
+    epd.clear().unwrap();
+
+    // Black top half, red bottom half.
+    let window = epd_2in7b::Window {
+        x: 16,
+        y: 40,
+        width: 24,
+        height: 10,
+    };
+    let black = [[0x00; 3]; 5].concat();
+    let white = [[0xFF; 3]; 5].concat();
+    let report = epd
+        .display_window(
+            &window,
+            &[&black[..], &white].concat(),
+            &[&white[..], &black].concat(),
+        )
+        .unwrap();
+    assert_eq!(report.timings.len(), 1);
+    assert!(report.timings[0].power_on.is_some(), "{report:?}");
+    assert!(report.timings[0].power_off.is_some(), "{report:?}");
+
+    let sim = epd.interface();
+    assert_eq!(sim.refreshes(), 2);
+    for (x, y, pixel) in sim.frame().enumerate_pixels() {
+        let expected = match (x, y) {
+            (16..40, 40..45) => BLACK,
+            (16..40, 45..50) => RED,
+            _ => WHITE,
+        };
+        assert_eq!(*pixel, expected, "({x}, {y})");
+    }
+}
+
+#[test]
+fn epd_12in48b_quadrants() {
+    const ROW_BYTES: usize = epd_12in48b::EPD_WIDTH / 8;
+    let mut black = vec![0xFF; ROW_BYTES * epd_12in48b::EPD_HEIGHT];
+    let mut red = black.clone();
+    // Top left pixel is black, bottom right one is red.
+    black[0] = 0x7F;
+    *red.last_mut().unwrap() = 0xFE;
+
+    let mut epd = epd_12in48b::Epd::with_interface(Simulator::epd_12in48b());
+    epd.init().unwrap();
+    epd.write_frame(&black, &red).unwrap();
+    epd.refresh().unwrap();
+    epd.sleep().unwrap();
+
+    let frame = epd.interface().frame();
+    let (width, height) = frame.dimensions();
+    assert_eq!(frame[(0, 0)], BLACK);
+    assert_eq!(frame[(1, 0)], WHITE);
+    assert_eq!(frame[(width - 1, height - 1)], RED);
+    assert_eq!(frame[(width - 2, height - 1)], WHITE);
+    assert_eq!(
+        frame.pixels().filter(|&&pixel| pixel == WHITE).count(),
+        frame.len() / 3 - 2
+    );
+}
+
+#[test]
+fn ghosting() {
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b().with_ghosting(0.25));
+    epd.init().unwrap();
+    Display::write_frame(&mut epd, &[0x00; 5808], &[0xFF; 5808]).unwrap();
+    Display::refresh(&mut epd).unwrap();
+    epd.clear().unwrap();
+
+    let frame = epd.interface().frame();
+    assert!(frame.pixels().all(|&pixel| pixel == Rgb([207, 207, 207])));
+}
+
+#[test]
+fn output() {
+    let path = std::env::temp_dir().join("waveshare-epd-sim-output.png");
+    let _ = std::fs::remove_file(&path);
+
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b().with_output(&path));
+    epd.init().unwrap();
+    epd.clear().unwrap();
+
+    let png = image::open(&path).unwrap().into_rgb8();
+    assert_eq!(png, epd.interface().frame());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn epd_2in7b_temperature() {
+    let mut epd =
+        epd_2in7b::Epd::with_interface(Simulator::epd_2in7b().with_temperature(Controller::M1, 23));
+    epd.init().unwrap();
+    assert_eq!(epd.temperature().unwrap(), 23);
+    let report = epd.refresh_checked().unwrap();
+    assert_eq!(report.temperatures, [(None, 23)]);
+    assert_eq!(report.lut, None);
+    assert_eq!(report.timings.len(), 1);
+    assert_eq!(epd.interface().refreshes(), 1);
+}
+
+#[test]
+fn epd_2in7b_timings() {
+    let refresh_time = Duration::from_millis(100);
+    let mut epd = epd_2in7b::Epd::with_interface(
+        Simulator::epd_2in7b()
+            .with_refresh_time(refresh_time)
+            .with_realtime(true),
+    );
+    epd.init().unwrap();
+    let report = epd.display(repeat(0x00), repeat(0xFF)).unwrap();
+    let [timings] = &report.timings[..] else {
+        panic!("{report:?}");
+    };
+    assert_eq!(timings.controller, None);
+    // Powering on and off takes 80 ms, plus the delays that follow.
+    assert!(
+        timings.power_on >= Some(Duration::from_millis(90)),
+        "{timings:?}"
+    );
+    assert!(timings.refresh >= refresh_time, "{timings:?}");
+    assert!(
+        timings.power_off >= Some(Duration::from_millis(110)),
+        "{timings:?}"
+    );
+
+    // Each refresh is timed on its own.
+    let report = futures::executor::block_on(epd.turn_on()).unwrap();
+    assert_eq!(report.timings[0].transfer, Duration::ZERO);
+}
+
+#[test]
+fn epd_12in48b_timings() {
+    let mut epd = epd_12in48b::Epd::with_interface(
+        Simulator::epd_12in48b()
+            .with_refresh_time(Duration::from_millis(100))
+            .with_realtime(true),
+    );
+    epd.init().unwrap();
+    epd.clear().unwrap();
+    let report = futures::executor::block_on(epd.turn_on()).unwrap();
+    assert_eq!(report.timings.len(), 4);
+    for (timings, controller) in report.timings.iter().zip(Controller::ALL) {
+        assert_eq!(timings.controller, Some(controller));
+        assert!(timings.transfer > Duration::ZERO, "{timings:?}");
+        assert!(
+            timings.power_on >= Some(Duration::from_millis(300)),
+            "{timings:?}"
+        );
+        assert!(timings.refresh >= Duration::from_millis(100), "{timings:?}");
+        // The controllers stay powered until put to sleep.
+        assert_eq!(timings.power_off, None);
+    }
+}
+
+#[test]
+fn report_toml() {
+    let report = RefreshReport {
+        temperatures: vec![(None, 23), (Some(Controller::S1), 21)],
+        lut: Some("fast".into()),
+        timings: vec![Timings {
+            controller: None,
+            transfer: Duration::from_millis(12),
+            power_on: None,
+            refresh: Duration::from_secs(15),
+            power_off: Some(Duration::from_millis(110)),
+        }],
+    };
+    let text = toml::to_string(&report).unwrap();
+    assert_eq!(toml::from_str::<RefreshReport>(&text).unwrap(), report);
+}
+
+#[test]
+fn epd_12in48b_compensation() {
+    let compensation = Compensation {
+        luts: vec![
+            TemperatureLut {
+                from: i8::MIN,
+                name: "stock".into(),
+                lut: Lut::stock(),
+            },
+            TemperatureLut {
+                from: 25,
+                name: "fast".into(),
+                lut: Lut::fast(),
+            },
+        ],
+        ..Default::default()
+    };
+    let mut epd = epd_12in48b::Epd::with_interface(
+        Simulator::epd_12in48b()
+            .with_temperature(Controller::M1, 28)
+            .with_temperature(Controller::S2, 24),
+    )
+    .with_compensation(compensation);
+    epd.init().unwrap();
+    let report = futures::executor::block_on(epd.turn_on_compensated()).unwrap();
+    assert_eq!(
+        report.temperatures,
+        [
+            (Some(Controller::M1), 28),
+            (Some(Controller::S1), 20),
+            (Some(Controller::M2), 20),
+            (Some(Controller::S2), 24),
+        ]
+    );
+    // Picked by M1's temperature.
+    assert_eq!(report.lut.as_deref(), Some("fast"));
+    assert_eq!(
+        epd.interface().register(Controller::S2, 0x24).unwrap(),
+        &Lut::fast().to_bytes()[4 * 60..5 * 60]
+    );
+    assert_eq!(epd.interface().refreshes(), 1);
+}
+
+#[test]
+fn diagnose() {
+    let mut epd = epd_12in48b::Epd::with_interface(
+        Simulator::epd_12in48b().with_disconnected(Controller::S2),
+    )
+    .with_timeout(Duration::from_millis(500));
+    let health = Display::diagnose(&mut epd).unwrap();
+    assert_eq!(health.len(), 4);
+    for health in &health[..3] {
+        assert!(health.healthy(), "{health:?}");
+        assert_eq!(health.status, Some(0x01));
+        assert_eq!(health.revision, Some([0x0A, 0x01]));
+        // In simulated time, however long the test takes.
+        assert_eq!(health.response_time, Some(Duration::from_millis(40)));
+    }
+    let s2 = &health[3];
+    assert_eq!(s2.controller, Some(Controller::S2));
+    assert!(!s2.idle && !s2.healthy());
+    assert_eq!(s2.status, Some(0xFF));
+    assert_eq!(s2.response_time, None);
+
+    // Dead controllers don't stall for the whole refresh timeout.
+    let mut epd = epd_12in48b::Epd::with_interface(
+        Simulator::epd_12in48b().with_disconnected(Controller::S2),
+    );
+    Display::diagnose(&mut epd).unwrap();
+    assert!(epd.interface().elapsed() < Duration::from_secs(5));
+
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b());
+    let health = epd.diagnose().unwrap();
+    assert!(health.healthy());
+    assert_eq!(health.controller, None);
+    // The panel works again once initialized.
+    epd.init().unwrap();
+    epd.clear().unwrap();
+    assert_eq!(epd.interface().refreshes(), 1);
+}
@@ -0,0 +1,221 @@
+use std::time::Duration;
+use waveshare_epd::{
+    epd_12in48b, epd_2in7b,
+    interface::Controller,
+    mock::Mock,
+    sim::Simulator,
+    trace::{self, Entry, Recorder, Trace},
+    Display,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+fn frame() -> (Vec<u8>, Vec<u8>) {
+    let black: Vec<u8> = (0..epd_2in7b::EPD_WIDTH * epd_2in7b::EPD_HEIGHT / 8)
+        .map(|i| i as u8)
+        .collect();
+    let red = vec![0xF0; black.len()];
+    (black, red)
+}
+
+fn record(payloads: bool) -> Trace {
+    let (black, red) = frame();
+    let mut epd =
+        epd_2in7b::Epd::with_interface(Recorder::new(Mock::new()).with_payloads(payloads));
+    epd.init().unwrap();
+    Display::write_frame(&mut epd, &black, &red).unwrap();
+    Display::refresh(&mut epd).unwrap();
+    epd.sleep().unwrap();
+    epd.interface().trace().clone()
+}
+
+#[test]
+fn record_mock() {
+    let trace = record(false);
+    let (black, _) = frame();
+    assert_eq!(trace.entries[0], Entry::Reset(true));
+    assert!(trace.entries.contains(&Entry::Data {
+        to: vec![],
+        len: black.len(),
+        hash: trace::hash(&black),
+        payload: None,
+    }));
+    assert!(trace.entries.iter().any(|entry| matches!(
+        entry,
+        Entry::Wait {
+            idle_high: true,
+            timed_out: false,
+            ..
+        }
+    )));
+    assert!(!trace.is_cascade());
+}
+
+#[test]
+fn round_trip() {
+    let mut trace = record(true);
+    trace.entries.extend([
+        Entry::Command {
+            to: vec![Controller::M1, Controller::S2],
+            command: 0x12,
+        },
+        Entry::Read {
+            from: Some(Controller::S1),
+            len: 2,
+            hash: trace::hash(&[0x0A, 0x01]),
+        },
+        Entry::Busy {
+            controller: Some(Controller::M2),
+            high: false,
+        },
+        Entry::Wait {
+            to: Controller::ALL.to_vec(),
+            idle_high: true,
+            waited: Duration::from_micros(1234),
+            timed_out: true,
+        },
+    ]);
+    let text = trace.to_string();
+    assert!(text.starts_with("# waveshare-epd trace\n"));
+    assert!(text.contains("\ncommand M1+S2 12\n"));
+    assert_eq!(Trace::parse(&text).unwrap(), trace);
+
+    assert!(Trace::parse("command 1234").is_err());
+    assert!(Trace::parse("data 2 0000000000000000 abc").is_err());
+}
+
+#[test]
+fn replay() {
+    let (black, red) = frame();
+    let mut epd = epd_2in7b::Epd::with_interface(Simulator::epd_2in7b());
+    epd.init().unwrap();
+    Display::write_frame(&mut epd, &black, &red).unwrap();
+    Display::refresh(&mut epd).unwrap();
+
+    let mut sim = Simulator::epd_2in7b();
+    record(true).replay(&mut sim, TIMEOUT).unwrap();
+    assert_eq!(sim.refreshes(), 1);
+    assert_eq!(sim.frame(), epd.interface().frame());
+
+    // Hashes alone can't be replayed.
+    let mut sim = Simulator::epd_2in7b();
+    assert!(record(false).replay(&mut sim, TIMEOUT).is_err());
+}
+
+#[test]
+fn replay_cascade() {
+    let mut epd = epd_12in48b::Epd::with_interface(Recorder::new(Mock::new()).with_payloads(true));
+    epd.init().unwrap();
+    epd.clear().unwrap();
+    epd.refresh().unwrap();
+    let trace = epd.interface().trace().clone();
+    assert!(trace.is_cascade());
+
+    let mut sim = Simulator::epd_12in48b();
+    trace.replay_cascade(&mut sim, TIMEOUT).unwrap();
+    assert_eq!(sim.refreshes(), 1);
+}
+
+#[test]
+fn output() {
+    let path = std::env::temp_dir().join("waveshare-epd-trace-output.txt");
+    let mut epd = epd_2in7b::Epd::with_interface(
+        Recorder::new(Simulator::epd_2in7b())
+            .with_output(&path)
+            .unwrap(),
+    );
+    epd.init().unwrap();
+    epd.clear().unwrap();
+    let written = Trace::load(&path).unwrap();
+    assert_eq!(&written, epd.interface().trace());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn diff() {
+    let left = record(false);
+    assert!(trace::diff(&left, &record(false)).is_empty());
+
+    let mut right = left.clone();
+    let index = right
+        .entries
+        .iter()
+        .position(|entry| matches!(entry, Entry::Data { len, .. } if *len > 1))
+        .unwrap();
+    if let Entry::Data { hash, .. } = &mut right.entries[index] {
+        *hash ^= 1;
+    }
+    right.entries.push(Entry::Delay(Duration::from_millis(10)));
+    // Timings don't matter.
+    for entry in &mut right.entries {
+        if let Entry::Wait { waited, .. } = entry {
+            *waited += Duration::from_secs(1);
+        }
+    }
+
+    let differences = trace::diff(&left, &right);
+    assert_eq!(differences.len(), 2);
+    assert_eq!(differences[0].index, index);
+    assert_eq!(differences[0].left, Some(&left.entries[index]));
+    assert_eq!(differences[1].left, None);
+    assert_eq!(
+        differences[1].right,
+        Some(&Entry::Delay(Duration::from_millis(10)))
+    );
+}
+
+#[test]
+fn diff_aligns() {
+    let left = record(false);
+    let mut right = left.clone();
+    // A command too many only reports that command, not everything after it.
+    let extra = Entry::Command {
+        to: vec![],
+        command: 0x71,
+    };
+    right.entries.insert(3, extra.clone());
+    let differences = trace::diff(&left, &right);
+    assert_eq!(
+        differences,
+        [trace::Difference {
+            index: 3,
+            left: None,
+            right: Some(&extra),
+        }]
+    );
+
+    // And one too few, that one.
+    let differences = trace::diff(&right, &left);
+    assert_eq!(differences.len(), 1);
+    assert_eq!(differences[0].index, 3);
+    assert_eq!(differences[0].left, Some(&extra));
+    assert_eq!(differences[0].right, None);
+}
+
+#[test]
+fn diff_reads() {
+    let read = |hash| Entry::Read {
+        from: None,
+        len: 2,
+        hash,
+    };
+    let left = Trace {
+        entries: vec![read(1)],
+    };
+    // What the controller answered doesn't matter, how much was read does.
+    assert!(trace::diff(
+        &left,
+        &Trace {
+            entries: vec![read(2)]
+        }
+    )
+    .is_empty());
+    let longer = Trace {
+        entries: vec![Entry::Read {
+            from: None,
+            len: 3,
+            hash: 1,
+        }],
+    };
+    assert_eq!(trace::diff(&left, &longer).len(), 1);
+}
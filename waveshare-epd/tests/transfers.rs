@@ -0,0 +1,42 @@
+//! Calls to the backend each operation of the 2.7" driver takes, counted with
+//! the mock backend. How backends split them into syscalls is tested with
+//! each backend.
+
+use waveshare_epd::{
+    epd_2in7b::{Epd, Window, EPD_HEIGHT, EPD_WIDTH},
+    interface::Controller,
+    mock::Mock,
+    Display,
+};
+
+const FRAME_BYTES: usize = EPD_WIDTH * EPD_HEIGHT / 8;
+
+#[test]
+fn write_frame() {
+    let mut epd = Epd::with_interface(Mock::new());
+    let black: Vec<u8> = (0..FRAME_BYTES).map(|i| i as u8).collect();
+    let red = vec![0x0F; FRAME_BYTES];
+    epd.write_frame(&black, &red).unwrap();
+
+    // One per command, and one per plane.
+    let mock = epd.interface();
+    assert_eq!(mock.transfers(), 4);
+    assert_eq!(mock.data(Controller::M1, 0x10), black);
+    assert_eq!(mock.data(Controller::M1, 0x13), vec![0xF0; FRAME_BYTES]);
+}
+
+#[test]
+fn write_window() {
+    let mut epd = Epd::with_interface(Mock::new());
+    let window = Window {
+        x: 8,
+        y: 8,
+        width: 64,
+        height: 64,
+    };
+    let plane = vec![0xAA; window.len()];
+    epd.write_window(&window, &plane, &plane).unwrap();
+
+    // One per command, parameters and plane.
+    assert_eq!(epd.interface().transfers(), 6);
+}